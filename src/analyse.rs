@@ -1,28 +1,40 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
-use std::io::ErrorKind;
-use std::path::PathBuf;
-use std::sync::mpsc::Sender;
+use std::io::{self, ErrorKind, Write};
+use std::panic::AssertUnwindSafe;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{Receiver, Sender};
+use std::sync::{Arc, Barrier, Mutex, OnceLock, RwLock};
 use std::time::Instant;
 
 use bumpalo::Bump;
 use colored::Colorize;
 use indicatif::ProgressBar;
 use jwalk::WalkDir;
-use mago_syntax::ast::Statement;
+use mago_syntax::ast::Program;
+use serde_json::Value;
 
-use crate::config::Config;
+use crate::cache::{self, Cache, CachedFile};
+use crate::changes::{Change, ChangedFiles};
+use crate::config::{Config, PathFilter};
 use crate::debug_stats::{FileTimings, RuleTimings};
 use crate::file::File;
+use crate::outputs::checkstyle::Checkstyle;
 use crate::outputs::codeclimate::CodeClimate;
+use crate::outputs::github::GitHub;
+use crate::outputs::html::Html;
 use crate::outputs::json::Json;
+use crate::outputs::junit::JUnit;
 use crate::outputs::sarif::Sarif;
 use crate::outputs::text::Text;
-use crate::outputs::Format;
 use crate::outputs::OutputFormatter;
-use crate::results::{Results, Violation};
+use crate::outputs::{Format, Report, RuleInfo};
+use crate::results::{self, ParseError, Results, Severity, Violation};
+use crate::rules::visitor::{Traversal, Visitor};
 use crate::rules::Rule;
 use crate::rules::{self};
+use crate::suppression::Suppressions;
+use crate::symbols::{self, FileSymbols, SymbolTable};
 
 /// Print a verbose line. When a progress bar is active, route it through
 /// `ProgressBar::println` so the bar stays pinned to the bottom and the line
@@ -39,7 +51,8 @@ fn log_line(bar: Option<&ProgressBar>, msg: String) {
 
 pub fn scan_folder(
     current_dir: PathBuf,
-    sender: Sender<(String, PathBuf)>,
+    sender: Sender<(io::Result<String>, PathBuf)>,
+    filter: PathFilter,
     verbose: u8,
     bar: Option<ProgressBar>,
 ) {
@@ -56,44 +69,202 @@ pub fn scan_folder(
         };
         if (file_name != "." || !file_name.is_empty()) && metadata.is_file() {
             if let Some(extension) = path.extension() {
-                if extension == "php" {
+                if extension == "php" && filter.is_match(&path) {
                     if verbose >= 2 {
                         log_line(bar.as_ref(), format!("[vv] reading {}", path.display()));
                     }
                     let content = fs::read_to_string(entry.path());
-                    match content {
-                        Err(_) => {
-                            // println!("{err:?}");
-                        }
-                        Ok(content) => {
-                            sender.send((content, path)).unwrap();
-                        }
-                    }
+                    sender.send((content, path)).unwrap();
                 }
             }
         }
     }
 }
 
+/// State shared by the analysis threads of a single scan.
+struct Worker<'a> {
+    files: Mutex<Receiver<(io::Result<String>, PathBuf)>>,
+    /// Reached by every thread once its files are indexed.
+    indexed: Barrier,
+    bar: Option<&'a ProgressBar>,
+    verbose: u8,
+    collect_rule_metrics: bool,
+    /// The cache of the previous scan, when caching is enabled.
+    cache: Option<&'a Cache>,
+    /// Hashes of the previous scan's complete indexes, by rule code.
+    previous_index_hashes: Option<&'a HashMap<String, u64>>,
+    /// Rule code → file path → hash of the file's index entry.
+    entry_hashes: Mutex<HashMap<String, BTreeMap<String, u64>>>,
+    index_hashes: OnceLock<HashMap<String, u64>>,
+    /// Restricts the main pass to the changed files, when set.
+    changes: Option<&'a ChangedFiles>,
+}
+
+impl Worker<'_> {
+    /// Hash of every rule's complete index. Only valid once all the threads
+    /// have reached the `indexed` barrier.
+    fn index_hashes(&self) -> &HashMap<String, u64> {
+        self.index_hashes.get_or_init(|| {
+            self.entry_hashes
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(code, entries)| (code.clone(), cache::hash_index(entries)))
+                .collect()
+        })
+    }
+
+    /// Rules whose index changed since the previous scan, so their cached
+    /// violations can't be reused even for unchanged files.
+    fn stale_codes(&self) -> HashSet<String> {
+        let current = self.index_hashes();
+        let previous = match self.previous_index_hashes {
+            Some(previous) => previous,
+            None => return current.keys().cloned().collect(),
+        };
+
+        current
+            .iter()
+            .filter(|(code, hash)| previous.get(*code) != Some(hash))
+            .map(|(code, _)| code.clone())
+            .chain(
+                previous
+                    .keys()
+                    .filter(|code| !current.contains_key(*code))
+                    .cloned(),
+            )
+            .collect()
+    }
+}
+
+/// A file pulled from the queue by a worker during the pre-pass.
+enum ScannedFile<'a> {
+    Parsed {
+        file: File<'a>,
+        content_hash: u64,
+        index: HashMap<String, Value>,
+    },
+    /// Unchanged since the previous scan, so its analysis is read from the
+    /// cache and it is parsed only if a cross-file rule has to validate it
+    /// again.
+    Cached {
+        path: PathBuf,
+        content: String,
+        cached: CachedFile,
+    },
+    /// Couldn't be read, e.g. not valid UTF-8.
+    Unreadable { path: PathBuf, error: io::Error },
+}
+
+impl ScannedFile<'_> {
+    fn path(&self) -> &PathBuf {
+        match self {
+            ScannedFile::Parsed { file, .. } => &file.path,
+            ScannedFile::Cached { path, .. } => path,
+            ScannedFile::Unreadable { path, .. } => path,
+        }
+    }
+}
+
 pub struct Analyse {
-    pub(crate) rules: HashMap<String, Box<dyn Rule>>,
+    pub(crate) rules: BTreeMap<String, Box<dyn Rule>>,
+    /// Per-rule `include_paths`/`exclude_paths`, keyed by rule code.
+    rule_paths: HashMap<String, PathFilter>,
+    /// Severity of the violations of each rule, keyed by rule code.
+    severities: HashMap<String, Severity>,
+    /// The symbols declared by the indexed files, shared by all the rules.
+    symbols: RwLock<Arc<SymbolTable>>,
 }
 
 impl Analyse {
     pub fn new(config: &Config) -> Self {
+        let rules = Self::get_active_rules(config);
+        let rule_paths = rules
+            .keys()
+            .filter_map(|code| Some((code.clone(), config.rule_path_filter(code)?)))
+            .collect();
+        let severities = rules
+            .iter()
+            .map(|(code, rule)| {
+                let severity = config.rule_severity(code).unwrap_or(rule.severity());
+                (code.clone(), severity)
+            })
+            .collect();
+
         Self {
-            rules: Self::get_active_rules(config),
+            rules,
+            rule_paths,
+            severities,
+            symbols: RwLock::default(),
+        }
+    }
+
+    /// Index the file for the symbol table and for every rule. Returns the
+    /// file's index entries by rule code, with the symbols under
+    /// `symbols::INDEX_KEY`, so they can be cached.
+    pub(crate) fn index_file(&self, file: &File<'_>) -> HashMap<String, Value> {
+        let entries = self.index_entries(file);
+        for (code, entry) in &entries {
+            self.load_index_entry(code, entry);
+        }
+        entries
+    }
+
+    /// The file's index entries, without merging them into the indexes.
+    pub(crate) fn index_entries(&self, file: &File<'_>) -> HashMap<String, Value> {
+        let mut entries: HashMap<String, Value> = self
+            .rules
+            .iter()
+            .filter_map(|(code, rule)| Some((code.clone(), rule.index_entry(file)?)))
+            .collect();
+        if let Some(program) = file.ast {
+            if let Ok(entry) = serde_json::to_value(FileSymbols::collect(program)) {
+                entries.insert(symbols::INDEX_KEY.to_string(), entry);
+            }
         }
+        entries
     }
 
+    /// Merge an entry returned by `index_entries` into the symbol table or
+    /// into the index of its rule.
+    pub(crate) fn load_index_entry(&self, code: &str, entry: &Value) {
+        if code == symbols::INDEX_KEY {
+            if let Ok(file_symbols) = serde_json::from_value(entry.to_owned()) {
+                if let Ok(mut table) = self.symbols.write() {
+                    Arc::make_mut(&mut table).merge(file_symbols);
+                }
+            }
+        } else if let Some(rule) = self.rules.get(code) {
+            rule.load_index_entry(entry);
+        }
+    }
+
+    /// Remove an entry loaded by `load_index_entry` from the symbol table or
+    /// from the index of its rule.
+    pub(crate) fn unload_index_entry(&self, code: &str, entry: &Value) {
+        if code == symbols::INDEX_KEY {
+            if let Ok(file_symbols) = serde_json::from_value(entry.to_owned()) {
+                if let Ok(mut table) = self.symbols.write() {
+                    Arc::make_mut(&mut table).remove(file_symbols);
+                }
+            }
+        } else if let Some(rule) = self.rules.get(code) {
+            rule.unload_index_entry(entry);
+        }
+    }
+
+    #[allow(clippy::too_many_arguments)]
     pub(crate) fn scan(
         &self,
         path: String,
-        _config: &Config,
+        config: &Config,
         show_bar: bool,
         format: &Format,
         verbose: u8,
         collect_rule_metrics: bool,
+        jobs: usize,
+        cache: Option<&mut Cache>,
+        changes: Option<&ChangedFiles>,
     ) -> Results {
         let now = std::time::Instant::now();
         let mut results = Results::default();
@@ -117,70 +288,295 @@ impl Analyse {
         };
 
         let scan_path = path.clone();
+        let filter = config.path_filter();
         std::thread::spawn(move || {
             let path = PathBuf::from(scan_path);
-            self::scan_folder(path, send, verbose, thread_bar);
+            self::scan_folder(path, send, filter, verbose, thread_bar);
         });
 
-        let arena = Bump::new();
+        let jobs = if jobs == 0 {
+            std::thread::available_parallelism().map_or(1, |n| n.get())
+        } else {
+            jobs
+        };
+        let worker = Worker {
+            files: Mutex::new(recv),
+            indexed: Barrier::new(jobs),
+            bar: bar_active.then_some(&progress_bar),
+            verbose,
+            collect_rule_metrics,
+            cache: cache.as_deref(),
+            previous_index_hashes: cache.as_deref().and_then(|c| c.index_hashes.get(&path)),
+            entry_hashes: Mutex::new(HashMap::new()),
+            index_hashes: OnceLock::new(),
+            changes,
+        };
 
-        // 1. Collect all files
-        let mut scanned_files: Vec<File<'_>> = Vec::new();
-        for (content, path) in recv {
-            if verbose >= 2 {
-                log_line(
-                    bar_active.then_some(&progress_bar),
-                    format!("[vv] parsing {}", path.display()),
-                );
+        let mut cached_files = vec![];
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = (0..jobs)
+                .map(|_| scope.spawn(|| self.run_worker(&worker)))
+                .collect();
+            for handle in handles {
+                match handle.join() {
+                    Ok((worker_results, worker_cached_files)) => {
+                        results.merge(worker_results);
+                        cached_files.extend(worker_cached_files);
+                    }
+                    Err(e) => std::panic::resume_unwind(e),
+                }
             }
-            scanned_files.push(File::new(&arena, path, content));
+        });
+
+        let index_hashes = worker.index_hashes().clone();
+        drop(worker);
+        if let Some(cache) = cache {
+            cache.update(&path, cached_files, index_hashes);
         }
 
-        // 2. Pre-pass (indexing).
-        for file in &scanned_files {
-            if verbose >= 3 {
-                log_line(
-                    bar_active.then_some(&progress_bar),
-                    format!("[vvv] indexing {}", file.path.display()),
-                );
+        if bar_active {
+            progress_bar.finish();
+        }
+
+        results.duration = Some(now.elapsed());
+
+        results
+    }
+
+    /// Body of one analysis thread. Every thread parses the files it pulls
+    /// from the shared queue into its own arena and indexes them right away.
+    /// Once all the threads are done with indexing, each one validates the
+    /// files it has parsed, so cross-file rules see a complete index. Files
+    /// outside `worker.changes` are indexed but not validated.
+    ///
+    /// With the cache enabled, unchanged files are indexed from their cached
+    /// entries instead, and the analysis of every file is returned so it can
+    /// be cached for the next scan.
+    fn run_worker(&self, worker: &Worker<'_>) -> (Results, Vec<(String, CachedFile)>) {
+        let arena = Bump::new();
+        let mut results = Results::default();
+        if worker.collect_rule_metrics {
+            results.rule_timings = Some(RuleTimings::default());
+        }
+
+        // 1. Parse and index (pre-pass). A panic must not leave the other
+        // threads waiting on the barrier forever, so it is re-raised only
+        // after the barrier.
+        let parsed = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut scanned_files: Vec<ScannedFile<'_>> = Vec::new();
+            let mut entry_hashes: HashMap<String, BTreeMap<String, u64>> = HashMap::new();
+            loop {
+                let next = worker.files.lock().unwrap().recv();
+                let (content, path) = match next {
+                    Ok(file) => file,
+                    Err(_) => break,
+                };
+                let file_path = path.display().to_string();
+                let content = match content {
+                    Ok(content) => content,
+                    Err(error) => {
+                        scanned_files.push(ScannedFile::Unreadable { path, error });
+                        continue;
+                    }
+                };
+
+                let content_hash = cache::hash_content(&content);
+                let cached = worker
+                    .cache
+                    .and_then(|c| c.files.get(&file_path))
+                    .filter(|cached| cached.content_hash == content_hash);
+
+                let scanned = if let Some(cached) = cached {
+                    if worker.verbose >= 3 {
+                        log_line(
+                            worker.bar,
+                            format!("[vvv] indexing {} from cache", file_path),
+                        );
+                    }
+                    for (code, entry) in &cached.index {
+                        self.load_index_entry(code, entry);
+                    }
+                    ScannedFile::Cached {
+                        path,
+                        content,
+                        cached: cached.clone(),
+                    }
+                } else {
+                    if worker.verbose >= 2 {
+                        log_line(worker.bar, format!("[vv] parsing {}", file_path));
+                    }
+                    let file = File::new(&arena, path, content);
+
+                    if worker.verbose >= 3 {
+                        log_line(worker.bar, format!("[vvv] indexing {}", file_path));
+                    }
+                    let index = self.index_file(&file);
+                    ScannedFile::Parsed {
+                        file,
+                        content_hash,
+                        index,
+                    }
+                };
+
+                if worker.cache.is_some() {
+                    let index = match &scanned {
+                        ScannedFile::Parsed { index, .. } => index,
+                        ScannedFile::Cached { cached, .. } => &cached.index,
+                        ScannedFile::Unreadable { .. } => unreachable!(),
+                    };
+                    for (code, entry) in index {
+                        entry_hashes
+                            .entry(code.clone())
+                            .or_default()
+                            .insert(file_path.clone(), cache::hash_index_entry(entry));
+                    }
+                }
+                scanned_files.push(scanned);
             }
-            for rule in self.rules.values() {
-                rule.index_file(file);
+
+            let mut shared_hashes = worker.entry_hashes.lock().unwrap();
+            for (code, entries) in entry_hashes {
+                shared_hashes.entry(code).or_default().extend(entries);
             }
+            scanned_files
+        }));
+        worker.indexed.wait();
+        let scanned_files = match parsed {
+            Ok(scanned_files) => scanned_files,
+            Err(e) => std::panic::resume_unwind(e),
+        };
+        let mut stale_codes = if worker.cache.is_some() {
+            worker.stale_codes()
+        } else {
+            HashSet::new()
+        };
+        if stale_codes.contains(symbols::INDEX_KEY) {
+            stale_codes.extend(
+                self.rules
+                    .iter()
+                    .filter(|(_, rule)| rule.uses_symbols())
+                    .map(|(code, _)| code.clone()),
+            );
         }
 
-        // 3. Main pass.
-        let mut files = 0;
-        for mut file in scanned_files {
-            if verbose >= 1 {
-                log_line(
-                    bar_active.then_some(&progress_bar),
-                    format!("[v] analysing {}", file.path.display()),
-                );
+        // 2. Main pass.
+        let mut cached_files = vec![];
+        for scanned in scanned_files {
+            let change = worker
+                .changes
+                .map_or(Change::File, |changes| changes.get(scanned.path()));
+            if change == Change::Unchanged {
+                if let Some(bar) = worker.bar {
+                    bar.inc(1);
+                }
+                // Not validated, so the entry of the previous run stays valid
+                let path = scanned.path().display().to_string();
+                if let Some(previous) = worker.cache.and_then(|cache| cache.files.get(&path)) {
+                    cached_files.push((path, previous.clone()));
+                }
+                continue;
             }
-            if bar_active {
-                progress_bar.inc(1);
+
+            let (mut file, content_hash, index, cached_violations, mut suppressions) = match scanned
+            {
+                ScannedFile::Parsed {
+                    file,
+                    content_hash,
+                    index,
+                } => {
+                    let suppressions =
+                        Suppressions::from_file(&file, &|code| self.rules.contains_key(code));
+                    (file, content_hash, index, None, suppressions)
+                }
+                ScannedFile::Cached {
+                    path,
+                    content,
+                    cached,
+                } => {
+                    // Unused suppressions are reported with the symbol
+                    // around them, which needs the AST
+                    let file = if stale_codes.is_empty() && cached.suppressions.is_empty() {
+                        let mut file = File::unparsed(path, &content);
+                        file.parse_errors = cached.parse_errors;
+                        file
+                    } else {
+                        if worker.verbose >= 2 {
+                            log_line(worker.bar, format!("[vv] parsing {}", path.display()));
+                        }
+                        File::new(&arena, path, content)
+                    };
+                    (
+                        file,
+                        cached.content_hash,
+                        cached.index,
+                        Some(cached.violations),
+                        cached.suppressions,
+                    )
+                }
+                ScannedFile::Unreadable { path, error } => {
+                    if let Some(bar) = worker.bar {
+                        bar.inc(1);
+                    }
+                    results.add_parse_errors(
+                        path.display().to_string(),
+                        vec![ParseError::unreadable(&error)],
+                    );
+                    results.total_files_count += 1;
+                    continue;
+                }
+            };
+
+            if worker.verbose >= 1 {
+                log_line(worker.bar, format!("[v] analysing {}", file.path.display()));
+            }
+            if let Some(bar) = worker.bar {
+                bar.inc(1);
             }
 
-            let (violations, file_timings) = self.analyse_file(&mut file, collect_rule_metrics);
+            let (violations, file_timings) = match cached_violations {
+                None => self.validate_file(&mut file, None, worker.collect_rule_metrics),
+                Some(cached_violations) => {
+                    // Only the rules with a changed index validate the file again
+                    let mut violations: Vec<Violation> = cached_violations
+                        .into_iter()
+                        .filter(|violation| !stale_codes.contains(&violation.rule))
+                        .collect();
+                    let (mut fresh, file_timings) = self.validate_file(
+                        &mut file,
+                        Some(&stale_codes),
+                        worker.collect_rule_metrics,
+                    );
+                    violations.append(&mut fresh);
+                    (violations, file_timings)
+                }
+            };
             let file_path = file.path.display().to_string();
+            if worker.cache.is_some() {
+                cached_files.push((
+                    file_path.clone(),
+                    CachedFile {
+                        content_hash,
+                        index,
+                        violations: violations.clone(),
+                        suppressions: suppressions.clone(),
+                        parse_errors: file.parse_errors.clone(),
+                    },
+                ));
+            }
+
+            let mut violations = self.suppress(&file, &mut suppressions, violations);
+            violations.retain(|violation| change.contains(violation));
             results.add_file_violations(&file, violations);
+            results.add_parse_errors(file_path.clone(), file.parse_errors.clone());
 
             if let (Some(rt), Some(ft)) = (results.rule_timings.as_mut(), file_timings) {
                 rt.merge_file(file_path, ft);
             }
 
-            files += 1;
+            results.total_files_count += 1;
         }
 
-        if bar_active {
-            progress_bar.finish();
-        }
-
-        results.total_files_count = files;
-        results.duration = Some(now.elapsed());
-
-        results
+        (results, cached_files)
     }
 
     pub(crate) fn parse_config(config_path: String, output_format: &Format, quiet: bool) -> Config {
@@ -226,6 +622,12 @@ impl Analyse {
                         for (code, settings) in default.rules {
                             c.rules.entry(code).or_insert(settings);
                         }
+                        let dir = path.parent().unwrap_or(Path::new("."));
+                        c.resolve_plugin_paths(dir);
+                        // On stderr, so the JSON-based outputs remain valid
+                        for e in c.load_custom_rule_files(dir) {
+                            eprintln!("{}", e.red().bold());
+                        }
                         c
                     }
                     Err(e) => {
@@ -239,10 +641,16 @@ impl Analyse {
         }
     }
 
-    #[allow(dead_code)]
-    pub(crate) fn output(&mut self, results: &mut Results, format: Format, summary_only: bool) {
+    /// Render the results in every report, each to its own file or to the
+    /// standard output.
+    pub fn output(
+        &mut self,
+        results: &mut Results,
+        reports: &[Report],
+        summary_only: bool,
+    ) -> io::Result<()> {
         if summary_only {
-            results.files = HashMap::new();
+            results.files = BTreeMap::new();
         };
 
         for (path, violations) in results.files.clone() {
@@ -251,18 +659,94 @@ impl Analyse {
             }
         }
 
+        let rules = self.rule_infos();
+        for report in reports {
+            match &report.path {
+                None => Self::output_report(
+                    &mut io::stdout().lock(),
+                    &report.format,
+                    results,
+                    &rules,
+                    true,
+                )?,
+                Some(path) => {
+                    let written = fs::File::create(path).and_then(|file| {
+                        let mut out = io::BufWriter::new(file);
+                        // Colors are for the terminal, not for report files
+                        Self::output_report(&mut out, &report.format, results, &rules, false)
+                            .and_then(|_| out.flush())
+                    });
+                    if let Err(e) = written {
+                        return Err(io::Error::new(
+                            e.kind(),
+                            format!("Unable to write the report {}: {}", path.display(), e),
+                        ));
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn output_report(
+        out: &mut dyn Write,
+        format: &Format,
+        results: &Results,
+        rules: &BTreeMap<String, RuleInfo>,
+        colors: bool,
+    ) -> io::Result<()> {
         match format {
-            Format::json => Json::output(results),
-            Format::sarif => Sarif::output(results),
-            Format::codeclimate => CodeClimate::output(results),
-            _ => Text::output(results),
-        };
+            Format::json => Json::output(out, results, rules),
+            Format::sarif => Sarif::output(out, results, rules),
+            Format::codeclimate => CodeClimate::output(out, results, rules),
+            Format::html => Html::output(out, results, rules),
+            Format::checkstyle => Checkstyle::output(out, results, rules),
+            Format::junit => JUnit::output(out, results, rules),
+            Format::github => GitHub::output(out, results, rules),
+            Format::text => Text::output_with_colors(out, results, rules, colors),
+        }
+    }
+
+    /// What the reports tell about the rules of the analysis.
+    fn rule_infos(&self) -> BTreeMap<String, RuleInfo> {
+        let built_in_codes = rules::all_rules();
+        self.rules
+            .iter()
+            .map(|(code, rule)| {
+                let info = RuleInfo {
+                    description: rule.description(),
+                    explanation: rule.get_detailed_explanation(),
+                    severity: self
+                        .severities
+                        .get(code)
+                        .copied()
+                        .unwrap_or(rule.severity()),
+                    built_in: built_in_codes.contains_key(code),
+                };
+                (code.clone(), info)
+            })
+            .collect()
     }
 
     pub(crate) fn analyse_file(
         &self,
         file: &mut File<'_>,
         collect_rule_metrics: bool,
+    ) -> (Vec<Violation>, Option<FileTimings>) {
+        let (violations, timings) = self.validate_file(file, None, collect_rule_metrics);
+        let mut suppressions = Suppressions::from_file(file, &|code| self.rules.contains_key(code));
+
+        (self.suppress(file, &mut suppressions, violations), timings)
+    }
+
+    /// Validate the file with every rule, or only with the rules in `codes`.
+    /// Suppression comments aren't applied yet.
+    fn validate_file(
+        &self,
+        file: &mut File<'_>,
+        codes: Option<&HashSet<String>>,
+        collect_rule_metrics: bool,
     ) -> (Vec<Violation>, Option<FileTimings>) {
         let mut violations: Vec<Violation> = vec![];
         let mut timings = if collect_rule_metrics {
@@ -271,27 +755,157 @@ impl Analyse {
             None
         };
 
+        file.symbols = self
+            .symbols
+            .read()
+            .map(|table| Arc::clone(&table))
+            .unwrap_or_default();
         if let Some(program) = file.ast {
             file.reference_counter.build_reference_counter(program);
-            for statement in program.statements.iter() {
-                violations.append(&mut self.analyse_file_statement(
-                    file,
-                    statement,
-                    timings.as_mut(),
-                ));
-            }
+            violations = self.validate_program(file, program, codes, timings.as_mut());
         }
+
         (violations, timings)
     }
 
-    fn get_active_rules(config: &Config) -> HashMap<String, Box<dyn Rule>> {
+    /// Validates the statements of the program with the rules which don't
+    /// provide a visitor, then walks the program once for all the others.
+    fn validate_program<'a>(
+        &'a self,
+        file: &'a File<'a>,
+        program: &'a Program<'a>,
+        codes: Option<&HashSet<String>>,
+        mut timings: Option<&mut FileTimings>,
+    ) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let mut visitors: Vec<Box<dyn Visitor<'a> + 'a>> = Vec::new();
+        let mut visitor_codes = Vec::new();
+
+        let rules = self
+            .rules
+            .values()
+            .filter(|rule| codes.is_none_or(|codes| codes.contains(&rule.get_code())));
+        for rule in rules {
+            let rule_start = timings.as_ref().map(|_| Instant::now());
+
+            let code = rule.get_code();
+            let validated = rule.do_validate(file) && self.is_rule_path(&code, file);
+            let mut stmt_count = 0;
+            if validated {
+                match rule.visitor(file) {
+                    Some(visitor) => {
+                        visitors.push(visitor);
+                        visitor_codes.push(code.clone());
+                    }
+                    None => {
+                        for statement in program.statements.iter() {
+                            let flat = rule.flatten_statements_to_validate(statement);
+                            stmt_count += flat.len();
+                            for statement in flat {
+                                violations.append(&mut rule.validate(file, statement));
+                            }
+                        }
+                    }
+                }
+            }
+
+            if let Some(t) = timings.as_deref_mut() {
+                let elapsed = rule_start.unwrap().elapsed();
+                let entry = t.entry(code).or_default();
+                entry.duration += elapsed;
+                entry.validated |= validated;
+                entry.statements += stmt_count;
+            }
+        }
+
+        if visitors.is_empty() {
+            return violations;
+        }
+
+        let mut traversal = Traversal::new(&mut visitors, timings.is_some());
+        traversal.walk(program);
+        let statements = traversal.statements;
+        let durations = traversal.durations.take();
+
+        for (index, (visitor, code)) in visitors.into_iter().zip(visitor_codes).enumerate() {
+            let finish_start = Instant::now();
+            violations.append(&mut visitor.finish());
+
+            if let (Some(t), Some(durations)) = (timings.as_deref_mut(), &durations) {
+                let entry = t.entry(code).or_default();
+                entry.duration += durations[index] + finish_start.elapsed();
+                entry.statements += statements;
+            }
+        }
+
+        violations
+    }
+
+    /// Drop the suppressed violations, report the unused suppressions, set
+    /// the configured severities and make the fingerprints unique.
+    fn suppress(
+        &self,
+        file: &File<'_>,
+        suppressions: &mut Suppressions,
+        violations: Vec<Violation>,
+    ) -> Vec<Violation> {
+        let mut violations = suppressions.filter(violations);
+        if let Some(rule) = self.rules.get(rules::e31::CODE) {
+            let active_codes: Vec<String> = self
+                .rules
+                .keys()
+                .filter(|code| self.is_rule_path(code, file))
+                .cloned()
+                .collect();
+            for (span, suggestion) in suppressions.unused(&active_codes) {
+                violations.push(rule.new_violation(file, suggestion, span));
+            }
+        }
+        for violation in violations.iter_mut() {
+            if let Some(severity) = self.severities.get(&violation.rule) {
+                violation.severity = *severity;
+            }
+        }
+        results::disambiguate_fingerprints(&mut violations);
+
+        violations
+    }
+
+    /// Whether the rule's own path settings allow it to validate the file.
+    fn is_rule_path(&self, code: &str, file: &File<'_>) -> bool {
+        self.rule_paths
+            .get(code)
+            .is_none_or(|filter| filter.is_match(&file.path))
+    }
+
+    fn get_active_rules(config: &Config) -> BTreeMap<String, Box<dyn Rule>> {
+        let mut active_rules = rules::all_rules();
+        for rule in rules::configured_rules(&config.custom_rules, &config.plugins) {
+            // On stderr, so the JSON-based outputs remain valid
+            let rule = match rule {
+                Ok(rule) => rule,
+                Err(e) => {
+                    eprintln!("{}", e.red().bold());
+                    continue;
+                }
+            };
+            let code = rule.get_code();
+            if active_rules.contains_key(&code) {
+                eprintln!(
+                    "{}",
+                    format!("The rule {} is already defined", code).red().bold()
+                );
+                continue;
+            }
+            active_rules.insert(code, rule);
+        }
+
         let active_codes = Self::filter_active_codes(
-            rules::all_rules().into_keys().collect(),
+            active_rules.keys().cloned().collect(),
             &config.enabled_rules,
             &config.disable_rules,
         );
 
-        let mut active_rules = rules::all_rules();
         active_rules.retain(|code, rule| {
             rule.read_config(config);
 
@@ -327,39 +941,6 @@ impl Analyse {
 
         ProgressBar::new(files_count as u64)
     }
-
-    pub fn analyse_file_statement<'a>(
-        &self,
-        file: &File<'a>,
-        statement: &Statement<'a>,
-        mut timings: Option<&mut FileTimings>,
-    ) -> Vec<Violation> {
-        let mut violations = Vec::new();
-
-        for rule in self.rules.values() {
-            let rule_start = timings.as_ref().map(|_| Instant::now());
-
-            let validated = rule.do_validate(file);
-            let mut stmt_count = 0;
-            if validated {
-                let flat = rule.flatten_statements_to_validate(statement);
-                stmt_count = flat.len();
-                for statement in flat {
-                    violations.append(&mut rule.validate(file, statement));
-                }
-            }
-
-            if let Some(t) = timings.as_deref_mut() {
-                let elapsed = rule_start.unwrap().elapsed();
-                let entry = t.entry(rule.get_code()).or_default();
-                entry.duration += elapsed;
-                entry.validated |= validated;
-                entry.statements += stmt_count;
-            }
-        }
-
-        violations
-    }
 }
 
 #[cfg(test)]
@@ -426,4 +1007,66 @@ mod tests {
 
         assert_eq!(vec!["RULE1".to_string()], active_codes);
     }
+
+    #[test]
+    fn test_analyse_file_sets_configured_severity() {
+        let content = "<?php\nnamespace App;\n\nclass Service\n{\n    public function run()\n    {\n        @$this->fetch();\n        try {\n        } catch (\\Exception $e) {\n        }\n    }\n}\n";
+        let mut config = Config {
+            enabled_rules: vec!["E0002".to_string(), "E0011".to_string()],
+            ..Default::default()
+        };
+        config.rules.insert(
+            "E0011".to_string(),
+            serde_json::json!({"severity": "blocker"}),
+        );
+        let analyse = Analyse::new(&config);
+        let arena = bumpalo::Bump::new();
+        let mut file = File::new(&arena, PathBuf::from("Service.php"), content.to_string());
+
+        let violations = analyse.analyse_file(&mut file, false).0;
+        let severity = |code: &str| {
+            violations
+                .iter()
+                .find(|violation| violation.rule == code)
+                .map(|violation| violation.severity)
+        };
+
+        assert_eq!(severity("E0011"), Some(Severity::Blocker));
+        assert_eq!(severity("E0002"), Some(Severity::Major));
+    }
+
+    #[test]
+    fn test_scan_reports_parse_errors_and_unreadable_files() {
+        let dir = std::env::temp_dir().join("phanalist-test-scan-parse-errors");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("Valid.php"), "<?php\necho 1;\n").unwrap();
+        fs::write(dir.join("Broken.php"), "<?php\n\nfunction run( {\n}\n").unwrap();
+        fs::write(dir.join("Latin1.php"), b"<?php\necho '\xe9';\n").unwrap();
+
+        let config = Config::default();
+        let analyse = Analyse::new(&config);
+        let results = analyse.scan(
+            dir.display().to_string(),
+            &config,
+            false,
+            &Format::json,
+            0,
+            false,
+            1,
+            None,
+            None,
+        );
+
+        let errors = |name: &str| {
+            results
+                .parse_errors
+                .get(&dir.join(name).display().to_string())
+        };
+        assert_eq!(results.total_files_count, 3);
+        assert_eq!(errors("Valid.php"), None);
+        assert_eq!(errors("Broken.php").unwrap()[0].start_line, 3);
+        assert_eq!(errors("Latin1.php").unwrap()[0].start_line, 0);
+
+        fs::remove_dir_all(dir).unwrap();
+    }
 }
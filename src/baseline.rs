@@ -0,0 +1,206 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::results::{normalize_line, Results, Violation};
+
+/// One group of identical violations recorded in a baseline file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct BaselineEntry {
+    pub file: String,
+    pub rule: String,
+    /// Source line of the violation with whitespace normalized, so the entry
+    /// still matches after the code around it moves up or down.
+    pub line: String,
+    pub count: usize,
+}
+
+/// Known violations which should not be reported again.
+///
+/// Violations are matched on rule + file + normalized source line instead of
+/// line numbers, so unrelated edits that shift code don't resurrect them.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq)]
+pub struct Baseline {
+    pub entries: Vec<BaselineEntry>,
+}
+
+type Fingerprint = (String, String, String);
+
+impl Baseline {
+    /// Record every violation of `results` in the baseline.
+    pub fn extend(&mut self, results: &Results) {
+        let mut counts = self.counts();
+        for (path, violations) in &results.files {
+            for violation in violations {
+                *counts.entry(fingerprint(path, violation)).or_default() += 1;
+            }
+        }
+
+        let mut entries: Vec<BaselineEntry> = counts
+            .into_iter()
+            .map(|((file, rule, line), count)| BaselineEntry {
+                file,
+                rule,
+                line,
+                count,
+            })
+            .collect();
+        entries.sort_by(|a, b| (&a.file, &a.rule, &a.line).cmp(&(&b.file, &b.rule, &b.line)));
+
+        self.entries = entries;
+    }
+
+    /// Remove from `results` every violation which is already in the baseline.
+    /// Returns the number of suppressed violations.
+    pub fn apply(&self, results: &mut Results) -> usize {
+        let mut remaining = self.counts();
+        let mut suppressed = 0;
+
+        for (path, violations) in results.files.iter_mut() {
+            violations.retain(
+                |violation| match remaining.get_mut(&fingerprint(path, violation)) {
+                    Some(count) if *count > 0 => {
+                        *count -= 1;
+                        suppressed += 1;
+                        false
+                    }
+                    _ => true,
+                },
+            );
+        }
+
+        results.codes_count.clear();
+        for violation in results.files.values().flatten() {
+            *results
+                .codes_count
+                .entry(violation.rule.clone())
+                .or_default() += 1;
+        }
+
+        suppressed
+    }
+
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let content = fs::read_to_string(path)?;
+        serde_json::from_str(&content).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let content = serde_json::to_string_pretty(self).unwrap();
+        fs::write(path, content)
+    }
+
+    fn counts(&self) -> HashMap<Fingerprint, usize> {
+        let mut counts = HashMap::new();
+        for entry in &self.entries {
+            *counts
+                .entry((entry.file.clone(), entry.rule.clone(), entry.line.clone()))
+                .or_default() += entry.count;
+        }
+        counts
+    }
+}
+
+fn fingerprint(path: &str, violation: &Violation) -> Fingerprint {
+    (
+        path.to_string(),
+        violation.rule.clone(),
+        normalize_line(&violation.line),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::Severity;
+
+    fn get_violation(rule: &str, line: &str, start_line: usize) -> Violation {
+        Violation {
+            rule: rule.to_string(),
+            severity: Severity::Minor,
+            symbol: None,
+            fingerprint: String::new(),
+            line: line.to_string(),
+            suggestion: "Suggestion".to_string(),
+            start_line,
+            start_column: 0,
+            end_line: start_line,
+            end_column: 0,
+            edits: vec![],
+            related_locations: vec![],
+        }
+    }
+
+    fn get_baseline(results: &Results) -> Baseline {
+        let mut baseline = Baseline::default();
+        baseline.extend(results);
+        baseline
+    }
+
+    fn get_results(violations: Vec<Violation>) -> Results {
+        let mut results = Results::default();
+        for violation in &violations {
+            *results
+                .codes_count
+                .entry(violation.rule.clone())
+                .or_default() += 1;
+        }
+        results.files.insert("./class.php".to_string(), violations);
+        results
+    }
+
+    #[test]
+    fn test_apply_suppresses_known_violations_after_line_shift() {
+        let baseline = get_baseline(&get_results(vec![get_violation(
+            "E0009",
+            "    public function run() {",
+            10,
+        )]));
+
+        let mut results = get_results(vec![
+            get_violation("E0009", "public function   run() {", 42),
+            get_violation("E0009", "public function other() {", 50),
+        ]);
+        let suppressed = baseline.apply(&mut results);
+
+        assert_eq!(suppressed, 1);
+        assert_eq!(results.files.get("./class.php").unwrap().len(), 1);
+        assert_eq!(results.files.get("./class.php").unwrap()[0].start_line, 50);
+        assert_eq!(results.codes_count.get("E0009"), Some(&1));
+    }
+
+    #[test]
+    fn test_apply_reports_violations_above_baseline_count() {
+        let baseline = get_baseline(&get_results(vec![get_violation("E0011", "@foo();", 3)]));
+
+        let mut results = get_results(vec![
+            get_violation("E0011", "@foo();", 3),
+            get_violation("E0011", "@foo();", 4),
+        ]);
+        baseline.apply(&mut results);
+
+        assert_eq!(results.files.get("./class.php").unwrap().len(), 1);
+        assert!(results.has_violations_at(Severity::Info));
+    }
+
+    #[test]
+    fn test_from_results_groups_identical_violations() {
+        let baseline = get_baseline(&get_results(vec![
+            get_violation("E0011", "@foo();", 3),
+            get_violation("E0011", "@foo();", 4),
+        ]));
+
+        assert_eq!(
+            baseline.entries,
+            vec![BaselineEntry {
+                file: "./class.php".to_string(),
+                rule: "E0011".to_string(),
+                line: "@foo();".to_string(),
+                count: 2,
+            }]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::Config;
+use crate::results::{ParseError, Violation};
+use crate::suppression::Suppressions;
+
+/// Everything the analysis of a single file produced, keyed by its content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedFile {
+    pub content_hash: u64,
+    /// Rule code → the file's entry in the rule's cross-file index, with the
+    /// file's symbols under `symbols::INDEX_KEY`.
+    pub index: HashMap<String, Value>,
+    /// Violations before suppression comments are applied.
+    pub violations: Vec<Violation>,
+    pub suppressions: Suppressions,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub parse_errors: Vec<ParseError>,
+}
+
+/// On-disk cache of per-file results, so unchanged files aren't parsed or
+/// validated again.
+///
+/// Cross-file rules depend on every file, so their violations are reused only
+/// while the hash of their complete index stays the same. When it changes,
+/// unchanged files are validated again by those rules only.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cache {
+    pub version: String,
+    pub config_hash: u64,
+    /// File path → cached analysis.
+    pub files: HashMap<String, CachedFile>,
+    /// Scanned path → rule code → hash of the rule's complete index.
+    pub index_hashes: HashMap<String, HashMap<String, u64>>,
+}
+
+impl Cache {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            config_hash: config_hash(config),
+            files: HashMap::new(),
+            index_hashes: HashMap::new(),
+        }
+    }
+
+    /// Read the cache, starting from scratch when it is missing, unreadable
+    /// or was written by another version or with another configuration.
+    pub fn load(path: &Path, config: &Config) -> Self {
+        let empty = Self::new(config);
+
+        let cache = fs::read_to_string(path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Cache>(&content).ok());
+        match cache {
+            Some(cache)
+                if cache.version == empty.version && cache.config_hash == empty.config_hash =>
+            {
+                cache
+            }
+            _ => empty,
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        let content = serde_json::to_string(self).unwrap();
+        fs::write(path, content)
+    }
+
+    /// Replace the entries of a scanned path with the ones of the latest scan,
+    /// dropping the files which no longer exist.
+    pub fn update(
+        &mut self,
+        scan_path: &str,
+        files: Vec<(String, CachedFile)>,
+        index_hashes: HashMap<String, u64>,
+    ) {
+        self.files
+            .retain(|path, _| !Path::new(path).starts_with(scan_path));
+        self.files.extend(files);
+        self.index_hashes
+            .insert(scan_path.to_string(), index_hashes);
+    }
+}
+
+pub fn hash_content(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Hash of an index entry which doesn't depend on the order of arrays, as
+/// indexes are mostly built from hash sets.
+pub fn hash_index_entry(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    match value {
+        Value::Array(values) => {
+            let mut hashes: Vec<u64> = values.iter().map(hash_index_entry).collect();
+            hashes.sort_unstable();
+            hashes.hash(&mut hasher);
+        }
+        Value::Object(map) => {
+            let sorted: BTreeMap<&String, u64> = map
+                .iter()
+                .map(|(key, value)| (key, hash_index_entry(value)))
+                .collect();
+            sorted.hash(&mut hasher);
+        }
+        _ => value.to_string().hash(&mut hasher),
+    }
+    hasher.finish()
+}
+
+/// Hash of a rule's complete index from the hashes of every file's entry.
+pub fn hash_index(entries: &BTreeMap<String, u64>) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    entries.hash(&mut hasher);
+    hasher.finish()
+}
+
+fn config_hash(config: &Config) -> u64 {
+    // Hashed like an index entry, so it doesn't depend on the iteration
+    // order of the configuration maps.
+    let config_hash = hash_index_entry(&serde_json::to_value(config).unwrap_or_default());
+    if config.plugins.is_empty() {
+        return config_hash;
+    }
+
+    // A rebuilt plugin may report other violations with the same settings
+    let mut hasher = DefaultHasher::new();
+    config_hash.hash(&mut hasher);
+    for path in &config.plugins {
+        fs::read(path).ok().hash(&mut hasher);
+    }
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_hash_index_entry_ignores_array_order() {
+        assert_eq!(
+            hash_index_entry(&json!({"deps": {"A": ["B", "C"]}})),
+            hash_index_entry(&json!({"deps": {"A": ["C", "B"]}}))
+        );
+        assert_ne!(
+            hash_index_entry(&json!({"deps": {"A": ["B", "C"]}})),
+            hash_index_entry(&json!({"deps": {"A": ["B", "D"]}}))
+        );
+    }
+
+    #[test]
+    fn test_load_discards_cache_of_other_config() {
+        let path = std::env::temp_dir().join("phanalist-test-load-discards.cache");
+        let mut cache = Cache::new(&Config::default());
+        cache
+            .index_hashes
+            .insert("./src".to_string(), HashMap::new());
+        cache.save(&path).unwrap();
+
+        assert_eq!(Cache::load(&path, &Config::default()).index_hashes.len(), 1);
+
+        let config = Config {
+            disable_rules: vec!["E0001".to_string()],
+            ..Default::default()
+        };
+        assert!(Cache::load(&path, &config).index_hashes.is_empty());
+
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_update_replaces_files_of_scanned_path() {
+        let mut cache = Cache::new(&Config::default());
+        let cached_file = CachedFile {
+            content_hash: 1,
+            index: HashMap::new(),
+            violations: vec![],
+            suppressions: Suppressions::default(),
+            parse_errors: vec![],
+        };
+        cache
+            .files
+            .insert("./src/Removed.php".to_string(), cached_file.clone());
+        cache
+            .files
+            .insert("./lib/Other.php".to_string(), cached_file.clone());
+        cache
+            .files
+            .insert("./src2/Sibling.php".to_string(), cached_file.clone());
+
+        cache.update(
+            "./src",
+            vec![("./src/Kept.php".to_string(), cached_file)],
+            HashMap::new(),
+        );
+
+        let mut paths: Vec<&String> = cache.files.keys().collect();
+        paths.sort();
+        assert_eq!(
+            paths,
+            vec!["./lib/Other.php", "./src/Kept.php", "./src2/Sibling.php"]
+        );
+    }
+}
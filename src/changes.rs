@@ -0,0 +1,249 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::{self, BufRead};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::results::Violation;
+
+/// How a file changed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Change<'a> {
+    Unchanged,
+    File,
+    /// Inclusive ranges of changed lines.
+    Lines(&'a [(usize, usize)]),
+}
+
+impl Change<'_> {
+    /// Whether the violation is within the changed part of the file.
+    pub fn contains(&self, violation: &Violation) -> bool {
+        match self {
+            Change::Unchanged => false,
+            Change::File => true,
+            Change::Lines(ranges) => ranges
+                .iter()
+                .any(|(start, end)| violation.start_line <= *end && violation.end_line >= *start),
+        }
+    }
+}
+
+/// The files to report violations for, when only a part of the project has
+/// changed. Every file is still indexed, so cross-file rules stay accurate.
+#[derive(Debug, Default)]
+pub struct ChangedFiles {
+    /// Canonical path → inclusive ranges of changed lines, or `None` when
+    /// the whole file counts as changed.
+    files: HashMap<PathBuf, Option<Vec<(usize, usize)>>>,
+}
+
+impl ChangedFiles {
+    /// Files changed since the merge base of `base` and `HEAD`, including
+    /// uncommitted changes. Deleted files are skipped. With `line_level`
+    /// only the changed lines count as changed.
+    pub fn from_git(base: &str, line_level: bool) -> io::Result<Self> {
+        Self::from_git_in(Path::new("."), base, line_level)
+    }
+
+    fn from_git_in(dir: &Path, base: &str, line_level: bool) -> io::Result<Self> {
+        let root = git(dir, &["rev-parse", "--show-toplevel"])?;
+        let root = fs::canonicalize(root.trim())?;
+        let merge_base = git(dir, &["merge-base", base, "HEAD"])?;
+        // The prefixes are set explicitly, as `diff.noprefix` and
+        // `diff.mnemonicPrefix` would otherwise change them.
+        let diff = git(
+            dir,
+            &[
+                "-c",
+                "core.quotePath=false",
+                "diff",
+                "--unified=0",
+                "--no-color",
+                "--no-ext-diff",
+                "--no-renames",
+                "--diff-filter=d",
+                "--src-prefix=a/",
+                "--dst-prefix=b/",
+                merge_base.trim(),
+            ],
+        )?;
+
+        let mut changes = Self::from_diff(&diff, &root);
+        if !line_level {
+            changes.files.values_mut().for_each(|lines| *lines = None);
+        }
+
+        Ok(changes)
+    }
+
+    /// One path per line, relative to the current directory. Lines of
+    /// files that don't exist are skipped.
+    pub fn from_list(reader: impl BufRead) -> io::Result<Self> {
+        let mut files = HashMap::new();
+        for line in reader.lines() {
+            let line = line?;
+            let path = line.trim();
+            if path.is_empty() {
+                continue;
+            }
+            if let Ok(path) = fs::canonicalize(path) {
+                files.insert(path, None);
+            }
+        }
+
+        Ok(Self { files })
+    }
+
+    fn from_diff(diff: &str, root: &Path) -> Self {
+        let mut files: HashMap<PathBuf, Option<Vec<(usize, usize)>>> = HashMap::new();
+        let mut current = None;
+        for line in diff.lines() {
+            if let Some(path) = line.strip_prefix("+++ ") {
+                current = path.strip_prefix("b/").map(|path| root.join(path));
+                if let Some(path) = &current {
+                    files.insert(path.clone(), Some(vec![]));
+                }
+            } else if let Some(hunk) = line.strip_prefix("@@ ") {
+                let range = hunk_range(hunk);
+                if let (Some(path), Some(range)) = (&current, range) {
+                    if let Some(Some(ranges)) = files.get_mut(path) {
+                        ranges.push(range);
+                    }
+                }
+            }
+        }
+
+        Self { files }
+    }
+
+    pub fn get(&self, path: &Path) -> Change<'_> {
+        let lines = fs::canonicalize(path)
+            .ok()
+            .and_then(|path| self.files.get(&path));
+        match lines {
+            Some(Some(ranges)) => Change::Lines(ranges),
+            Some(None) => Change::File,
+            None => Change::Unchanged,
+        }
+    }
+}
+
+/// Lines of the new version of the file covered by a hunk header such as
+/// `-12,3 +14,5 @@`. A hunk which only removes lines covers the line
+/// before the removal, so violations spanning it are still reported.
+fn hunk_range(hunk: &str) -> Option<(usize, usize)> {
+    let new = hunk
+        .split_whitespace()
+        .find_map(|part| part.strip_prefix('+'))?;
+    let (start, count) = match new.split_once(',') {
+        Some((start, count)) => (start.parse().ok()?, count.parse().ok()?),
+        None => (new.parse().ok()?, 1),
+    };
+
+    match count {
+        0 => Some((start, start)),
+        count => Some((start, start + count - 1)),
+    }
+}
+
+fn git(dir: &Path, args: &[&str]) -> io::Result<String> {
+    let output = Command::new("git").current_dir(dir).args(args).output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::Severity;
+
+    #[test]
+    fn test_hunk_range() {
+        assert_eq!(hunk_range("-12,3 +14,5 @@ class Foo"), Some((14, 18)));
+        assert_eq!(hunk_range("-12 +14 @@"), Some((14, 14)));
+        assert_eq!(hunk_range("-12,2 +11,0 @@"), Some((11, 11)));
+    }
+
+    #[test]
+    fn test_from_diff_collects_ranges_per_file() {
+        let diff = "\
+diff --git a/src/Foo.php b/src/Foo.php
+index 1111111..2222222 100644
+--- a/src/Foo.php
++++ b/src/Foo.php
+@@ -3 +3,2 @@ class Foo
+-    public $a;
++    public $a;
++    public $b;
+@@ -10,0 +12 @@ class Foo
++    }
+diff --git a/src/Bar.php b/src/Bar.php
+new file mode 100644
+--- /dev/null
++++ b/src/Bar.php
+@@ -0,0 +1,4 @@
++<?php
+";
+        let changes = ChangedFiles::from_diff(diff, Path::new("/project"));
+
+        assert_eq!(
+            changes.files.get(Path::new("/project/src/Foo.php")),
+            Some(&Some(vec![(3, 4), (12, 12)]))
+        );
+        assert_eq!(
+            changes.files.get(Path::new("/project/src/Bar.php")),
+            Some(&Some(vec![(1, 4)]))
+        );
+    }
+
+    #[test]
+    fn test_from_git_ignores_configured_prefixes() {
+        let dir = std::env::temp_dir().join("phanalist-test-from-git-prefixes");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let run = |args: &[&str]| git(&dir, args).unwrap();
+        run(&["init", "--quiet", "--initial-branch=main"]);
+        run(&["config", "user.name", "phanalist"]);
+        run(&["config", "user.email", "phanalist@example.com"]);
+        run(&["config", "diff.mnemonicPrefix", "true"]);
+        run(&["config", "diff.noprefix", "true"]);
+        fs::write(dir.join("A.php"), "<?php\n").unwrap();
+        run(&["add", "A.php"]);
+        run(&["commit", "--quiet", "-m", "base"]);
+        fs::write(dir.join("A.php"), "<?php\n\nclass A {}\n").unwrap();
+
+        let changes = ChangedFiles::from_git_in(&dir, "main", true).unwrap();
+
+        assert_eq!(changes.get(&dir.join("A.php")), Change::Lines(&[(2, 3)]));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_change_contains_overlapping_violations() {
+        let violation = |start_line, end_line| Violation {
+            rule: "E0001".to_string(),
+            severity: Severity::Minor,
+            symbol: None,
+            fingerprint: String::new(),
+            line: String::new(),
+            suggestion: String::new(),
+            start_line,
+            start_column: 0,
+            end_line,
+            end_column: 0,
+            edits: vec![],
+            related_locations: vec![],
+        };
+        let change = Change::Lines(&[(10, 12)]);
+
+        assert!(change.contains(&violation(5, 10)));
+        assert!(change.contains(&violation(11, 11)));
+        assert!(!change.contains(&violation(13, 20)));
+        assert!(Change::File.contains(&violation(13, 20)));
+    }
+}
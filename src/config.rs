@@ -1,17 +1,37 @@
 use std::collections::HashMap;
+use std::fs;
 use std::io::Write;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
+use colored::Colorize;
+use globset::{Glob, GlobSet, GlobSetBuilder};
 use serde::{Deserialize, Serialize};
 use serde_json::Value as JsonValue;
 
+use crate::results::Severity;
 use crate::rules;
+use crate::rules::custom::Definition;
 
 #[derive(Serialize, Debug, Deserialize, Clone)]
 pub struct Config {
     pub enabled_rules: Vec<String>,
     pub disable_rules: Vec<String>,
+    /// Globs of the files to analyse (empty = all `.php` files)
+    #[serde(default)]
+    pub include_paths: Vec<String>,
+    /// Globs of the files to skip, e.g. `vendor/**`
+    #[serde(default)]
+    pub exclude_paths: Vec<String>,
     pub rules: HashMap<String, JsonValue>,
+    /// Rules matching PHP code patterns, defined by the project
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_rules: Vec<Definition>,
+    /// YAML files listing more `custom_rules`, relative to the configuration file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub custom_rule_files: Vec<String>,
+    /// Rules compiled to WebAssembly, relative to the configuration file
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<String>,
 }
 
 impl Default for Config {
@@ -96,11 +116,24 @@ impl Default for Config {
             String::from(rules::e30::CODE),
             serde_json::to_value(rules::e30::Settings::default()).unwrap(),
         );
+        rules.insert(
+            String::from(rules::e32::CODE),
+            serde_json::to_value(rules::e32::Settings::default()).unwrap(),
+        );
+        rules.insert(
+            String::from(rules::e33::CODE),
+            serde_json::to_value(rules::e33::Settings::default()).unwrap(),
+        );
 
         Config {
             enabled_rules,
             disable_rules,
+            include_paths: vec![],
+            exclude_paths: vec![],
             rules,
+            custom_rules: vec![],
+            custom_rule_files: vec![],
+            plugins: vec![],
         }
     }
 }
@@ -118,4 +151,227 @@ impl Config {
 
         file.write_all(t.as_bytes())
     }
+
+    /// The files which should be analysed at all.
+    pub fn path_filter(&self) -> PathFilter {
+        PathFilter::new(&self.include_paths, &self.exclude_paths)
+    }
+
+    /// The files a rule should validate, taken from the `include_paths` and
+    /// `exclude_paths` settings of the rule.
+    pub fn rule_path_filter(&self, code: &str) -> Option<PathFilter> {
+        let settings = self.rules.get(code)?;
+        let include = Self::string_list(settings.get("include_paths"));
+        let exclude = Self::string_list(settings.get("exclude_paths"));
+        if include.is_empty() && exclude.is_empty() {
+            return None;
+        }
+
+        Some(PathFilter::new(&include, &exclude))
+    }
+
+    /// The severity set by the `severity` setting of a rule, if any.
+    pub fn rule_severity(&self, code: &str) -> Option<Severity> {
+        let value = self.rules.get(code)?.get("severity")?;
+        match serde_json::from_value(value.clone()) {
+            Ok(severity) => Some(severity),
+            Err(e) => {
+                eprintln!(
+                    "{}",
+                    format!("Invalid severity for rule #{}: {}", code, e)
+                        .red()
+                        .bold()
+                );
+                None
+            }
+        }
+    }
+
+    /// Append the rules of the `custom_rule_files`, which are relative to
+    /// `dir`. Returns the reason of every file which couldn't be read.
+    pub(crate) fn load_custom_rule_files(&mut self, dir: &Path) -> Vec<String> {
+        let mut errors = vec![];
+        for path in self.custom_rule_files.clone() {
+            let path = dir.join(path);
+            let definitions = fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|content| {
+                    serde_yaml::from_str::<Vec<Definition>>(&content).map_err(|e| e.to_string())
+                });
+            match definitions {
+                Ok(definitions) => self.custom_rules.extend(definitions),
+                Err(e) => errors.push(format!(
+                    "Unable to use the custom rules of {}: {}",
+                    path.display(),
+                    e
+                )),
+            }
+        }
+        errors
+    }
+
+    /// Make the paths of the `plugins` relative to the working directory
+    /// instead of `dir`.
+    pub(crate) fn resolve_plugin_paths(&mut self, dir: &Path) {
+        for path in self.plugins.iter_mut() {
+            *path = dir.join(&path).display().to_string();
+        }
+    }
+
+    fn string_list(value: Option<&JsonValue>) -> Vec<String> {
+        match value {
+            Some(JsonValue::Array(values)) => values
+                .iter()
+                .filter_map(|v| v.as_str().map(String::from))
+                .collect(),
+            _ => vec![],
+        }
+    }
+}
+
+/// Include/exclude glob lists matched against file paths.
+///
+/// Paths are matched as they are reported, without the leading `./`, so
+/// `--src ./src` with `exclude_paths: ["src/Tests/**"]` skips the tests.
+/// Patterns starting with `**/` match at any depth.
+#[derive(Debug, Clone, Default)]
+pub struct PathFilter {
+    include: Option<GlobSet>,
+    exclude: Option<GlobSet>,
+}
+
+impl PathFilter {
+    pub fn new(include: &[String], exclude: &[String]) -> Self {
+        Self {
+            include: Self::build(include),
+            exclude: Self::build(exclude),
+        }
+    }
+
+    pub fn is_match(&self, path: &Path) -> bool {
+        let path = path.strip_prefix("./").unwrap_or(path);
+
+        if let Some(exclude) = &self.exclude {
+            if exclude.is_match(path) {
+                return false;
+            }
+        }
+
+        match &self.include {
+            Some(include) => include.is_match(path),
+            None => true,
+        }
+    }
+
+    fn build(patterns: &[String]) -> Option<GlobSet> {
+        if patterns.is_empty() {
+            return None;
+        }
+
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            match Glob::new(pattern) {
+                Ok(glob) => {
+                    builder.add(glob);
+                }
+                Err(e) => eprintln!(
+                    "{}",
+                    format!("Invalid path pattern {}: {}", pattern, e)
+                        .red()
+                        .bold()
+                ),
+            }
+        }
+
+        builder.build().ok()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn test_path_filter_empty_matches_everything() {
+        let filter = PathFilter::new(&[], &[]);
+
+        assert!(filter.is_match(Path::new("./src/Service/Search.php")));
+    }
+
+    #[test]
+    fn test_path_filter_exclude() {
+        let filter = PathFilter::new(&[], &strings(&["**/vendor/**", "src/Tests/**"]));
+
+        assert!(!filter.is_match(Path::new("./src/vendor/lib/Client.php")));
+        assert!(!filter.is_match(Path::new("./src/Tests/SearchTest.php")));
+        assert!(filter.is_match(Path::new("./src/Service/Search.php")));
+    }
+
+    #[test]
+    fn test_path_filter_include_and_exclude() {
+        let filter = PathFilter::new(&strings(&["src/Service/**"]), &strings(&["**/*Proxy.php"]));
+
+        assert!(filter.is_match(Path::new("src/Service/Search.php")));
+        assert!(!filter.is_match(Path::new("src/Service/SearchProxy.php")));
+        assert!(!filter.is_match(Path::new("src/Controller/Search.php")));
+    }
+
+    #[test]
+    fn test_rule_path_filter() {
+        let mut config = Config::default();
+        config.rules.insert(
+            "E0024".to_string(),
+            json!({"max_loc": 30, "exclude_paths": ["**/Tests/**"]}),
+        );
+
+        let filter = config.rule_path_filter("E0024").unwrap();
+        assert!(!filter.is_match(Path::new("src/Tests/SearchTest.php")));
+        assert!(config.rule_path_filter("E0002").is_none());
+    }
+
+    #[test]
+    fn test_rule_severity() {
+        let mut config = Config::default();
+        config
+            .rules
+            .insert("E0011".to_string(), json!({"severity": "blocker"}));
+        config
+            .rules
+            .insert("E0002".to_string(), json!({"severity": "fatal"}));
+
+        assert_eq!(config.rule_severity("E0011"), Some(Severity::Blocker));
+        assert_eq!(config.rule_severity("E0002"), None);
+        assert_eq!(config.rule_severity("E0007"), None);
+    }
+
+    #[test]
+    fn test_load_custom_rule_files() {
+        let dir = std::env::temp_dir().join("phanalist-test-custom-rule-files");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("rules.yaml"),
+            "- code: C0001\n  message: Remove the debug calls\n  pattern: dd(...)\n",
+        )
+        .unwrap();
+
+        let mut config: Config = serde_yaml::from_str(
+            "enabled_rules: []\ndisable_rules: []\nrules: {}\ncustom_rule_files: [rules.yaml, missing.yaml]\n",
+        )
+        .unwrap();
+        let errors = config.load_custom_rule_files(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(config.custom_rules.len(), 1);
+        assert_eq!(config.custom_rules[0].code, "C0001");
+        assert_eq!(config.custom_rules[0].pattern, Some("dd(...)".to_string()));
+        assert_eq!(config.custom_rules[0].severity, Severity::Minor);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("missing.yaml"));
+    }
 }
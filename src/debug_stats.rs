@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::time::Duration;
 
 use cli_table::{format::Justify, Cell, Style, Table};
@@ -57,11 +57,18 @@ impl RuleTimings {
         }
     }
 
+    /// Append all the samples collected by another analysis thread.
+    pub fn merge(&mut self, other: RuleTimings) {
+        for (code, mut samples) in other.per_file {
+            self.per_file.entry(code).or_default().append(&mut samples);
+        }
+    }
+
     /// Render the debug section to stdout. `show_timing` prints the per-file
     /// timing table + slowest-files listing; `show_stats` prints the cost table.
     pub fn print_text(
         &self,
-        codes_count: &HashMap<String, i64>,
+        codes_count: &BTreeMap<String, i64>,
         total_files: i64,
         show_timing: bool,
         show_stats: bool,
@@ -156,7 +163,7 @@ impl RuleTimings {
     }
 
     /// Aggregate into per-rule stats. `codes_count` supplies violation counts.
-    pub fn compute(&self, codes_count: &HashMap<String, i64>) -> Vec<RuleStat> {
+    pub fn compute(&self, codes_count: &BTreeMap<String, i64>) -> Vec<RuleStat> {
         let grand_total_nanos: u128 = self
             .per_file
             .values()
@@ -254,7 +261,7 @@ mod tests {
             .collect();
         t.per_file.insert("E0001".to_string(), per);
 
-        let stat = only_stat(t.compute(&HashMap::new()));
+        let stat = only_stat(t.compute(&BTreeMap::new()));
         assert_eq!(stat.samples, 100);
         assert_eq!(stat.min, Duration::from_millis(1));
         assert_eq!(stat.max, Duration::from_millis(100));
@@ -268,7 +275,7 @@ mod tests {
     #[test]
     fn percentiles_single_sample() {
         let t = timings_with(vec![("a.php", metric(7, true, 3))]);
-        let stat = only_stat(t.compute(&HashMap::new()));
+        let stat = only_stat(t.compute(&BTreeMap::new()));
         assert_eq!(stat.samples, 1);
         assert_eq!(stat.min, Duration::from_millis(7));
         assert_eq!(stat.max, Duration::from_millis(7));
@@ -285,7 +292,7 @@ mod tests {
             ("b.php", metric(20, true, 1)),
             ("c.php", metric(30, true, 1)),
         ]);
-        let stat = only_stat(t.compute(&HashMap::new()));
+        let stat = only_stat(t.compute(&BTreeMap::new()));
         assert_eq!(stat.min, Duration::from_millis(10));
         assert_eq!(stat.max, Duration::from_millis(30));
         assert_eq!(stat.avg, Duration::from_millis(20));
@@ -301,7 +308,7 @@ mod tests {
             ("b.php", metric(0, false, 0)),
             ("c.php", metric(5, true, 2)),
         ]);
-        let mut codes = HashMap::new();
+        let mut codes = BTreeMap::new();
         codes.insert("E0001".to_string(), 9);
 
         let stat = only_stat(t.compute(&codes));
@@ -323,7 +330,7 @@ mod tests {
             "E0002".to_string(),
             vec![("a.php".to_string(), metric(10, true, 0))],
         );
-        let stats = t.compute(&HashMap::new());
+        let stats = t.compute(&BTreeMap::new());
         let sum: f64 = stats.iter().map(|s| s.pct_of_total).sum();
         assert!((sum - 100.0).abs() < 0.001, "pct sum was {sum}");
         let e1 = stats.iter().find(|s| s.code == "E0001").unwrap();
@@ -340,7 +347,7 @@ mod tests {
             ("e.php", metric(2, true, 0)),
             ("f.php", metric(4, true, 0)),
         ]);
-        let stat = only_stat(t.compute(&HashMap::new()));
+        let stat = only_stat(t.compute(&BTreeMap::new()));
         assert_eq!(stat.slowest.len(), 5);
         let durations: Vec<u64> = stat.slowest.iter().map(|(_, d)| d.as_millis() as u64).collect();
         assert_eq!(durations, vec![6, 5, 4, 3, 2]);
@@ -1,11 +1,14 @@
-use std::{collections::HashMap, path::PathBuf};
+use std::{collections::HashMap, path::PathBuf, sync::Arc};
 
 use bumpalo::Bump;
 use mago_database::file::FileId;
-use mago_span::Span;
-use mago_syntax::ast::{Program, Statement};
+use mago_span::{HasSpan, Span};
+use mago_syntax::ast::{ClassLikeMember, Program, Sequence, Statement};
 use serde::{Deserialize, Serialize};
 
+use crate::results::ParseError;
+use crate::symbols::{self, NameResolver, SymbolTable};
+
 /// A PHP source file paired with its parsed AST.
 ///
 /// The AST is allocated inside an external `bumpalo::Bump` arena; the same arena
@@ -16,10 +19,45 @@ pub struct File<'arena> {
     pub path: PathBuf,
     pub lines: Vec<String>,
     pub line_starts: Vec<u32>,
-    pub namespace: Option<String>,
-    pub class_name: Option<String>,
+    /// Every class-like, function and constant declared in the file.
+    pub declared_symbols: Vec<DeclaredSymbol>,
     pub reference_counter: RC,
     pub ast: Option<&'arena Program<'arena>>,
+    /// The symbols of the whole project, set before the file is validated.
+    pub symbols: Arc<SymbolTable>,
+    /// Syntax errors reported by the parser, the AST is partial when any.
+    pub parse_errors: Vec<ParseError>,
+}
+
+/// A class-like, function or constant declared at the top level of a file or
+/// of one of its namespaces.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DeclaredSymbol {
+    pub kind: SymbolKind,
+    /// `None` in the global namespace.
+    pub namespace: Option<String>,
+    pub name: String,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SymbolKind {
+    Class,
+    Interface,
+    Trait,
+    Enum,
+    Function,
+    Constant,
+}
+
+impl DeclaredSymbol {
+    pub fn fully_qualified_name(&self) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}\\{}", namespace, self.name),
+            None => self.name.clone(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -61,16 +99,48 @@ impl<'arena> File<'arena> {
         let line_starts = compute_line_starts(&content);
         let lines: Vec<String> = content.lines().map(|s| s.to_string()).collect();
 
-        let (namespace, class_name) = extract_namespace_and_class(program);
+        let declared_symbols = collect_declared_symbols(program);
 
-        Self {
+        let mut file = Self {
             path,
             lines,
             line_starts,
-            namespace,
-            class_name,
+            declared_symbols,
             reference_counter: RC::new(),
             ast: Some(program),
+            symbols: Arc::default(),
+            parse_errors: vec![],
+        };
+        file.parse_errors = program
+            .errors
+            .iter()
+            .map(|error| {
+                let span = error.span();
+                ParseError {
+                    message: error.to_string(),
+                    start_line: file.line_number(span.start.offset),
+                    start_column: file.column_number(span.start.offset),
+                    end_line: file.line_number(span.end.offset),
+                    end_column: file.column_number(span.end.offset),
+                }
+            })
+            .collect();
+
+        file
+    }
+
+    /// A file which isn't parsed, for reporting violations read from the
+    /// analysis cache.
+    pub fn unparsed(path: PathBuf, content: &str) -> Self {
+        Self {
+            path,
+            lines: content.lines().map(|s| s.to_string()).collect(),
+            line_starts: compute_line_starts(content),
+            declared_symbols: vec![],
+            reference_counter: RC::new(),
+            ast: None,
+            symbols: Arc::default(),
+            parse_errors: vec![],
         }
     }
 
@@ -79,10 +149,34 @@ impl<'arena> File<'arena> {
         None
     }
 
-    pub fn get_fully_qualified_name(&self) -> Option<String> {
-        match &self.namespace {
-            Some(n) => self.class_name.as_ref().map(|s| format!("{}\\{}", n, s)),
-            None => self.class_name.clone(),
+    /// The declared symbol around the byte offset.
+    pub fn declared_symbol_at(&self, offset: u32) -> Option<&DeclaredSymbol> {
+        self.declared_symbols
+            .iter()
+            .find(|symbol| contains(symbol.span, offset))
+    }
+
+    /// Fully qualified name of the innermost class-like, method or function
+    /// declared around the byte offset, e.g. `App\Service::run`.
+    pub fn enclosing_symbol(&self, offset: u32) -> Option<String> {
+        let symbol = self.declared_symbol_at(offset)?;
+        let name = symbol.fully_qualified_name();
+
+        match symbol.kind {
+            SymbolKind::Function => Some(format!("{}()", name)),
+            SymbolKind::Constant => None,
+            _ => match class_like_members(self.ast?.statements.iter(), symbol.span) {
+                Some(members) => Some(member_symbol(name, members, offset)),
+                None => Some(name),
+            },
+        }
+    }
+
+    /// Resolves the names used at the byte offset, within its namespace.
+    pub fn resolver_at(&self, offset: u32) -> NameResolver {
+        match self.ast {
+            Some(program) => symbols::resolver_at(program, offset),
+            None => NameResolver::default(),
         }
     }
 
@@ -141,35 +235,190 @@ fn compute_line_starts(content: &str) -> Vec<u32> {
     starts
 }
 
-fn extract_namespace_and_class<'arena>(
-    program: &Program<'arena>,
-) -> (Option<String>, Option<String>) {
-    let mut namespace = None;
-    let mut class_name = None;
+fn class_like_members<'a>(
+    statements: impl Iterator<Item = &'a Statement<'a>>,
+    span: Span,
+) -> Option<&'a Sequence<'a, ClassLikeMember<'a>>> {
+    for statement in statements {
+        if !contains(statement.span(), span.start.offset) {
+            continue;
+        }
+
+        return match statement {
+            Statement::Namespace(ns) => class_like_members(ns.statements().iter(), span),
+            Statement::Class(class) => Some(&class.members),
+            Statement::Interface(interface) => Some(&interface.members),
+            Statement::Trait(r#trait) => Some(&r#trait.members),
+            Statement::Enum(r#enum) => Some(&r#enum.members),
+            _ => None,
+        };
+    }
+
+    None
+}
+
+fn member_symbol(
+    class: String,
+    members: &Sequence<'_, ClassLikeMember<'_>>,
+    offset: u32,
+) -> String {
+    for member in members.iter() {
+        if let ClassLikeMember::Method(method) = member {
+            if contains(method.span(), offset) {
+                return format!("{}::{}", class, String::from_utf8_lossy(method.name.value));
+            }
+        }
+    }
+
+    class
+}
+
+fn contains(span: Span, offset: u32) -> bool {
+    span.start.offset <= offset && offset < span.end.offset
+}
+
+fn collect_declared_symbols(program: &Program<'_>) -> Vec<DeclaredSymbol> {
+    let mut symbols = vec![];
 
     for statement in program.statements.iter() {
         match statement {
             Statement::Namespace(ns) => {
-                if let Some(name) = ns.name.as_ref() {
-                    namespace = Some(String::from_utf8_lossy(name.value()).to_string());
+                let namespace = ns
+                    .name
+                    .as_ref()
+                    .map(|name| String::from_utf8_lossy(name.value()).into_owned());
+                for statement in ns.statements().iter() {
+                    declare_symbols(statement, namespace.as_ref(), &mut symbols);
                 }
-                for s in ns.statements().iter() {
-                    if let Statement::Class(class) = s {
-                        class_name = Some(String::from_utf8_lossy(class.name.value).into_owned());
-                        break;
-                    }
-                }
-            }
-            Statement::Class(class) => {
-                class_name = Some(String::from_utf8_lossy(class.name.value).to_string());
             }
-            _ => {}
+            _ => declare_symbols(statement, None, &mut symbols),
         }
+    }
 
-        if class_name.is_some() {
-            break;
+    symbols
+}
+
+fn declare_symbols(
+    statement: &Statement<'_>,
+    namespace: Option<&String>,
+    symbols: &mut Vec<DeclaredSymbol>,
+) {
+    let mut declare = |kind, name: &[u8], span| {
+        symbols.push(DeclaredSymbol {
+            kind,
+            namespace: namespace.cloned(),
+            name: String::from_utf8_lossy(name).into_owned(),
+            span,
+        })
+    };
+
+    match statement {
+        Statement::Class(class) => declare(SymbolKind::Class, class.name.value, class.span()),
+        Statement::Interface(interface) => declare(
+            SymbolKind::Interface,
+            interface.name.value,
+            interface.span(),
+        ),
+        Statement::Trait(r#trait) => declare(SymbolKind::Trait, r#trait.name.value, r#trait.span()),
+        Statement::Enum(r#enum) => declare(SymbolKind::Enum, r#enum.name.value, r#enum.span()),
+        Statement::Function(function) => {
+            declare(SymbolKind::Function, function.name.value, function.span())
+        }
+        Statement::Constant(constant) => {
+            for item in constant.items.iter() {
+                declare(SymbolKind::Constant, item.name.value, constant.span());
+            }
         }
+        _ => {}
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enclosing_symbol() {
+        let content = "<?php\nnamespace App;\n\nclass Service\n{\n    private $a;\n\n    public function run()\n    {\n        @foo();\n    }\n}\n\nfunction helper()\n{\n}\n";
+        let arena = Bump::new();
+        let file = File::new(&arena, PathBuf::from("Service.php"), content.to_string());
+        let offset = |needle: &str| content.find(needle).unwrap() as u32;
+
+        assert_eq!(
+            file.enclosing_symbol(offset("@foo")),
+            Some("App\\Service::run".to_string())
+        );
+        assert_eq!(
+            file.enclosing_symbol(offset("private")),
+            Some("App\\Service".to_string())
+        );
+        assert_eq!(
+            file.enclosing_symbol(offset("{\n}")),
+            Some("App\\helper()".to_string())
+        );
+        assert_eq!(file.enclosing_symbol(0), None);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let arena = Bump::new();
+        let valid = File::new(
+            &arena,
+            PathBuf::from("valid.php"),
+            "<?php\necho 1;\n".to_string(),
+        );
+        assert!(valid.parse_errors.is_empty());
 
-    (namespace, class_name)
+        let content = "<?php\n\nclass Broken {\n    public function run( {\n    }\n}\n";
+        let broken = File::new(&arena, PathBuf::from("broken.php"), content.to_string());
+        assert!(!broken.parse_errors.is_empty());
+        assert_eq!(broken.parse_errors[0].start_line, 4);
+        assert!(!broken.parse_errors[0].message.is_empty());
+    }
+
+    #[test]
+    fn test_declared_symbols() {
+        let content = "<?php\nnamespace App\\Model;\n\ninterface Entity {}\n\nclass User implements Entity {}\n\nnamespace App\\Util;\n\ntrait Helper {}\n\nfunction slug() {}\n\nconst VERSION = 1;\n";
+        let arena = Bump::new();
+        let file = File::new(&arena, PathBuf::from("test.php"), content.to_string());
+
+        let symbols: Vec<(SymbolKind, String)> = file
+            .declared_symbols
+            .iter()
+            .map(|symbol| (symbol.kind, symbol.fully_qualified_name()))
+            .collect();
+        assert_eq!(
+            symbols,
+            vec![
+                (SymbolKind::Interface, "App\\Model\\Entity".to_string()),
+                (SymbolKind::Class, "App\\Model\\User".to_string()),
+                (SymbolKind::Trait, "App\\Util\\Helper".to_string()),
+                (SymbolKind::Function, "App\\Util\\slug".to_string()),
+                (SymbolKind::Constant, "App\\Util\\VERSION".to_string()),
+            ]
+        );
+
+        let offset = content.find("class User").unwrap() as u32;
+        assert_eq!(file.declared_symbol_at(offset).unwrap().name, "User");
+    }
+
+    #[test]
+    fn test_declared_symbols_in_bracketed_namespaces() {
+        let content = "<?php\nnamespace App {\n    enum Status {}\n}\n\nnamespace {\n    class Legacy {}\n}\n";
+        let arena = Bump::new();
+        let file = File::new(&arena, PathBuf::from("test.php"), content.to_string());
+
+        let symbols: Vec<(SymbolKind, String)> = file
+            .declared_symbols
+            .iter()
+            .map(|symbol| (symbol.kind, symbol.fully_qualified_name()))
+            .collect();
+        assert_eq!(
+            symbols,
+            vec![
+                (SymbolKind::Enum, "App\\Status".to_string()),
+                (SymbolKind::Class, "Legacy".to_string()),
+            ]
+        );
+    }
 }
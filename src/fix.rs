@@ -0,0 +1,212 @@
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use bumpalo::Bump;
+use similar::TextDiff;
+
+use crate::file::File;
+use crate::results::{Edit, Results, Violation};
+
+/// The outcome of fixing a single file.
+pub struct FileFix {
+    pub path: String,
+    pub original: String,
+    pub fixed: String,
+    /// Indexes of the file's violations which are fixed.
+    pub fixed_violations: Vec<usize>,
+}
+
+impl FileFix {
+    pub fn diff(&self) -> String {
+        TextDiff::from_lines(&self.original, &self.fixed)
+            .unified_diff()
+            .header(&self.path, &self.path)
+            .to_string()
+    }
+}
+
+/// Compute the fixes of every file with fixable violations. Files which
+/// changed since they were analysed, or which don't parse without errors
+/// before and after the fix, are left alone.
+pub fn fix_results(results: &Results) -> Vec<FileFix> {
+    let mut paths: Vec<&String> = results
+        .files
+        .iter()
+        .filter(|(_, violations)| violations.iter().any(|v| !v.edits.is_empty()))
+        .map(|(path, _)| path)
+        .collect();
+    paths.sort();
+
+    paths
+        .into_iter()
+        .filter_map(|path| {
+            let original = fs::read_to_string(path).ok()?;
+            let (fixed, fixed_violations) = apply_edits(&original, &results.files[path]);
+            if fixed_violations.is_empty() || !parses_cleanly(Path::new(path), &original, &fixed) {
+                return None;
+            }
+
+            Some(FileFix {
+                path: path.clone(),
+                original,
+                fixed,
+                fixed_violations,
+            })
+        })
+        .collect()
+}
+
+/// Write the fixed files and drop the fixed violations from the results.
+pub fn apply(results: &mut Results, fixes: &[FileFix]) -> io::Result<()> {
+    for fix in fixes {
+        fs::write(&fix.path, &fix.fixed)?;
+
+        if let Some(violations) = results.files.get_mut(&fix.path) {
+            let mut index = 0;
+            violations.retain(|violation| {
+                let fixed = fix.fixed_violations.contains(&index);
+                index += 1;
+                if fixed {
+                    if let Some(count) = results.codes_count.get_mut(&violation.rule) {
+                        *count -= 1;
+                    }
+                }
+                !fixed
+            });
+        }
+    }
+    results.codes_count.retain(|_, count| *count > 0);
+
+    Ok(())
+}
+
+/// Apply the edits of the violations to the content. A violation is fixed
+/// only when all its edits can be applied: its source line is unchanged and
+/// none of its edits overlaps the edits of another fixed violation.
+pub(crate) fn apply_edits(content: &str, violations: &[Violation]) -> (String, Vec<usize>) {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut accepted: Vec<&Edit> = vec![];
+    let mut fixed_violations = vec![];
+
+    for (index, violation) in violations.iter().enumerate() {
+        if violation.edits.is_empty()
+            || lines.get(violation.start_line.saturating_sub(1)) != Some(&violation.line.as_str())
+        {
+            continue;
+        }
+
+        let applicable = violation.edits.iter().all(|edit| {
+            edit.start <= edit.end
+                && content.is_char_boundary(edit.start)
+                && content.is_char_boundary(edit.end)
+                && accepted
+                    .iter()
+                    .all(|other| *other == edit || !overlaps(other, edit))
+        });
+        if applicable {
+            for edit in &violation.edits {
+                if !accepted.contains(&edit) {
+                    accepted.push(edit);
+                }
+            }
+            fixed_violations.push(index);
+        }
+    }
+
+    accepted.sort_by_key(|edit| edit.start);
+    let mut fixed = content.to_string();
+    for edit in accepted.iter().rev() {
+        fixed.replace_range(edit.start..edit.end, &edit.replacement);
+    }
+
+    (fixed, fixed_violations)
+}
+
+/// Touching edits count as overlapping, so the order of insertions at the
+/// same offset never matters.
+fn overlaps(a: &Edit, b: &Edit) -> bool {
+    a.start <= b.end && b.start <= a.end
+}
+
+/// Edits are computed from the AST, so they can't be trusted when the parser
+/// had to recover from errors.
+fn parses_cleanly(path: &Path, original: &str, fixed: &str) -> bool {
+    let arena = Bump::new();
+    [original, fixed].iter().all(|content| {
+        File::new(&arena, PathBuf::from(path), content.to_string())
+            .ast
+            .is_some_and(|program| program.errors.is_empty())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::Severity;
+
+    fn get_violation(line: &str, start_line: usize, edits: Vec<Edit>) -> Violation {
+        Violation {
+            rule: "E0011".to_string(),
+            severity: Severity::Minor,
+            symbol: None,
+            fingerprint: String::new(),
+            line: line.to_string(),
+            suggestion: "Suggestion".to_string(),
+            start_line,
+            start_column: 0,
+            end_line: start_line,
+            end_column: 0,
+            edits,
+            related_locations: vec![],
+        }
+    }
+
+    fn edit(start: usize, end: usize, replacement: &str) -> Edit {
+        Edit {
+            start,
+            end,
+            replacement: replacement.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_apply_edits_skips_overlapping_edits() {
+        let content = "<?php\n@foo();\n@bar();\n";
+        let violations = vec![
+            get_violation("@foo();", 2, vec![edit(6, 7, "")]),
+            get_violation("@foo();", 2, vec![edit(6, 10, "baz")]),
+            get_violation("@bar();", 3, vec![edit(14, 15, "")]),
+        ];
+
+        let (fixed, fixed_violations) = apply_edits(content, &violations);
+
+        assert_eq!(fixed, "<?php\nfoo();\nbar();\n");
+        assert_eq!(fixed_violations, vec![0, 2]);
+    }
+
+    #[test]
+    fn test_apply_edits_skips_changed_lines() {
+        let content = "<?php\n@foo();\n";
+        let violations = vec![get_violation("@bar();", 2, vec![edit(6, 7, "")])];
+
+        let (fixed, fixed_violations) = apply_edits(content, &violations);
+
+        assert_eq!(fixed, content);
+        assert!(fixed_violations.is_empty());
+    }
+
+    #[test]
+    fn test_apply_edits_applies_shared_edits_once() {
+        let content = "  <?php\n";
+        let violations = vec![
+            get_violation("  <?php", 1, vec![edit(0, 2, "")]),
+            get_violation("  <?php", 1, vec![edit(0, 2, "")]),
+        ];
+
+        let (fixed, fixed_violations) = apply_edits(content, &violations);
+
+        assert_eq!(fixed, "<?php\n");
+        assert_eq!(fixed_violations, vec![0, 1]);
+    }
+}
@@ -0,0 +1,648 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::Write;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::sync::mpsc;
+
+use bumpalo::Bump;
+use serde_json::{json, Map, Value};
+
+use crate::analyse;
+use crate::config::PathFilter;
+use crate::file::File;
+use crate::rules::dependencies::class_like_dependencies;
+use crate::symbols::{namespace_of, ClassKind, FileSymbols, SymbolTable};
+
+/// The graphs `phanalist graph` exports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphKind {
+    /// The dependencies between class-likes, as collected by E0017 and E0029.
+    Class,
+    /// The dependencies between class-likes, collapsed to their namespaces.
+    Namespace,
+    /// The parent classes and the implemented or extended interfaces.
+    Inheritance,
+}
+
+impl FromStr for GraphKind {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<GraphKind, Self::Err> {
+        match input {
+            "class" => Ok(GraphKind::Class),
+            "namespace" => Ok(GraphKind::Namespace),
+            "inheritance" => Ok(GraphKind::Inheritance),
+            _ => Err(format!(
+                "unknown graph {}, possible options: class, namespace, inheritance",
+                input
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GraphFormat {
+    Dot,
+    Graphml,
+    Json,
+}
+
+impl FromStr for GraphFormat {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<GraphFormat, Self::Err> {
+        match input {
+            "dot" => Ok(GraphFormat::Dot),
+            "graphml" => Ok(GraphFormat::Graphml),
+            "json" => Ok(GraphFormat::Json),
+            _ => Err(format!(
+                "unknown graph format {}, possible options: dot, graphml, json",
+                input
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Options {
+    pub kind: GraphKind,
+    /// Keep only the nodes in this namespace, metrics are still computed on
+    /// the whole project.
+    pub namespace: Option<String>,
+    /// Collapse the namespaces to their first segments.
+    pub depth: Option<usize>,
+}
+
+/// The class-likes of the analysed files and what they depend on.
+#[derive(Default)]
+pub struct Project {
+    symbols: SymbolTable,
+    /// Fully qualified name → the class-likes it depends on, declared in the
+    /// project or not.
+    dependencies: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// Nodes and edges with their attributes, ordered by id so the output is
+/// stable.
+#[derive(Debug, Default, PartialEq)]
+pub struct Graph {
+    kind: &'static str,
+    nodes: BTreeMap<String, Map<String, Value>>,
+    edges: BTreeMap<(String, String), Map<String, Value>>,
+}
+
+impl Project {
+    pub fn scan(paths: &[String], filter: &PathFilter) -> Self {
+        let mut project = Self::default();
+        for path in paths {
+            let (sender, receiver) = mpsc::channel();
+            analyse::scan_folder(PathBuf::from(path), sender, filter.clone(), 0, None);
+            for (content, path) in receiver {
+                if let Ok(content) = content {
+                    project.add_file(path, content);
+                }
+            }
+        }
+
+        project
+    }
+
+    fn add_file(&mut self, path: PathBuf, content: String) {
+        let arena = Bump::new();
+        let file = File::new(&arena, path, content);
+        if let Some(program) = file.ast {
+            self.symbols.merge(FileSymbols::collect(program));
+        }
+        for class_like in class_like_dependencies(&file) {
+            self.dependencies.insert(
+                class_like.name,
+                class_like.dependencies.into_keys().collect(),
+            );
+        }
+    }
+
+    pub fn graph(&self, options: &Options) -> Graph {
+        let mut graph = match options.kind {
+            GraphKind::Class => self.class_graph(),
+            GraphKind::Namespace => self.namespace_graph(options.depth),
+            GraphKind::Inheritance => self.inheritance_graph(),
+        };
+        if let Some(namespace) = &options.namespace {
+            graph.retain_namespace(namespace);
+        }
+
+        graph
+    }
+
+    /// The declared class-likes depended on by each class-like, by name.
+    fn class_edges(&self) -> BTreeMap<&str, BTreeSet<&str>> {
+        let declared: HashMap<String, &str> = self
+            .dependencies
+            .keys()
+            .map(|name| (name.to_ascii_lowercase(), name.as_str()))
+            .collect();
+
+        self.dependencies
+            .iter()
+            .map(|(name, dependencies)| {
+                let targets = dependencies
+                    .iter()
+                    .filter_map(|dependency| declared.get(&dependency.to_ascii_lowercase()))
+                    .copied()
+                    .filter(|target| target != name)
+                    .collect();
+                (name.as_str(), targets)
+            })
+            .collect()
+    }
+
+    fn class_graph(&self) -> Graph {
+        let edges = self.class_edges();
+        let mut afferent: HashMap<&str, usize> = HashMap::new();
+        for target in edges.values().flatten() {
+            *afferent.entry(target).or_default() += 1;
+        }
+
+        let mut graph = Graph {
+            kind: "class",
+            ..Default::default()
+        };
+        for (name, targets) in &edges {
+            let is_abstract = self
+                .symbols
+                .class(name)
+                .is_some_and(|class| class.is_abstract);
+            let mut attributes = self.class_attributes(name);
+            attributes.extend(coupling_attributes(
+                afferent.get(name).copied().unwrap_or_default(),
+                targets.len(),
+                if is_abstract { 1.0 } else { 0.0 },
+            ));
+            graph.nodes.insert(name.to_string(), attributes);
+            for target in targets {
+                graph
+                    .edges
+                    .insert((name.to_string(), target.to_string()), Map::new());
+            }
+        }
+
+        graph
+    }
+
+    /// Ca and Ce of a namespace count the class-likes outside of it, like
+    /// E0022, and A the share of its class-likes which are abstract, like
+    /// E0023.
+    fn namespace_graph(&self, depth: Option<usize>) -> Graph {
+        let collapse = |name: &str| {
+            let namespace = namespace_of(name);
+            match depth {
+                Some(depth) => namespace
+                    .split('\\')
+                    .take(depth)
+                    .collect::<Vec<_>>()
+                    .join("\\"),
+                None => namespace.to_string(),
+            }
+        };
+
+        let mut classes: BTreeMap<String, (usize, usize)> = BTreeMap::new();
+        for name in self.dependencies.keys() {
+            let counts = classes.entry(collapse(name)).or_default();
+            counts.0 += 1;
+            counts.1 += usize::from(self.symbols.class(name).is_some_and(|c| c.is_abstract));
+        }
+
+        let mut weights: BTreeMap<(String, String), usize> = BTreeMap::new();
+        let mut afferent: HashMap<String, BTreeSet<&str>> = HashMap::new();
+        let mut efferent: HashMap<String, BTreeSet<&str>> = HashMap::new();
+        for (name, targets) in self.class_edges() {
+            let source = collapse(name);
+            for target in targets {
+                let target_namespace = collapse(target);
+                if source == target_namespace {
+                    continue;
+                }
+                efferent.entry(source.clone()).or_default().insert(target);
+                afferent
+                    .entry(target_namespace.clone())
+                    .or_default()
+                    .insert(name);
+                *weights
+                    .entry((node_id(&source), node_id(&target_namespace)))
+                    .or_default() += 1;
+            }
+        }
+
+        let mut graph = Graph {
+            kind: "namespace",
+            ..Default::default()
+        };
+        for (namespace, (total, abstract_count)) in classes {
+            let mut attributes = Map::new();
+            attributes.insert("classes".to_string(), json!(total));
+            attributes.extend(coupling_attributes(
+                afferent.get(&namespace).map_or(0, BTreeSet::len),
+                efferent.get(&namespace).map_or(0, BTreeSet::len),
+                abstract_count as f64 / total as f64,
+            ));
+            graph.nodes.insert(node_id(&namespace), attributes);
+        }
+        for (edge, weight) in weights {
+            let mut attributes = Map::new();
+            attributes.insert("weight".to_string(), json!(weight));
+            graph.edges.insert(edge, attributes);
+        }
+
+        graph
+    }
+
+    fn inheritance_graph(&self) -> Graph {
+        let mut graph = Graph {
+            kind: "inheritance",
+            ..Default::default()
+        };
+        for name in self.dependencies.keys() {
+            graph
+                .nodes
+                .insert(name.clone(), self.class_attributes(name));
+        }
+        for name in self.dependencies.keys() {
+            let class = match self.symbols.class(name) {
+                Some(class) => class,
+                None => continue,
+            };
+            let parents = class
+                .extends
+                .iter()
+                .map(|parent| (parent, "extends"))
+                .chain(class.implements.iter().map(|parent| (parent, "implements")));
+            for (parent, relation) in parents {
+                // Parents outside of the project have no node
+                let parent = match self.symbols.class(parent) {
+                    Some(parent) => &parent.name,
+                    None => continue,
+                };
+                let mut attributes = Map::new();
+                attributes.insert("relation".to_string(), json!(relation));
+                graph
+                    .edges
+                    .insert((name.clone(), parent.clone()), attributes);
+            }
+        }
+
+        graph
+    }
+
+    /// The kind of a class-like, with the DIT and NOC of E0020 and E0021.
+    fn class_attributes(&self, name: &str) -> Map<String, Value> {
+        let mut attributes = Map::new();
+        let kind = match self.symbols.class(name).map(|class| class.kind) {
+            Some(ClassKind::Class) | None => "class",
+            Some(ClassKind::Interface) => "interface",
+            Some(ClassKind::Trait) => "trait",
+            Some(ClassKind::Enum) => "enum",
+        };
+        attributes.insert("type".to_string(), json!(kind));
+        attributes.insert("dit".to_string(), json!(self.symbols.depth(name)));
+        attributes.insert(
+            "noc".to_string(),
+            json!(self.symbols.children(name).count()),
+        );
+
+        attributes
+    }
+}
+
+/// Ca, Ce, and the I, A and D derived from them as E0023 does.
+fn coupling_attributes(ca: usize, ce: usize, abstractness: f64) -> Map<String, Value> {
+    let instability = match ca + ce {
+        0 => 0.0,
+        total => ce as f64 / total as f64,
+    };
+    let distance = (abstractness + instability - 1.0).abs();
+    let round = |value: f64| (value * 100.0).round() / 100.0;
+
+    let mut attributes = Map::new();
+    attributes.insert("ca".to_string(), json!(ca));
+    attributes.insert("ce".to_string(), json!(ce));
+    attributes.insert("instability".to_string(), json!(round(instability)));
+    attributes.insert("abstractness".to_string(), json!(round(abstractness)));
+    attributes.insert("distance".to_string(), json!(round(distance)));
+
+    attributes
+}
+
+/// The global namespace has an empty name, which graph tools don't accept.
+fn node_id(namespace: &str) -> String {
+    match namespace {
+        "" => "\\".to_string(),
+        namespace => namespace.to_string(),
+    }
+}
+
+fn is_in_namespace(name: &str, namespace: &str) -> bool {
+    let namespace = namespace.trim_matches('\\');
+    name.get(..namespace.len())
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case(namespace))
+        && (name.len() == namespace.len() || name[namespace.len()..].starts_with('\\'))
+}
+
+impl Graph {
+    fn retain_namespace(&mut self, namespace: &str) {
+        self.nodes.retain(|id, _| is_in_namespace(id, namespace));
+        let nodes = &self.nodes;
+        self.edges
+            .retain(|(source, target), _| nodes.contains_key(source) && nodes.contains_key(target));
+    }
+
+    pub fn render(&self, format: GraphFormat) -> String {
+        match format {
+            GraphFormat::Dot => self.to_dot(),
+            GraphFormat::Graphml => self.to_graphml(),
+            GraphFormat::Json => self.to_json(),
+        }
+    }
+
+    fn to_dot(&self) -> String {
+        let quote = |text: &str| format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""));
+        let attributes = |attributes: &Map<String, Value>| {
+            attributes
+                .iter()
+                .map(|(name, value)| match value {
+                    Value::String(text) => format!("{}={}", name, quote(text)),
+                    value => format!("{}={}", name, value),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+
+        let mut dot = format!("digraph {} {{\n", quote(self.kind));
+        for (id, node) in &self.nodes {
+            let _ = writeln!(dot, "  {} [{}];", quote(id), attributes(node));
+        }
+        for ((source, target), edge) in &self.edges {
+            let _ = match edge.is_empty() {
+                true => writeln!(dot, "  {} -> {};", quote(source), quote(target)),
+                false => writeln!(
+                    dot,
+                    "  {} -> {} [{}];",
+                    quote(source),
+                    quote(target),
+                    attributes(edge)
+                ),
+            };
+        }
+        dot.push_str("}\n");
+
+        dot
+    }
+
+    fn to_graphml(&self) -> String {
+        let escape = |text: &str| {
+            text.replace('&', "&amp;")
+                .replace('<', "&lt;")
+                .replace('>', "&gt;")
+                .replace('"', "&quot;")
+        };
+        let data = |element: &str, attributes: &Map<String, Value>| {
+            attributes
+                .iter()
+                .map(|(name, value)| {
+                    let value = match value {
+                        Value::String(text) => escape(text),
+                        value => value.to_string(),
+                    };
+                    format!("<data key=\"{}_{}\">{}</data>", element, name, value)
+                })
+                .collect::<String>()
+        };
+
+        let mut graphml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">\n",
+        );
+        let keys = self
+            .nodes
+            .values()
+            .flatten()
+            .map(|attribute| ("node", attribute))
+            .chain(
+                self.edges
+                    .values()
+                    .flatten()
+                    .map(|attribute| ("edge", attribute)),
+            )
+            .map(|(element, (name, value))| (element, name, graphml_type(value)))
+            .collect::<BTreeSet<_>>();
+        for (element, name, r#type) in keys {
+            let _ = writeln!(
+                graphml,
+                "  <key id=\"{}_{}\" for=\"{}\" attr.name=\"{}\" attr.type=\"{}\"/>",
+                element, name, element, name, r#type
+            );
+        }
+
+        let _ = writeln!(
+            graphml,
+            "  <graph id=\"{}\" edgedefault=\"directed\">",
+            self.kind
+        );
+        for (id, node) in &self.nodes {
+            let _ = writeln!(
+                graphml,
+                "    <node id=\"{}\">{}</node>",
+                escape(id),
+                data("node", node)
+            );
+        }
+        for ((source, target), edge) in &self.edges {
+            let _ = writeln!(
+                graphml,
+                "    <edge source=\"{}\" target=\"{}\">{}</edge>",
+                escape(source),
+                escape(target),
+                data("edge", edge)
+            );
+        }
+        graphml.push_str("  </graph>\n</graphml>\n");
+
+        graphml
+    }
+
+    fn to_json(&self) -> String {
+        let nodes = self
+            .nodes
+            .iter()
+            .map(|(id, attributes)| {
+                let mut node = Map::new();
+                node.insert("id".to_string(), json!(id));
+                node.extend(attributes.clone());
+                Value::Object(node)
+            })
+            .collect::<Vec<_>>();
+        let edges = self
+            .edges
+            .iter()
+            .map(|((source, target), attributes)| {
+                let mut edge = Map::new();
+                edge.insert("source".to_string(), json!(source));
+                edge.insert("target".to_string(), json!(target));
+                edge.extend(attributes.clone());
+                Value::Object(edge)
+            })
+            .collect::<Vec<_>>();
+
+        json!({"graph": self.kind, "nodes": nodes, "edges": edges}).to_string()
+    }
+}
+
+fn graphml_type(value: &Value) -> &'static str {
+    match value {
+        Value::Bool(_) => "boolean",
+        Value::Number(number) if number.is_f64() => "double",
+        Value::Number(_) => "int",
+        _ => "string",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn project() -> Project {
+        let mut project = Project::default();
+        project.add_file(
+            PathBuf::from("Order.php"),
+            "<?php\nnamespace App\\Domain\\Sales;\n\nabstract class Entity {}\n\nclass Order extends Entity\n{\n    public function bill(): \\App\\Billing\\Invoice\n    {\n        return new \\App\\Billing\\Invoice($this);\n    }\n}\n".to_string(),
+        );
+        project.add_file(
+            PathBuf::from("Invoice.php"),
+            "<?php\nnamespace App\\Billing;\n\nuse App\\Domain\\Sales\\Order;\n\ninterface Document {}\n\nclass Invoice implements Document\n{\n    public function __construct(private Order $order, private \\DateTimeImmutable $date) {}\n}\n".to_string(),
+        );
+        project
+    }
+
+    fn options(kind: GraphKind) -> Options {
+        Options {
+            kind,
+            namespace: None,
+            depth: None,
+        }
+    }
+
+    #[test]
+    fn class_graph() {
+        let graph = project().graph(&options(GraphKind::Class));
+
+        assert_eq!(
+            graph.nodes.keys().collect::<Vec<_>>(),
+            vec![
+                "App\\Billing\\Document",
+                "App\\Billing\\Invoice",
+                "App\\Domain\\Sales\\Entity",
+                "App\\Domain\\Sales\\Order",
+            ]
+        );
+        assert_eq!(
+            graph.edges.keys().cloned().collect::<Vec<_>>(),
+            vec![
+                (
+                    "App\\Billing\\Invoice".to_string(),
+                    "App\\Billing\\Document".to_string()
+                ),
+                (
+                    "App\\Billing\\Invoice".to_string(),
+                    "App\\Domain\\Sales\\Order".to_string()
+                ),
+                (
+                    "App\\Domain\\Sales\\Order".to_string(),
+                    "App\\Billing\\Invoice".to_string()
+                ),
+                (
+                    "App\\Domain\\Sales\\Order".to_string(),
+                    "App\\Domain\\Sales\\Entity".to_string()
+                ),
+            ]
+        );
+        let order = &graph.nodes["App\\Domain\\Sales\\Order"];
+        assert_eq!(order["ca"], json!(1));
+        assert_eq!(order["ce"], json!(2));
+        assert_eq!(order["instability"], json!(0.67));
+        assert_eq!(order["dit"], json!(1));
+        let entity = &graph.nodes["App\\Domain\\Sales\\Entity"];
+        assert_eq!(entity["noc"], json!(1));
+        assert_eq!(entity["abstractness"], json!(1.0));
+    }
+
+    #[test]
+    fn namespace_graph() {
+        let graph = project().graph(&options(GraphKind::Namespace));
+
+        assert_eq!(graph.nodes.len(), 2);
+        let billing = &graph.nodes["App\\Billing"];
+        assert_eq!(billing["classes"], json!(2));
+        assert_eq!(billing["ca"], json!(1));
+        assert_eq!(billing["ce"], json!(1));
+        assert_eq!(billing["abstractness"], json!(0.5));
+        assert_eq!(
+            graph.edges[&("App\\Billing".to_string(), "App\\Domain\\Sales".to_string())]["weight"],
+            json!(1)
+        );
+    }
+
+    #[test]
+    fn collapsed_namespaces() {
+        let mut options = options(GraphKind::Namespace);
+        options.depth = Some(1);
+        let graph = project().graph(&options);
+
+        assert_eq!(graph.nodes.keys().collect::<Vec<_>>(), vec!["App"]);
+        assert!(graph.edges.is_empty());
+    }
+
+    #[test]
+    fn inheritance_graph_filtered_by_namespace() {
+        let mut options = options(GraphKind::Inheritance);
+        options.namespace = Some("App\\Domain".to_string());
+        let graph = project().graph(&options);
+
+        assert_eq!(graph.nodes.len(), 2);
+        assert_eq!(
+            graph.edges[&(
+                "App\\Domain\\Sales\\Order".to_string(),
+                "App\\Domain\\Sales\\Entity".to_string()
+            )]["relation"],
+            json!("extends")
+        );
+    }
+
+    #[test]
+    fn namespace_filter_with_multibyte_names() {
+        assert!(is_in_namespace("Ünicode\\Model", "Ünicode"));
+        assert!(is_in_namespace("App\\Ünicode", "App"));
+        assert!(!is_in_namespace("Ünicode\\Model", "U"));
+        assert!(!is_in_namespace("App", "App\\Ünicode"));
+    }
+
+    #[test]
+    fn formats() {
+        let mut options = options(GraphKind::Inheritance);
+        options.namespace = Some("App\\Billing".to_string());
+        let graph = project().graph(&options);
+
+        let dot = graph.render(GraphFormat::Dot);
+        assert!(dot.starts_with("digraph \"inheritance\" {\n"));
+        assert!(dot.contains(
+            "  \"App\\\\Billing\\\\Invoice\" -> \"App\\\\Billing\\\\Document\" [relation=\"implements\"];\n"
+        ));
+
+        let graphml = graph.render(GraphFormat::Graphml);
+        assert!(graphml
+            .contains("<key id=\"node_dit\" for=\"node\" attr.name=\"dit\" attr.type=\"int\"/>"));
+        assert!(graphml.contains("<node id=\"App\\Billing\\Document\"><data key=\"node_type\">interface</data><data key=\"node_dit\">0</data><data key=\"node_noc\">0</data></node>"));
+
+        let json: Value = serde_json::from_str(&graph.render(GraphFormat::Json)).unwrap();
+        assert_eq!(json["graph"], json!("inheritance"));
+        assert_eq!(json["nodes"][1]["id"], json!("App\\Billing\\Invoice"));
+        assert_eq!(json["edges"][0]["target"], json!("App\\Billing\\Document"));
+    }
+}
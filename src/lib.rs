@@ -1,12 +1,20 @@
 use analyse::Analyse;
 use outputs::Format;
 pub mod analyse;
+pub mod baseline;
+pub mod cache;
+pub mod changes;
 pub mod config;
 pub mod debug_stats;
 pub mod file;
+pub mod fix;
+pub mod graph;
+pub mod lsp;
 pub mod outputs;
 pub mod results;
 pub mod rules;
+pub mod suppression;
+pub mod symbols;
 
 pub fn scan(path: String) -> results::Results {
     let output_format = Format::json;
@@ -14,7 +22,17 @@ pub fn scan(path: String) -> results::Results {
 
     let analyze: Analyse = Analyse::new(&config);
 
-    analyze.scan("./src".to_string(), &config, false, &output_format,0,false)
+    analyze.scan(
+        "./src".to_string(),
+        &config,
+        false,
+        &output_format,
+        0,
+        false,
+        0,
+        None,
+        None,
+    )
 }
 
 #[cfg(test)]
@@ -0,0 +1,487 @@
+use std::collections::{HashMap, HashSet};
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bumpalo::Bump;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as _, PublishDiagnostics,
+};
+use lsp_types::request::{HoverRequest, Request as _};
+use lsp_types::{
+    CodeDescription, Diagnostic, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DidSaveTextDocumentParams, Hover,
+    HoverContents, HoverParams, HoverProviderCapability, InitializeParams, MarkupContent,
+    MarkupKind, NumberOrString, Position, PublishDiagnosticsParams, Range, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
+    TextDocumentSyncSaveOptions, Url,
+};
+use serde_json::Value;
+
+use crate::analyse::{self, Analyse};
+use crate::cache;
+use crate::config::{Config, PathFilter};
+use crate::file::File;
+use crate::outputs::Format;
+use crate::results::{Severity, Violation};
+use crate::rules;
+
+type LspError = Box<dyn Error + Sync + Send>;
+
+static RULES_URL: &str = "https://github.com/denzyldick/phanalist/blob/main/src/rules/examples";
+
+struct Document {
+    path: PathBuf,
+    text: String,
+    violations: Vec<Violation>,
+}
+
+/// Language server publishing the violations of the open documents.
+///
+/// Every file of the workspace is indexed on startup and the index entries
+/// are kept, so cross-file rules see the whole project. When an edit changes
+/// the entries of a document, its previous entries are replaced in the
+/// indexes and all the open documents are validated again.
+struct Server {
+    analyse: Analyse,
+    filter: PathFilter,
+    /// File path → rule code → the file's entry in the rule's index.
+    index: HashMap<PathBuf, HashMap<String, Value>>,
+    documents: HashMap<Url, Document>,
+    /// Codes of the rules documented in the repository, unlike the custom
+    /// rules and the plugins.
+    built_in_codes: HashSet<String>,
+}
+
+/// Run the language server over stdio until the client shuts it down.
+pub fn run(config_path: &str) -> Result<(), LspError> {
+    let (connection, io_threads) = Connection::stdio();
+
+    let capabilities = serde_json::to_value(ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::FULL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..Default::default()
+            },
+        )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        ..Default::default()
+    })?;
+    let params: InitializeParams = serde_json::from_value(connection.initialize(capabilities)?)?;
+
+    // Paths are relative to the workspace, like when running from the command
+    // line, so the path globs of the configuration apply the same way.
+    #[allow(deprecated)]
+    let root_uri = params.root_uri;
+    let root = params
+        .workspace_folders
+        .and_then(|folders| folders.into_iter().next())
+        .map(|folder| folder.uri)
+        .or(root_uri)
+        .and_then(|uri| uri.to_file_path().ok());
+    if let Some(root) = root {
+        std::env::set_current_dir(root)?;
+    }
+
+    let mut server = Server::new(config_path);
+    server.index_workspace();
+    server.main_loop(&connection)?;
+
+    // The writer thread stops once the connection is dropped
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+impl Server {
+    fn new(config_path: &str) -> Self {
+        // Unlike the command line, a missing configuration file isn't created
+        let config = if Path::new(config_path).exists() {
+            Analyse::parse_config(config_path.to_string(), &Format::json, true)
+        } else {
+            Config::default()
+        };
+
+        Self {
+            analyse: Analyse::new(&config),
+            filter: config.path_filter(),
+            index: HashMap::new(),
+            documents: HashMap::new(),
+            built_in_codes: rules::all_rules().into_keys().collect(),
+        }
+    }
+
+    fn index_workspace(&mut self) {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let filter = self.filter.clone();
+        std::thread::spawn(move || {
+            analyse::scan_folder(PathBuf::from("."), sender, filter, 0, None)
+        });
+
+        for (content, path) in receiver {
+            // Unreadable files can't be opened in the editor either
+            let content = match content {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            let arena = Bump::new();
+            let file = File::new(&arena, path, content);
+            let entries = self.analyse.index_file(&file);
+            self.index.insert(file.path, entries);
+        }
+    }
+
+    fn main_loop(&mut self, connection: &Connection) -> Result<(), LspError> {
+        for message in &connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    let response = self.handle_request(request);
+                    connection.sender.send(Message::Response(response))?;
+                }
+                Message::Notification(notification) => {
+                    for published in self.handle_notification(notification)? {
+                        connection.sender.send(Message::Notification(published))?;
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => match serde_json::from_value::<HoverParams>(request.params) {
+                Ok(params) => Response::new_ok(request.id, self.hover(params)),
+                Err(e) => {
+                    Response::new_err(request.id, ErrorCode::InvalidParams as i32, e.to_string())
+                }
+            },
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("Unsupported request {}", method),
+            ),
+        }
+    }
+
+    /// Returns the diagnostics to publish.
+    fn handle_notification(
+        &mut self,
+        notification: Notification,
+    ) -> Result<Vec<Notification>, LspError> {
+        let uris = match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let document = params.text_document;
+                self.update_document(document.uri, document.text)
+            }
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                // Full synchronization, so the last change has the whole text
+                match params.content_changes.into_iter().last() {
+                    Some(change) => self.update_document(params.text_document.uri, change.text),
+                    None => vec![],
+                }
+            }
+            DidSaveTextDocument::METHOD => {
+                let params: DidSaveTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                let uri = params.text_document.uri;
+                match self.documents.get(&uri) {
+                    Some(document) => {
+                        let text = params.text.unwrap_or_else(|| document.text.clone());
+                        self.update_document(uri, text)
+                    }
+                    None => vec![],
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                return Ok(self.close_document(params.text_document.uri));
+            }
+            _ => vec![],
+        };
+
+        Ok(uris.iter().map(|uri| self.publish(uri)).collect())
+    }
+
+    /// Validate the new text of a document. Returns the documents whose
+    /// diagnostics have to be published.
+    fn update_document(&mut self, uri: Url, text: String) -> Vec<Url> {
+        let path = match workspace_path(&uri) {
+            Some(path) => path,
+            None => return vec![],
+        };
+        self.documents.insert(
+            uri.clone(),
+            Document {
+                path: path.clone(),
+                text,
+                violations: vec![],
+            },
+        );
+
+        let uris = if self.reindex(&path, self.documents[&uri].text.clone()) {
+            self.documents.keys().cloned().collect()
+        } else {
+            vec![uri]
+        };
+        for uri in &uris {
+            self.validate(uri);
+        }
+        uris
+    }
+
+    /// Forget the document, indexing the file as it is on disk again.
+    fn close_document(&mut self, uri: Url) -> Vec<Notification> {
+        let document = match self.documents.remove(&uri) {
+            Some(document) => document,
+            None => return vec![],
+        };
+
+        let mut published = vec![publish_diagnostics(uri, vec![])];
+        let content = fs::read_to_string(&document.path).unwrap_or_default();
+        if self.reindex(&document.path, content) {
+            let uris: Vec<Url> = self.documents.keys().cloned().collect();
+            for uri in &uris {
+                self.validate(uri);
+                published.push(self.publish(uri));
+            }
+        }
+        published
+    }
+
+    /// Update the index entries of the file, replacing them in the indexes
+    /// when they changed. Returns whether they changed.
+    fn reindex(&mut self, path: &Path, content: String) -> bool {
+        let arena = Bump::new();
+        let file = File::new(&arena, path.to_path_buf(), content);
+        let entries: HashMap<String, Value> = if self.filter.is_match(path) {
+            self.analyse.index_entries(&file)
+        } else {
+            HashMap::new()
+        };
+
+        let hashes = |entries: &HashMap<String, Value>| -> HashMap<String, u64> {
+            entries
+                .iter()
+                .map(|(code, entry)| (code.clone(), cache::hash_index_entry(entry)))
+                .collect()
+        };
+        let previous = self.index.get(path).map(hashes).unwrap_or_default();
+        if previous == hashes(&entries) {
+            return false;
+        }
+
+        for (code, entry) in self.index.remove(path).unwrap_or_default() {
+            self.analyse.unload_index_entry(&code, &entry);
+        }
+        for (code, entry) in &entries {
+            self.analyse.load_index_entry(code, entry);
+        }
+        self.index.insert(path.to_path_buf(), entries);
+        true
+    }
+
+    fn validate(&mut self, uri: &Url) {
+        let document = match self.documents.get_mut(uri) {
+            Some(document) => document,
+            None => return,
+        };
+        if !self.filter.is_match(&document.path) {
+            document.violations = vec![];
+            return;
+        }
+
+        let arena = Bump::new();
+        let mut file = File::new(&arena, document.path.clone(), document.text.clone());
+        document.violations = self.analyse.analyse_file(&mut file, false).0;
+    }
+
+    fn publish(&self, uri: &Url) -> Notification {
+        let diagnostics = match self.documents.get(uri) {
+            Some(document) => document
+                .violations
+                .iter()
+                .map(|violation| {
+                    let built_in = self.built_in_codes.contains(&violation.rule);
+                    diagnostic(&document.text, violation, built_in)
+                })
+                .collect(),
+            None => vec![],
+        };
+        publish_diagnostics(uri.clone(), diagnostics)
+    }
+
+    /// The explanation of the rules violated at the position.
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let position = params.text_document_position_params.position;
+        let document = self
+            .documents
+            .get(&params.text_document_position_params.text_document.uri)?;
+
+        let mut codes: Vec<&String> = document
+            .violations
+            .iter()
+            .filter(|violation| {
+                let range = range(&document.text, violation);
+                range.start <= position && position <= range.end
+            })
+            .map(|violation| &violation.rule)
+            .collect();
+        codes.sort();
+        codes.dedup();
+
+        let explanations: Vec<String> = codes
+            .into_iter()
+            .filter_map(|code| self.analyse.rules.get(code)?.get_detailed_explanation())
+            .collect();
+        if explanations.is_empty() {
+            return None;
+        }
+
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: explanations.join("\n\n---\n\n"),
+            }),
+            range: None,
+        })
+    }
+}
+
+fn publish_diagnostics(uri: Url, diagnostics: Vec<Diagnostic>) -> Notification {
+    Notification::new(
+        PublishDiagnostics::METHOD.to_string(),
+        PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        },
+    )
+}
+
+/// The path of a document relative to the workspace, as the command line
+/// would report it, e.g. `./src/Service.php`.
+fn workspace_path(uri: &Url) -> Option<PathBuf> {
+    let path = uri.to_file_path().ok()?;
+    let root = std::env::current_dir().ok()?;
+    Some(match path.strip_prefix(&root) {
+        Ok(relative) => Path::new(".").join(relative),
+        Err(_) => path,
+    })
+}
+
+fn diagnostic_severity(severity: Severity) -> DiagnosticSeverity {
+    match severity {
+        Severity::Info => DiagnosticSeverity::INFORMATION,
+        Severity::Minor | Severity::Major => DiagnosticSeverity::WARNING,
+        Severity::Critical | Severity::Blocker => DiagnosticSeverity::ERROR,
+    }
+}
+
+/// Only the built-in rules link to their documentation.
+fn diagnostic(text: &str, violation: &Violation, built_in: bool) -> Diagnostic {
+    let number = violation
+        .rule
+        .trim_start_matches('E')
+        .trim_start_matches('0');
+    let href = built_in
+        .then(|| Url::parse(&format!("{RULES_URL}/e{number}/e{number}.md")).ok())
+        .flatten();
+
+    Diagnostic {
+        range: range(text, violation),
+        severity: Some(diagnostic_severity(violation.severity)),
+        code: Some(NumberOrString::String(violation.rule.clone())),
+        code_description: href.map(|href| CodeDescription { href }),
+        source: Some(String::from("phanalist")),
+        message: violation.suggestion.clone(),
+        ..Default::default()
+    }
+}
+
+/// Violations have 1-based lines and byte columns, while LSP positions are
+/// 0-based with UTF-16 columns.
+fn range(text: &str, violation: &Violation) -> Range {
+    let position = |line: usize, column: usize| {
+        let line = line.saturating_sub(1);
+        let character = text.lines().nth(line).map_or(column, |content| {
+            let end = (0..=column.min(content.len()))
+                .rev()
+                .find(|i| content.is_char_boundary(*i))
+                .unwrap_or(0);
+            content[..end].encode_utf16().count()
+        });
+        Position::new(line as u32, character as u32)
+    };
+
+    Range::new(
+        position(violation.start_line, violation.start_column),
+        position(violation.end_line, violation.end_column),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn get_violation(start_line: usize, start_column: usize, end_column: usize) -> Violation {
+        Violation {
+            rule: "E0011".to_string(),
+            severity: Severity::Minor,
+            symbol: None,
+            fingerprint: String::new(),
+            line: String::new(),
+            suggestion: "Suggestion".to_string(),
+            start_line,
+            start_column,
+            end_line: start_line,
+            end_column,
+            edits: vec![],
+            related_locations: vec![],
+        }
+    }
+
+    #[test]
+    fn test_range_counts_utf16_columns() {
+        let text = "<?php\n$é = @foo();\n";
+
+        assert_eq!(
+            range(text, &get_violation(2, 6, 13)),
+            Range::new(Position::new(1, 5), Position::new(1, 12))
+        );
+    }
+
+    #[test]
+    fn test_diagnostic_links_rule_documentation() {
+        let diagnostic = diagnostic("<?php\n", &get_violation(1, 0, 1), true);
+
+        assert_eq!(
+            diagnostic.code,
+            Some(NumberOrString::String("E0011".into()))
+        );
+        assert_eq!(
+            diagnostic.code_description.unwrap().href.as_str(),
+            "https://github.com/denzyldick/phanalist/blob/main/src/rules/examples/e11/e11.md"
+        );
+    }
+    #[test]
+    fn test_diagnostic_doesnt_link_custom_rules() {
+        let diagnostic = diagnostic("<?php\n", &get_violation(1, 0, 1), false);
+
+        assert_eq!(diagnostic.code_description, None);
+    }
+}
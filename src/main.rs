@@ -1,37 +1,62 @@
 extern crate exitcode;
 
-use std::path::Path;
+use std::fs;
+use std::io::{self, BufReader};
+use std::path::{Path, PathBuf};
 use std::process;
 use std::str::FromStr;
 
-use clap::Parser;
+use clap::{Parser, Subcommand};
 
 use crate::analyse::Analyse;
-use crate::outputs::Format;
+use crate::baseline::Baseline;
+use crate::cache::Cache;
+use crate::changes::ChangedFiles;
+use crate::config::Config;
+use crate::graph::{GraphFormat, GraphKind, Options, Project};
+use crate::outputs::{Format, Report};
+use crate::results::{Results, Severity, Sort};
 
 mod analyse;
+mod baseline;
+mod cache;
+mod changes;
 mod config;
 mod debug_stats;
 mod file;
+mod fix;
+mod graph;
+mod lsp;
 mod outputs;
 mod results;
 mod rules;
+mod suppression;
+mod symbols;
 
 ///
 /// A static analyser for your PHP project.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about=None)]
 struct Args {
-    #[arg(short, long, default_value = "./phanalist.yaml")]
+    #[command(subcommand)]
+    command: Option<Command>,
+    #[arg(short, long, default_value = "./phanalist.yaml", global = true)]
     config: String,
-    #[arg(short, long, default_values_t = ["./src".to_string()])]
+    #[arg(short, long, default_values_t = ["./src".to_string()], global = true)]
     src: Vec<String>,
     #[arg(short, long)]
     /// The list of rules to use (by default it is used from config)
     rules: Option<Vec<String>>,
     #[arg(short, long, default_value = "text")]
-    /// Possible options: text, json, sarif, codeclimate
+    /// Possible options: text, json, sarif, codeclimate, html, checkstyle, junit, github
     output_format: String,
+    #[arg(long, conflicts_with = "output_format")]
+    /// Write a report as format:path, "-" being the standard output. Repeat it
+    /// for several reports, e.g. --report sarif:phanalist.sarif --report text:-
+    report: Vec<Report>,
+    #[arg(long, default_value = "file")]
+    /// Order of the reported files and violations: file, rule, severity, count
+    sort: Sort,
     #[arg(long)]
     /// Output only summary
     summary_only: bool,
@@ -42,18 +67,95 @@ struct Args {
     /// Increase verbosity. Repeat to print each file as it is scanned:
     /// -v main pass, -vv parsing, -vvv indexing
     verbose: u8,
+    #[arg(short, long, default_value_t = 0)]
+    /// Number of analysis threads (0 = number of CPUs)
+    jobs: usize,
     #[arg(long)]
     /// Print per-rule per-file timing (min/max/avg/p90/p95/p99 + slowest files)
     debug_rule_timing: bool,
     #[arg(long)]
     /// Print per-rule cost/coverage stats (total time, %, violations, files, statements)
     debug_rule_stats: bool,
+    #[arg(long)]
+    /// Path to a baseline file; violations recorded in it are not reported
+    baseline: Option<String>,
+    #[arg(long)]
+    /// Write all current violations to the baseline file instead of failing
+    generate_baseline: bool,
+    #[arg(long)]
+    /// Path to a cache file; unchanged files are not analysed again
+    cache: Option<String>,
+    #[arg(long, conflicts_with = "files_from")]
+    /// Report only files changed since the merge base with this git ref
+    diff_base: Option<String>,
+    #[arg(long, requires = "diff_base")]
+    /// With --diff-base, report only violations on the changed lines
+    changed_lines: bool,
+    #[arg(long)]
+    /// Report only the files listed in this file, one per line ("-" for stdin)
+    files_from: Option<String>,
+    #[arg(long, conflicts_with_all = ["fix_dry_run", "generate_baseline"])]
+    /// Apply the automatic fixes of the reported violations
+    fix: bool,
+    #[arg(long, conflicts_with = "generate_baseline")]
+    /// Print the automatic fixes as a diff without applying them
+    fix_dry_run: bool,
+    #[arg(long, default_value = "info")]
+    /// Fail only on violations of this severity or above: info, minor, major, critical, blocker
+    fail_on: Severity,
+    #[arg(long)]
+    /// Fail when a file can't be read or parsed
+    fail_on_parse_error: bool,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Run a language server over stdio, for editor integration
+    Lsp,
+    /// Export the dependency graph of the classes, of the namespaces or the
+    /// inheritance tree, with their metrics
+    Graph {
+        #[arg(long, default_value = "class")]
+        /// Possible options: class, namespace, inheritance
+        graph: GraphKind,
+        #[arg(long, default_value = "dot")]
+        /// Possible options: dot, graphml, json
+        format: GraphFormat,
+        #[arg(long)]
+        /// Keep only the nodes in this namespace, e.g. App\Domain
+        namespace: Option<String>,
+        #[arg(long)]
+        /// Collapse the namespaces to this number of segments
+        depth: Option<usize>,
+        #[arg(long)]
+        /// Write the graph to this file instead of the standard output
+        output: Option<String>,
+    },
 }
 
 fn main() {
     std::env::set_var("RUST_BACKTRACE", "1");
     let args = Args::parse();
 
+    if let Some(Command::Lsp) = args.command {
+        if let Err(e) = lsp::run(&args.config) {
+            eprintln!("Language server error: {}", e);
+            process::exit(exitcode::SOFTWARE);
+        }
+        process::exit(exitcode::OK);
+    }
+
+    if let Some(Command::Graph {
+        graph,
+        format,
+        namespace,
+        depth,
+        output,
+    }) = &args.command
+    {
+        export_graph(&args, *graph, *format, namespace, *depth, output);
+    }
+
     let quiet = args.quiet;
 
     let paths = args.src;
@@ -64,13 +166,30 @@ fn main() {
         }
     }
 
-    let format = match outputs::Format::from_str(args.output_format.as_str()) {
-        Ok(format) => format,
-        Err(_) => {
-            println!("Invalid input format ({})", args.output_format.as_str());
-            process::exit(exitcode::USAGE);
+    let reports = if args.report.is_empty() {
+        match outputs::Format::from_str(args.output_format.as_str()) {
+            Ok(format) => vec![Report { format, path: None }],
+            Err(_) => {
+                println!("Invalid input format ({})", args.output_format.as_str());
+                process::exit(exitcode::USAGE);
+            }
         }
+    } else {
+        args.report.clone()
     };
+    if reports.iter().filter(|report| report.path.is_none()).count() > 1 {
+        println!("Only one report can be written to the standard output");
+        process::exit(exitcode::USAGE);
+    }
+    // The report on the standard output decides what else can be printed there
+    let format = reports
+        .iter()
+        .find(|report| report.path.is_none())
+        .map_or(Format::text, |report| report.format.clone());
+    let reports: Vec<Report> = reports
+        .into_iter()
+        .filter(|report| !quiet || report.path.is_some())
+        .collect();
 
     let mut config = Analyse::parse_config(args.config, &format, quiet);
     if let Some(rules) = args.rules {
@@ -78,7 +197,48 @@ fn main() {
     }
     let mut analyze = Analyse::new(&config);
 
-    let mut has_violations = false;
+    let baseline_path = PathBuf::from(
+        args.baseline
+            .clone()
+            .unwrap_or_else(|| "./phanalist-baseline.json".to_string()),
+    );
+    let baseline = match &args.baseline {
+        Some(_) if !args.generate_baseline => match Baseline::load(&baseline_path) {
+            Ok(baseline) => Some(baseline),
+            Err(e) => {
+                println!("Unable to read baseline {}: {}", baseline_path.display(), e);
+                process::exit(exitcode::IOERR);
+            }
+        },
+        _ => None,
+    };
+    let mut new_baseline = Baseline::default();
+
+    let cache_path = args.cache.map(PathBuf::from);
+    let mut cache = cache_path
+        .as_ref()
+        .map(|cache_path| Cache::load(cache_path, &config));
+
+    let changes = if let Some(base) = &args.diff_base {
+        ChangedFiles::from_git(base, args.changed_lines)
+    } else if let Some(files_from) = &args.files_from {
+        match files_from.as_str() {
+            "-" => ChangedFiles::from_list(io::stdin().lock()),
+            path => fs::File::open(path).and_then(|f| ChangedFiles::from_list(BufReader::new(f))),
+        }
+    } else {
+        Ok(ChangedFiles::default())
+    };
+    let changes = match changes {
+        Ok(changes) => changes,
+        Err(e) => {
+            println!("Unable to get the changed files: {}", e);
+            process::exit(exitcode::IOERR);
+        }
+    };
+    let changes = (args.diff_base.is_some() || args.files_from.is_some()).then_some(&changes);
+
+    let mut all_results = Results::default();
 
     let collect_rule_metrics = args.debug_rule_timing || args.debug_rule_stats;
 
@@ -94,23 +254,82 @@ fn main() {
             &format,
             args.verbose,
             collect_rule_metrics,
+            args.jobs,
+            cache.as_mut(),
+            changes,
         );
-        if !quiet {
-            analyze.output(&mut results, format.clone(), args.summary_only);
+        if args.generate_baseline {
+            new_baseline.extend(&results);
+            continue;
+        }
+        if let Some(baseline) = &baseline {
+            baseline.apply(&mut results);
+        }
+        if args.fix || args.fix_dry_run {
+            let fixes = fix::fix_results(&results);
+            if args.fix_dry_run {
+                // Only a text report can share the standard output with the diff
+                for file_fix in &fixes {
+                    if format == Format::text {
+                        print!("{}", file_fix.diff());
+                    } else {
+                        eprint!("{}", file_fix.diff());
+                    }
+                }
+            } else {
+                if let Err(e) = fix::apply(&mut results, &fixes) {
+                    eprintln!("Unable to apply the fixes: {}", e);
+                    process::exit(exitcode::IOERR);
+                }
+                if !quiet && format == Format::text {
+                    let fixed: usize = fixes.iter().map(|f| f.fixed_violations.len()).sum();
+                    println!("Fixed {} violations in {} files", fixed, fixes.len());
+                }
+            }
+        }
+        all_results.merge(results);
+    }
+
+    let has_violations = all_results.has_violations_at(args.fail_on)
+        || (args.fail_on_parse_error && all_results.has_parse_errors());
+
+    if !args.generate_baseline {
+        all_results.sort(args.sort);
+        if let Err(e) = analyze.output(&mut all_results, &reports, args.summary_only) {
+            eprintln!("{}", e);
+            process::exit(exitcode::IOERR);
         }
 
         if collect_rule_metrics && format == Format::text {
-            if let Some(rt) = &results.rule_timings {
+            if let Some(rt) = &all_results.rule_timings {
                 rt.print_text(
-                    &results.codes_count,
-                    results.total_files_count,
+                    &all_results.codes_count,
+                    all_results.total_files_count,
                     args.debug_rule_timing,
                     args.debug_rule_stats,
                 );
             }
         }
+    }
+
+    if let (Some(cache), Some(cache_path)) = (&cache, &cache_path) {
+        if let Err(e) = cache.save(cache_path) {
+            eprintln!("Unable to save cache {}: {}", cache_path.display(), e);
+        }
+    }
 
-        has_violations = has_violations || results.has_any_violations();
+    if args.generate_baseline {
+        if let Err(e) = new_baseline.save(&baseline_path) {
+            println!("Unable to save baseline {}: {}", baseline_path.display(), e);
+            process::exit(exitcode::IOERR);
+        }
+        if !quiet {
+            println!(
+                "Baseline with {} entries written to {}",
+                new_baseline.entries.len(),
+                baseline_path.display()
+            );
+        }
     }
 
     if has_violations {
@@ -119,3 +338,45 @@ fn main() {
         process::exit(exitcode::OK);
     }
 }
+
+fn export_graph(
+    args: &Args,
+    kind: GraphKind,
+    format: GraphFormat,
+    namespace: &Option<String>,
+    depth: Option<usize>,
+    output: &Option<String>,
+) -> ! {
+    for path in args.src.iter() {
+        if !Path::new(&path).exists() {
+            eprintln!("Path {} does not exist", path);
+            process::exit(exitcode::IOERR);
+        }
+    }
+
+    // Like the language server, the graph doesn't create a missing configuration
+    let config = if Path::new(&args.config).exists() {
+        Analyse::parse_config(args.config.clone(), &Format::json, true)
+    } else {
+        Config::default()
+    };
+    let project = Project::scan(&args.src, &config.path_filter());
+    let graph = project
+        .graph(&Options {
+            kind,
+            namespace: namespace.clone(),
+            depth,
+        })
+        .render(format);
+
+    match output {
+        Some(output) => {
+            if let Err(e) = fs::write(output, graph) {
+                eprintln!("Unable to write the graph to {}: {}", output, e);
+                process::exit(exitcode::IOERR);
+            }
+        }
+        None => print!("{}", graph),
+    }
+    process::exit(exitcode::OK);
+}
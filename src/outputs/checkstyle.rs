@@ -0,0 +1,121 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+use crate::results::{Results, Severity};
+
+use super::{escape, OutputFormatter, RuleInfo};
+
+/// Checkstyle knows the `info`, `warning` and `error` severities.
+fn severity(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "info",
+        Severity::Minor | Severity::Major => "warning",
+        Severity::Critical | Severity::Blocker => "error",
+    }
+}
+
+pub struct Checkstyle {}
+
+impl OutputFormatter for Checkstyle {
+    /// Produce output for the Checkstyle XML format (Jenkins, Bamboo, TeamCity...)
+    fn output(
+        out: &mut dyn Write,
+        results: &Results,
+        _rules: &BTreeMap<String, RuleInfo>,
+    ) -> io::Result<()> {
+        write!(out, "{}", Self::render(results))
+    }
+}
+
+impl Checkstyle {
+    fn render(results: &Results) -> String {
+        let paths = results.sorted_paths();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        xml.push_str("<checkstyle version=\"4.3\">\n");
+        for path in paths {
+            let _ = writeln!(xml, "  <file name=\"{}\">", escape(path));
+            for error in results.parse_errors.get(path).into_iter().flatten() {
+                let _ = writeln!(
+                    xml,
+                    "    <error line=\"{}\" column=\"{}\" severity=\"error\" message=\"{}\" source=\"phanalist.parse-error\"/>",
+                    error.start_line.max(1),
+                    error.start_column + 1,
+                    escape(&error.message)
+                );
+            }
+            for violation in results.files.get(path).into_iter().flatten() {
+                let _ = writeln!(
+                    xml,
+                    "    <error line=\"{}\" column=\"{}\" severity=\"{}\" message=\"{}\" source=\"phanalist.{}\"/>",
+                    violation.start_line,
+                    violation.start_column + 1,
+                    severity(violation.severity),
+                    escape(&violation.suggestion),
+                    escape(&violation.rule)
+                );
+            }
+            xml.push_str("  </file>\n");
+        }
+        xml.push_str("</checkstyle>\n");
+
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::results::{ParseError, Violation};
+
+    #[test]
+    fn violations_and_parse_errors() {
+        let violation = Violation {
+            rule: String::from("E0009"),
+            severity: Severity::Critical,
+            line: String::new(),
+            suggestion: String::from("The method run has a cognitive complexity of 20 > 10"),
+            start_line: 4,
+            start_column: 4,
+            end_line: 9,
+            end_column: 5,
+            symbol: None,
+            fingerprint: String::new(),
+            edits: vec![],
+            related_locations: vec![],
+        };
+        let results = Results {
+            files: BTreeMap::from([(String::from("src/A&B.php"), vec![violation])]),
+            parse_errors: BTreeMap::from([(
+                String::from("src/broken.php"),
+                vec![ParseError {
+                    message: String::from("Unexpected token \"}\""),
+                    start_line: 3,
+                    start_column: 0,
+                    end_line: 3,
+                    end_column: 1,
+                }],
+            )]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            Checkstyle::render(&results),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<checkstyle version=\"4.3\">
+  <file name=\"src/A&amp;B.php\">
+    <error line=\"4\" column=\"5\" severity=\"error\" message=\"The method run has a cognitive complexity of 20 &gt; 10\" source=\"phanalist.E0009\"/>
+  </file>
+  <file name=\"src/broken.php\">
+    <error line=\"3\" column=\"1\" severity=\"error\" message=\"Unexpected token &quot;}&quot;\" source=\"phanalist.parse-error\"/>
+  </file>
+</checkstyle>
+"
+        );
+    }
+}
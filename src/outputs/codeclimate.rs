@@ -1,28 +1,33 @@
-use crate::{results::Results, rules};
+use crate::results::{self, Results};
 
-use super::OutputFormatter;
+use super::{OutputFormatter, RuleInfo};
 
 use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
 
 pub struct CodeClimate {}
 
 impl OutputFormatter for CodeClimate {
     /// Produce output for CodeClimate format (that is also Gitlab-compatible)
-    fn output(results: &mut Results) {
+    fn output(
+        out: &mut dyn Write,
+        results: &Results,
+        rules: &BTreeMap<String, RuleInfo>,
+    ) -> io::Result<()> {
         // CodeClimate spec:
         // https://github.com/codeclimate/platform/blob/master/spec/analyzers/SPEC.md#data-types
 
-        let rules = rules::all_rules();
         let mut res: Vec<Value> = vec![];
-        for (key, violations) in &results.files {
+        for (key, violations) in results.sorted_files() {
             for violation in violations {
                 let rule_id = &violation.rule;
                 let rule_markdown = match rules.get(rule_id) {
-                    Some(rule) => rule.get_detailed_explanation().unwrap_or_default(),
+                    Some(rule) => rule.explanation.clone().unwrap_or_default(),
                     None => String::from("Unknown rule"),
                 };
 
-                res.push(json!({
+                let mut issue = json!({
                     "type": "issue",
                     "check_name": &violation.rule,
                     "description": &violation.suggestion,
@@ -30,8 +35,8 @@ impl OutputFormatter for CodeClimate {
                         "body": &rule_markdown
                     },
                     "categories": ["Complexity"],
-                    "fingerprint": "",
-                    "severity": "major",
+                    "fingerprint": &violation.fingerprint,
+                    "severity": violation.severity,
                     "location": {
                         "path": &key,
                         "positions": {
@@ -45,13 +50,59 @@ impl OutputFormatter for CodeClimate {
                             },
                         }
                     }
-                }));
+                });
+                if !violation.related_locations.is_empty() {
+                    issue["other_locations"] = violation
+                        .related_locations
+                        .iter()
+                        .map(|related| {
+                            json!({
+                                "path": &related.path,
+                                "positions": {
+                                    "begin": {
+                                        "line": related.start_line,
+                                        "column": related.start_column,
+                                    },
+                                    "end": {
+                                        "line": related.end_line,
+                                        "column": related.end_column,
+                                    },
+                                }
+                            })
+                        })
+                        .collect();
+                }
+                res.push(issue);
             }
         }
 
-        match serde_json::to_string(&res) {
-            Ok(output) => println!("{}", output),
-            Err(e) => eprintln!("Erreur de sérialisation JSON: {}", e),
+        for (key, errors) in &results.parse_errors {
+            for error in errors {
+                res.push(json!({
+                    "type": "issue",
+                    "check_name": "parse-error",
+                    "description": &error.message,
+                    "categories": ["Bug Risk"],
+                    "fingerprint": results::fingerprint("parse-error", key, None, &error.message),
+                    "severity": "critical",
+                    "location": {
+                        "path": &key,
+                        "positions": {
+                            "begin": {
+                                "line": error.start_line.max(1),
+                                "column": error.start_column,
+                            },
+                            "end": {
+                                "line": error.end_line.max(1),
+                                "column": error.end_column,
+                            },
+                        }
+                    }
+                }));
+            }
         }
+
+        serde_json::to_writer(&mut *out, &res)?;
+        writeln!(out)
     }
 }
@@ -0,0 +1,198 @@
+use std::{
+    collections::BTreeMap,
+    env,
+    fmt::Write as _,
+    fs::OpenOptions,
+    io::{self, Write},
+};
+
+use crate::results::{Results, Severity};
+
+use super::{OutputFormatter, RuleInfo};
+
+/// Workflow commands have the `notice`, `warning` and `error` levels.
+fn level(severity: Severity) -> &'static str {
+    match severity {
+        Severity::Info => "notice",
+        Severity::Minor | Severity::Major => "warning",
+        Severity::Critical | Severity::Blocker => "error",
+    }
+}
+
+/// Escape the message of a workflow command.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape a property value of a workflow command, like the file path.
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+pub struct GitHub {}
+
+impl OutputFormatter for GitHub {
+    /// Produce GitHub Actions workflow commands, which annotate the pull request diff.
+    /// The summary table goes to `$GITHUB_STEP_SUMMARY` when the variable is set.
+    fn output(
+        out: &mut dyn Write,
+        results: &Results,
+        rules: &BTreeMap<String, RuleInfo>,
+    ) -> io::Result<()> {
+        write!(out, "{}", Self::annotations(results))?;
+
+        if let Some(path) = env::var_os("GITHUB_STEP_SUMMARY").filter(|path| !path.is_empty()) {
+            let written = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(&path)
+                .and_then(|mut file| file.write_all(Self::summary(results, rules).as_bytes()));
+            if let Err(e) = written {
+                eprintln!(
+                    "Unable to write the step summary to {}: {}",
+                    path.to_string_lossy(),
+                    e
+                );
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl GitHub {
+    fn annotations(results: &Results) -> String {
+        let mut output = String::new();
+
+        for (path, errors) in &results.parse_errors {
+            for error in errors {
+                let _ = writeln!(
+                    output,
+                    "::error file={},line={},col={},title=parse-error::{}",
+                    escape_property(path),
+                    error.start_line.max(1),
+                    error.start_column + 1,
+                    escape_data(&error.message)
+                );
+            }
+        }
+
+        for (path, violations) in results.sorted_files() {
+            for violation in violations {
+                let _ = writeln!(
+                    output,
+                    "::{} file={},line={},endLine={},col={},endColumn={},title={}::{}",
+                    level(violation.severity),
+                    escape_property(path),
+                    violation.start_line,
+                    violation.end_line,
+                    violation.start_column + 1,
+                    violation.end_column.max(1),
+                    escape_property(&violation.rule),
+                    escape_data(&violation.suggestion)
+                );
+            }
+        }
+
+        output
+    }
+
+    /// Markdown table of the violations per rule.
+    fn summary(results: &Results, rules: &BTreeMap<String, RuleInfo>) -> String {
+        let violations: i64 = results.codes_count.values().sum();
+
+        let mut summary = String::from("### Phanalist\n\n");
+        let _ = writeln!(
+            summary,
+            "Analysed {} files, detected {} violations.\n",
+            results.total_files_count, violations
+        );
+        if !results.parse_errors.is_empty() {
+            let _ = writeln!(
+                summary,
+                "{} files couldn't be parsed.\n",
+                results.parse_errors.len()
+            );
+        }
+
+        let codes = results.sorted_codes_count();
+        if !codes.is_empty() {
+            summary.push_str("| Rule | Description | Violations |\n|---|---|---:|\n");
+            for (code, count) in codes {
+                let description = rules
+                    .get(code.as_str())
+                    .map(|rule| rule.description.as_str())
+                    .unwrap_or_default();
+                let _ = writeln!(
+                    summary,
+                    "| {} | {} | {} |",
+                    code,
+                    description.replace('|', "\\|"),
+                    count
+                );
+            }
+            summary.push('\n');
+        }
+
+        summary
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::results::Violation;
+
+    fn results() -> Results {
+        let violation = Violation {
+            rule: String::from("E0009"),
+            severity: Severity::Critical,
+            line: String::new(),
+            suggestion: String::from("Cognitive complexity of 20%\nis too high"),
+            start_line: 4,
+            start_column: 4,
+            end_line: 9,
+            end_column: 5,
+            symbol: None,
+            fingerprint: String::new(),
+            edits: vec![],
+            related_locations: vec![],
+        };
+
+        Results {
+            files: BTreeMap::from([(String::from("src/a,b.php"), vec![violation])]),
+            codes_count: BTreeMap::from([(String::from("E0009"), 1)]),
+            total_files_count: 2,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn annotations() {
+        assert_eq!(
+            GitHub::annotations(&results()),
+            "::error file=src/a%2Cb.php,line=4,endLine=9,col=5,endColumn=5,title=E0009::Cognitive complexity of 20%25%0Ais too high\n"
+        );
+    }
+
+    #[test]
+    fn summary() {
+        let rules = BTreeMap::from([(
+            String::from("E0009"),
+            RuleInfo {
+                description: String::from("Cognitive complexity"),
+                ..Default::default()
+            },
+        )]);
+        let summary = GitHub::summary(&results(), &rules);
+
+        assert!(summary.contains("Analysed 2 files, detected 1 violations."));
+        assert!(summary.contains(
+            "| Rule | Description | Violations |\n|---|---|---:|\n| E0009 | Cognitive complexity | 1 |\n"
+        ));
+    }
+}
@@ -0,0 +1,401 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    io::{self, Write},
+};
+
+use pulldown_cmark::{html::push_html, Options, Parser};
+
+use crate::results::{Results, Violation};
+
+use super::{escape, OutputFormatter, RuleInfo};
+
+/// Lines of source shown around the violating span.
+const CONTEXT_LINES: usize = 2;
+
+const STYLE: &str = r#"
+body { font-family: -apple-system, "Segoe UI", Helvetica, Arial, sans-serif; margin: 0 auto; max-width: 72rem; padding: 1rem 2rem; color: #1f2328; }
+h1 { margin-bottom: 0.2rem; }
+.totals { color: #59636e; margin-top: 0; }
+table { border-collapse: collapse; width: 100%; margin-bottom: 1.5rem; }
+th, td { border-bottom: 1px solid #d1d9e0; padding: 0.3rem 0.6rem; text-align: left; }
+td.number, th.number { text-align: right; }
+table.sortable th { cursor: pointer; user-select: none; }
+table.sortable th[data-order="asc"]::after { content: " \25B2"; }
+table.sortable th[data-order="desc"]::after { content: " \25BC"; }
+section.file { margin-bottom: 2rem; }
+.violation { border-left: 4px solid #d1d9e0; margin: 0.8rem 0; padding: 0.2rem 0.8rem; }
+.violation.info { border-color: #0969da; }
+.violation.minor { border-color: #bf8700; }
+.violation.major { border-color: #bc4c00; }
+.violation.critical, .violation.blocker, .violation.parse-error { border-color: #cf222e; }
+.severity { border-radius: 0.8rem; font-size: 0.8rem; padding: 0.05rem 0.5rem; background: #eff2f5; }
+.position { color: #59636e; font-family: monospace; }
+pre.snippet { background: #f6f8fa; overflow-x: auto; padding: 0.5rem 0; }
+pre.snippet span.line { display: block; padding: 0 0.5rem; }
+pre.snippet span.number { color: #8c959f; display: inline-block; min-width: 3rem; user-select: none; }
+pre.snippet mark { background: #ffd8b5; }
+ul.related { color: #59636e; }
+details.rule { border: 1px solid #d1d9e0; border-radius: 0.4rem; margin: 0.5rem 0; padding: 0.5rem 1rem; }
+details.rule summary { cursor: pointer; font-weight: bold; }
+details.rule pre { background: #f6f8fa; overflow-x: auto; padding: 0.5rem; }
+"#;
+
+/// Sorts the tables with the `sortable` class by the clicked column,
+/// comparing the `data-value` of the cells when they have one.
+const SCRIPT: &str = r#"
+document.querySelectorAll("table.sortable").forEach(function (table) {
+  table.querySelectorAll("th").forEach(function (header, column) {
+    header.addEventListener("click", function () {
+      var order = header.dataset.order === "asc" ? "desc" : "asc";
+      table.querySelectorAll("th").forEach(function (other) { delete other.dataset.order; });
+      header.dataset.order = order;
+      var body = table.tBodies[0];
+      var rows = Array.prototype.slice.call(body.rows);
+      rows.sort(function (a, b) {
+        var x = a.cells[column], y = b.cells[column];
+        var left = x.dataset.value !== undefined ? Number(x.dataset.value) : x.textContent;
+        var right = y.dataset.value !== undefined ? Number(y.dataset.value) : y.textContent;
+        var result = typeof left === "number" ? left - right : left.localeCompare(right);
+        return order === "asc" ? result : -result;
+      });
+      rows.forEach(function (row) { body.appendChild(row); });
+    });
+  });
+});
+"#;
+
+pub struct Html {}
+
+impl OutputFormatter for Html {
+    /// Produce a single HTML page which can be opened without network access
+    fn output(
+        out: &mut dyn Write,
+        results: &Results,
+        rules: &BTreeMap<String, RuleInfo>,
+    ) -> io::Result<()> {
+        writeln!(out, "{}", Self::render(results, rules))
+    }
+}
+
+impl Html {
+    fn render(results: &Results, rules: &BTreeMap<String, RuleInfo>) -> String {
+        let violations_count: usize = results.files.values().map(Vec::len).sum();
+
+        // Clean files have no section, so their source isn't read either
+        let mut files = results.sorted_files();
+        files.retain(|(_, violations)| !violations.is_empty());
+        let codes = results.sorted_codes_count();
+
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<title>Phanalist report</title>\n");
+        let _ = writeln!(html, "<style>{STYLE}</style>\n</head>\n<body>");
+        html.push_str("<h1>Phanalist report</h1>\n");
+        let mut totals = format!(
+            "Analysed {} files, detected {} violations",
+            results.total_files_count, violations_count
+        );
+        if let Some(duration) = results.duration {
+            let _ = write!(totals, " in {duration:.2?}");
+        }
+        let _ = writeln!(html, "<p class=\"totals\">{}</p>", escape(&totals));
+
+        if !codes.is_empty() {
+            html.push_str("<h2>Rules</h2>\n<table class=\"sortable\">\n<thead><tr><th>Rule</th><th>Description</th><th class=\"number\">Violations</th></tr></thead>\n<tbody>\n");
+            for (code, count) in &codes {
+                let description = rules
+                    .get(code.as_str())
+                    .map(|rule| rule.description.as_str())
+                    .unwrap_or_default();
+                let _ = writeln!(
+                    html,
+                    "<tr><td><a href=\"#rule-{code}\">{code}</a></td><td>{}</td><td class=\"number\" data-value=\"{count}\">{count}</td></tr>",
+                    escape(description),
+                    code = escape(code),
+                );
+            }
+            html.push_str("</tbody>\n</table>\n");
+        }
+
+        if !files.is_empty() {
+            html.push_str("<h2>Files</h2>\n<table class=\"sortable\">\n<thead><tr><th>File</th><th class=\"number\">Violations</th><th>Highest severity</th></tr></thead>\n<tbody>\n");
+            for (index, (path, violations)) in files.iter().enumerate() {
+                let severity = violations
+                    .iter()
+                    .map(|violation| violation.severity)
+                    .max()
+                    .unwrap_or_default();
+                let _ = writeln!(
+                    html,
+                    "<tr><td><a href=\"#file-{index}\">{}</a></td><td class=\"number\" data-value=\"{count}\">{count}</td><td data-value=\"{}\">{severity}</td></tr>",
+                    escape(path),
+                    severity as u8,
+                    count = violations.len(),
+                );
+            }
+            html.push_str("</tbody>\n</table>\n");
+        }
+
+        if !results.parse_errors.is_empty() {
+            html.push_str("<h2>Parse errors</h2>\n");
+            for (path, errors) in &results.parse_errors {
+                let _ = writeln!(html, "<section class=\"file\">\n<h3>{}</h3>", escape(path));
+                for error in errors {
+                    html.push_str("<div class=\"violation parse-error\">\n");
+                    if error.start_line > 0 {
+                        let _ = write!(
+                            html,
+                            "<span class=\"position\">{}:{}</span> ",
+                            error.start_line,
+                            error.start_column + 1
+                        );
+                    }
+                    let _ = writeln!(html, "{}\n</div>", escape(&error.message));
+                }
+                html.push_str("</section>\n");
+            }
+        }
+
+        if !files.is_empty() {
+            html.push_str("<h2>Violations</h2>\n");
+            for (index, (path, violations)) in files.iter().enumerate() {
+                let source = fs::read_to_string(path.as_str()).ok();
+                let lines: Option<Vec<&str>> = source.as_deref().map(|s| s.lines().collect());
+                let _ = writeln!(
+                    html,
+                    "<section class=\"file\" id=\"file-{index}\">\n<h3>{}</h3>",
+                    escape(path)
+                );
+                for violation in violations.iter() {
+                    Self::render_violation(&mut html, violation, lines.as_deref());
+                }
+                html.push_str("</section>\n");
+            }
+        }
+
+        if !codes.is_empty() {
+            html.push_str("<h2>Rule explanations</h2>\n");
+            for (code, _) in &codes {
+                let rule = rules.get(code.as_str());
+                let description = rule
+                    .map(|rule| rule.description.as_str())
+                    .unwrap_or_default();
+                let explanation = match rule.and_then(|rule| rule.explanation.as_deref()) {
+                    Some(markdown) => markdown_to_html(markdown),
+                    None => format!("<p>{}</p>", escape(description)),
+                };
+                let _ = writeln!(
+                    html,
+                    "<details class=\"rule\" id=\"rule-{code}\">\n<summary>{code} {}</summary>\n{explanation}</details>",
+                    escape(description),
+                    code = escape(code),
+                );
+            }
+        }
+
+        let _ = writeln!(html, "<script>{SCRIPT}</script>\n</body>\n</html>");
+
+        html
+    }
+
+    fn render_violation(html: &mut String, violation: &Violation, lines: Option<&[&str]>) {
+        let severity = violation.severity;
+        let _ = writeln!(
+            html,
+            "<div class=\"violation {severity}\">\n<p><a href=\"#rule-{rule}\"><strong>{rule}</strong></a> <span class=\"severity\">{severity}</span> <span class=\"position\">{}:{}</span> {}</p>",
+            violation.start_line,
+            violation.start_column + 1,
+            escape(&violation.suggestion),
+            rule = escape(&violation.rule),
+        );
+
+        match lines {
+            Some(lines) if violation.start_line > 0 && violation.start_line <= lines.len() => {
+                html.push_str(&snippet(lines, violation));
+            }
+            _ if !violation.line.is_empty() => {
+                let _ = writeln!(
+                    html,
+                    "<pre class=\"snippet\"><span class=\"line\"><span class=\"number\">{}</span>{}</span></pre>",
+                    violation.start_line,
+                    escape(&violation.line)
+                );
+            }
+            _ => {}
+        }
+
+        if !violation.related_locations.is_empty() {
+            html.push_str("<ul class=\"related\">\n");
+            for related in &violation.related_locations {
+                let _ = writeln!(
+                    html,
+                    "<li><span class=\"position\">{}:{}:{}</span> {}</li>",
+                    escape(&related.path),
+                    related.start_line,
+                    related.start_column + 1,
+                    escape(&related.message)
+                );
+            }
+            html.push_str("</ul>\n");
+        }
+        html.push_str("</div>\n");
+    }
+}
+
+/// Numbered source lines around the violation, its span wrapped in `<mark>`.
+fn snippet(lines: &[&str], violation: &Violation) -> String {
+    let start = violation.start_line;
+    let end = violation.end_line.clamp(start, lines.len());
+    let first = start.saturating_sub(CONTEXT_LINES).max(1);
+    let last = (end + CONTEXT_LINES).min(lines.len());
+
+    let mut html = String::from("<pre class=\"snippet\">");
+    for number in first..=last {
+        let line = lines[number - 1];
+        let _ = write!(
+            html,
+            "<span class=\"line\"><span class=\"number\">{number}</span>"
+        );
+        if number < start || number > end {
+            html.push_str(&escape(line));
+        } else {
+            let from = if number == start {
+                violation.start_column
+            } else {
+                0
+            };
+            let to = if number == end {
+                violation.end_column
+            } else {
+                line.len()
+            };
+            html.push_str(&highlight(line, from, to));
+        }
+        html.push_str("</span>");
+    }
+    html.push_str("</pre>\n");
+
+    html
+}
+
+/// Wrap the bytes `from..to` of the line in `<mark>`, moved back to the
+/// nearest character boundaries.
+fn highlight(line: &str, from: usize, to: usize) -> String {
+    let boundary = |mut index: usize| {
+        index = index.min(line.len());
+        while !line.is_char_boundary(index) {
+            index -= 1;
+        }
+        index
+    };
+    let from = boundary(from);
+    let to = boundary(to).max(from);
+    if from == to {
+        return escape(line);
+    }
+
+    format!(
+        "{}<mark>{}</mark>{}",
+        escape(&line[..from]),
+        escape(&line[from..to]),
+        escape(&line[to..])
+    )
+}
+
+fn markdown_to_html(markdown: &str) -> String {
+    let mut html = String::new();
+    push_html(&mut html, Parser::new_ext(markdown, Options::ENABLE_TABLES));
+
+    html
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::results::Severity;
+
+    fn violation(start: (usize, usize), end: (usize, usize)) -> Violation {
+        Violation {
+            rule: String::from("E0001"),
+            severity: Severity::Major,
+            line: String::new(),
+            suggestion: String::from("Opening tag <?php is missing"),
+            start_line: start.0,
+            start_column: start.1,
+            end_line: end.0,
+            end_column: end.1,
+            symbol: None,
+            fingerprint: String::new(),
+            edits: vec![],
+            related_locations: vec![],
+        }
+    }
+
+    #[test]
+    fn highlight_escapes_and_marks_the_span() {
+        assert_eq!(
+            highlight("if ($a < $b) {", 4, 11),
+            "if (<mark>$a &lt; $b</mark>) {"
+        );
+        assert_eq!(highlight("$é = 1;", 0, 2), "<mark>$</mark>é = 1;");
+        assert_eq!(highlight("echo 1;", 20, 30), "echo 1;");
+    }
+
+    #[test]
+    fn snippet_marks_every_line_of_the_span() {
+        let lines = vec!["<?php", "", "function run() {", "    return 1;", "}", ""];
+        let html = snippet(&lines, &violation((3, 9), (5, 1)));
+
+        assert!(html.contains("<span class=\"number\">1</span>&lt;?php"));
+        assert!(html.contains("function <mark>run() {</mark>"));
+        assert!(html.contains("<mark>    return 1;</mark>"));
+        assert!(html.contains("<span class=\"number\">5</span><mark>}</mark>"));
+        assert!(html.contains("<span class=\"number\">6</span></span>"));
+    }
+
+    #[test]
+    fn report_is_self_contained() {
+        let results = Results {
+            files: BTreeMap::from([(
+                String::from("missing/<file>.php"),
+                vec![violation((1, 0), (1, 5))],
+            )]),
+            codes_count: BTreeMap::from([(String::from("E0001"), 1)]),
+            total_files_count: 1,
+            ..Default::default()
+        };
+        let html = Html::render(&results, &BTreeMap::new());
+
+        assert!(html.contains("<a href=\"#rule-E0001\">E0001</a>"));
+        assert!(html.contains("<h3>missing/&lt;file&gt;.php</h3>"));
+        assert!(html.contains("Opening tag &lt;?php is missing"));
+        assert!(html.contains("<details class=\"rule\" id=\"rule-E0001\">"));
+        assert!(!html.contains("src=\"http"));
+        assert!(!html.contains("href=\"http"));
+    }
+
+    #[test]
+    fn clean_files_are_left_out() {
+        let results = Results {
+            files: BTreeMap::from([
+                (String::from("src/Clean.php"), vec![]),
+                (
+                    String::from("src/Dirty.php"),
+                    vec![violation((1, 0), (1, 5))],
+                ),
+            ]),
+            codes_count: BTreeMap::from([(String::from("E0001"), 1)]),
+            total_files_count: 2,
+            ..Default::default()
+        };
+        let html = Html::render(&results, &BTreeMap::new());
+
+        assert!(html.contains("<a href=\"#file-0\">src/Dirty.php</a>"));
+        assert!(html.contains("<section class=\"file\" id=\"file-0\">"));
+        assert!(!html.contains("src/Clean.php"));
+    }
+}
@@ -1,10 +1,89 @@
-use crate::results::Results;
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::time::Duration;
 
-use super::OutputFormatter;
+use serde::{Serialize, Serializer};
+
+use crate::results::{ParseError, Results, Violation};
+
+use super::{OutputFormatter, RuleInfo};
+
+/// `Results` with the files in the order of `--sort`, which JSON objects keep.
+#[derive(Serialize)]
+struct SortedResults<'a> {
+    #[serde(serialize_with = "serialize_files")]
+    files: Vec<(&'a String, &'a Vec<Violation>)>,
+    parse_errors: &'a BTreeMap<String, Vec<ParseError>>,
+    codes_count: &'a BTreeMap<String, i64>,
+    total_files_count: i64,
+    duration: Option<Duration>,
+}
+
+fn serialize_files<S: Serializer>(
+    files: &[(&String, &Vec<Violation>)],
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(files.iter().copied())
+}
 
 pub struct Json {}
 impl OutputFormatter for Json {
-    fn output(results: &mut Results) {
-        println!("{}", serde_json::to_string_pretty(&results).unwrap());
+    fn output(
+        out: &mut dyn Write,
+        results: &Results,
+        _rules: &BTreeMap<String, RuleInfo>,
+    ) -> io::Result<()> {
+        let sorted = SortedResults {
+            files: results.sorted_files(),
+            parse_errors: &results.parse_errors,
+            codes_count: &results.codes_count,
+            total_files_count: results.total_files_count,
+            duration: results.duration,
+        };
+        serde_json::to_writer_pretty(&mut *out, &sorted)?;
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::results::{Severity, Sort};
+
+    fn violation(severity: Severity) -> Violation {
+        Violation {
+            rule: String::from("E0001"),
+            severity,
+            line: String::new(),
+            suggestion: String::new(),
+            start_line: 1,
+            start_column: 0,
+            end_line: 1,
+            end_column: 5,
+            symbol: None,
+            fingerprint: String::new(),
+            edits: vec![],
+            related_locations: vec![],
+        }
+    }
+
+    #[test]
+    fn files_follow_the_sort() {
+        let mut results = Results {
+            files: BTreeMap::from([
+                (String::from("a.php"), vec![violation(Severity::Minor)]),
+                (String::from("b.php"), vec![violation(Severity::Critical)]),
+            ]),
+            ..Default::default()
+        };
+        results.sort(Sort::Severity);
+
+        let mut out = vec![];
+        Json::output(&mut out, &results, &BTreeMap::new()).unwrap();
+        let json = String::from_utf8(out).unwrap();
+
+        assert!(json.find("\"b.php\"").unwrap() < json.find("\"a.php\"").unwrap());
+        let parsed: Results = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.files.len(), 2);
     }
 }
@@ -0,0 +1,161 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    io::{self, Write},
+};
+
+use crate::results::Results;
+
+use super::{escape, OutputFormatter, RuleInfo};
+
+pub struct JUnit {}
+
+impl OutputFormatter for JUnit {
+    /// Produce output for the JUnit XML format, one test case per file
+    fn output(
+        out: &mut dyn Write,
+        results: &Results,
+        _rules: &BTreeMap<String, RuleInfo>,
+    ) -> io::Result<()> {
+        write!(out, "{}", Self::render(results))
+    }
+}
+
+impl JUnit {
+    fn render(results: &Results) -> String {
+        let paths = results.sorted_paths();
+
+        let failures = results.files.values().filter(|v| !v.is_empty()).count();
+        let errors = results.parse_errors.len();
+        // A report without any test case reads as "no tests ran" in most
+        // dashboards, so a clean run has a single passing one.
+        let tests = paths.len().max(1);
+        let time = results
+            .duration
+            .map(|duration| duration.as_secs_f64())
+            .unwrap_or_default();
+
+        let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+        let _ = writeln!(
+            xml,
+            "<testsuites name=\"phanalist\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\" time=\"{time:.3}\">"
+        );
+        let _ = writeln!(
+            xml,
+            "  <testsuite name=\"phanalist\" tests=\"{tests}\" failures=\"{failures}\" errors=\"{errors}\" time=\"{time:.3}\">"
+        );
+        if paths.is_empty() {
+            xml.push_str("    <testcase name=\"phanalist\" classname=\"phanalist\"/>\n");
+        }
+        for path in paths {
+            let _ = writeln!(
+                xml,
+                "    <testcase name=\"{path}\" classname=\"{path}\">",
+                path = escape(path)
+            );
+            if let Some(errors) = results.parse_errors.get(path) {
+                let _ = write!(
+                    xml,
+                    "      <error type=\"parse-error\" message=\"{} parse errors\">",
+                    errors.len()
+                );
+                for error in errors {
+                    let _ = writeln!(
+                        xml,
+                        "{}:{} {}",
+                        error.start_line,
+                        error.start_column + 1,
+                        escape(&error.message)
+                    );
+                }
+                xml.push_str("</error>\n");
+            }
+            if let Some(violations) = results.files.get(path).filter(|v| !v.is_empty()) {
+                let _ = write!(
+                    xml,
+                    "      <failure type=\"violations\" message=\"{} violations\">",
+                    violations.len()
+                );
+                for violation in violations {
+                    let _ = writeln!(
+                        xml,
+                        "{}:{} {} ({}): {}",
+                        violation.start_line,
+                        violation.start_column + 1,
+                        escape(&violation.rule),
+                        violation.severity,
+                        escape(&violation.suggestion)
+                    );
+                }
+                xml.push_str("</failure>\n");
+            }
+            xml.push_str("    </testcase>\n");
+        }
+        xml.push_str("  </testsuite>\n</testsuites>\n");
+
+        xml
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::results::{Severity, Violation};
+
+    fn violation(rule: &str, line: usize, suggestion: &str) -> Violation {
+        Violation {
+            rule: String::from(rule),
+            severity: Severity::Major,
+            line: String::new(),
+            suggestion: String::from(suggestion),
+            start_line: line,
+            start_column: 0,
+            end_line: line,
+            end_column: 10,
+            symbol: None,
+            fingerprint: String::new(),
+            edits: vec![],
+            related_locations: vec![],
+        }
+    }
+
+    #[test]
+    fn one_test_case_per_file() {
+        let results = Results {
+            files: BTreeMap::from([(
+                String::from("src/Service.php"),
+                vec![
+                    violation("E0001", 1, "Opening tag <?php is missing"),
+                    violation("E0006", 7, "Property $name is missing its type"),
+                ],
+            )]),
+            total_files_count: 3,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            JUnit::render(&results),
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<testsuites name=\"phanalist\" tests=\"1\" failures=\"1\" errors=\"0\" time=\"0.000\">
+  <testsuite name=\"phanalist\" tests=\"1\" failures=\"1\" errors=\"0\" time=\"0.000\">
+    <testcase name=\"src/Service.php\" classname=\"src/Service.php\">
+      <failure type=\"violations\" message=\"2 violations\">1:1 E0001 (major): Opening tag &lt;?php is missing
+7:1 E0006 (major): Property $name is missing its type
+</failure>
+    </testcase>
+  </testsuite>
+</testsuites>
+"
+        );
+    }
+
+    #[test]
+    fn clean_run_has_a_passing_test_case() {
+        let xml = JUnit::render(&Results::default());
+
+        assert!(xml.contains("tests=\"1\" failures=\"0\" errors=\"0\""));
+        assert!(xml.contains("<testcase name=\"phanalist\" classname=\"phanalist\"/>"));
+    }
+}
@@ -1,14 +1,39 @@
-use crate::results::Results;
+use crate::results::{Results, Severity};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+use std::path::PathBuf;
 use std::str::FromStr;
 
+pub mod checkstyle;
 pub mod codeclimate;
+pub mod github;
+pub mod html;
 pub mod json;
+pub mod junit;
 pub mod sarif;
 pub mod text;
 
 pub trait OutputFormatter {
-    fn output(_results: &mut Results) {}
+    /// `rules` describes the rules of the analysis, by code.
+    fn output(
+        out: &mut dyn Write,
+        results: &Results,
+        rules: &BTreeMap<String, RuleInfo>,
+    ) -> io::Result<()>;
+}
+
+/// What the reports tell about a rule, collected once from the rules of the
+/// analysis.
+#[derive(Clone, Debug, Default)]
+pub struct RuleInfo {
+    pub description: String,
+    pub explanation: Option<String>,
+    /// The severity set in the configuration, or the rule's default one.
+    pub severity: Severity,
+    /// Built-in rules are documented in the repository, unlike the custom
+    /// rules and the plugins.
+    pub built_in: bool,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
@@ -18,6 +43,10 @@ pub enum Format {
     json,
     sarif,
     codeclimate,
+    html,
+    checkstyle,
+    junit,
+    github,
 }
 
 impl FromStr for Format {
@@ -29,7 +58,93 @@ impl FromStr for Format {
             "json" => Ok(Format::json),
             "sarif" => Ok(Format::sarif),
             "codeclimate" => Ok(Format::codeclimate),
+            "html" => Ok(Format::html),
+            "checkstyle" => Ok(Format::checkstyle),
+            "junit" => Ok(Format::junit),
+            "github" => Ok(Format::github),
             _ => Err(()),
         }
     }
 }
+
+/// A format to render the results in and the file to write it to, given as
+/// `format:path` on the command line. Without a path, or with `-`, the report
+/// goes to the standard output.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Report {
+    pub format: Format,
+    pub path: Option<PathBuf>,
+}
+
+impl FromStr for Report {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Report, Self::Err> {
+        let (format, path) = match input.split_once(':') {
+            Some((format, path)) => (format, Some(path)),
+            None => (input, None),
+        };
+        let format = Format::from_str(format).map_err(|_| {
+            format!(
+                "unknown format \"{}\", possible options: text, json, sarif, codeclimate, html, checkstyle, junit, github",
+                format
+            )
+        })?;
+        let path = match path {
+            None | Some("-") => None,
+            Some("") => return Err(String::from("the path of the report is empty")),
+            Some(path) => Some(PathBuf::from(path)),
+        };
+
+        Ok(Report { format, path })
+    }
+}
+
+/// Escape text for the content and attribute values of HTML and XML documents.
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_report_from_str() {
+        assert_eq!(
+            Report::from_str("sarif:build/phanalist.sarif"),
+            Ok(Report {
+                format: Format::sarif,
+                path: Some(PathBuf::from("build/phanalist.sarif"))
+            })
+        );
+        assert_eq!(
+            Report::from_str("text:-"),
+            Ok(Report {
+                format: Format::text,
+                path: None
+            })
+        );
+        assert_eq!(
+            Report::from_str("json"),
+            Ok(Report {
+                format: Format::json,
+                path: None
+            })
+        );
+        assert!(Report::from_str("xml:report.xml").is_err());
+        assert!(Report::from_str("json:").is_err());
+    }
+}
@@ -3,12 +3,67 @@ use serde_sarif::sarif::{
     Sarif as StandardSarif, Tool, ToolComponent,
 };
 
-use crate::{results::Results, rules};
+use crate::results::{Results, Severity, Violation};
+
+use super::{OutputFormatter, RuleInfo};
+
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
+/// SARIF only knows three levels of results.
+fn level(severity: Severity) -> serde_json::Value {
+    let level = match severity {
+        Severity::Info => "note",
+        Severity::Minor | Severity::Major => "warning",
+        Severity::Critical | Severity::Blocker => "error",
+    };
+    serde_json::Value::String(String::from(level))
+}
 
-use super::OutputFormatter;
 pub struct Sarif {}
+/// The other places of a violation, like the steps of a dependency cycle.
+fn related_locations(violation: &Violation) -> Option<Vec<sarif::Location>> {
+    if violation.related_locations.is_empty() {
+        return None;
+    }
+
+    let locations = violation
+        .related_locations
+        .iter()
+        .enumerate()
+        .map(|(id, related)| sarif::Location {
+            id: Some(id as i64),
+            message: Some(Message {
+                text: Some(related.message.clone()),
+                ..Default::default()
+            }),
+            physical_location: Some(PhysicalLocation {
+                artifact_location: Some(ArtifactLocation {
+                    uri: Some(related.path.replace("./", "")),
+                    ..Default::default()
+                }),
+                region: Some(sarif::Region {
+                    start_line: Some((related.start_line as i64).max(1)),
+                    start_column: Some((related.start_column as i64).max(1)),
+                    end_line: Some((related.end_line as i64).max(1)),
+                    end_column: Some((related.end_column as i64).max(1)),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        })
+        .collect();
+
+    Some(locations)
+}
+
 impl OutputFormatter for Sarif {
-    fn output(results: &mut Results) {
+    fn output(
+        out: &mut dyn Write,
+        results: &Results,
+        rules: &BTreeMap<String, RuleInfo>,
+    ) -> io::Result<()> {
         let version: &str = env!("CARGO_PKG_VERSION");
         let description = MultiformatMessageString {
             markdown: None,
@@ -17,32 +72,44 @@ impl OutputFormatter for Sarif {
         };
 
         let mut sarif_rules = vec![];
-        let mut rule_to_index: std::collections::HashMap<String, i64> = std::collections::HashMap::new();
-        let rules = rules::all_rules();
-        for (current_index, rule) in rules.into_iter().enumerate() {
-            let r = rule.1.description();
-            let description = r;
+        let mut rule_to_index: std::collections::HashMap<String, i64> =
+            std::collections::HashMap::new();
+        for (current_index, rule) in rules.iter().enumerate() {
+            let description = rule.1.description.clone();
 
             let rule_number = rule.0.trim_start_matches('E').trim_start_matches('0');
-            let rule_number = if rule_number.is_empty() { "0" } else { rule_number };
+            let rule_number = if rule_number.is_empty() {
+                "0"
+            } else {
+                rule_number
+            };
 
             let multiformat_message = MultiformatMessageString {
-                markdown: rule.1.get_detailed_explanation(),
+                markdown: rule.1.explanation.clone(),
                 properties: None,
                 text: description,
             };
             sarif_rules.push(sarif::ReportingDescriptor {
-                default_configuration: None,
+                default_configuration: Some(sarif::ReportingConfiguration {
+                    enabled: None,
+                    level: Some(level(rule.1.severity)),
+                    parameters: None,
+                    properties: None,
+                    rank: None,
+                }),
                 deprecated_guids: None,
                 deprecated_ids: None,
                 deprecated_names: None,
                 guid: None,
                 help: Some(multiformat_message.clone()),
                 full_description: Some(multiformat_message.clone()),
-                help_uri: Some(format!(
-                    "https://github.com/denzyldick/phanalist/blob/main/src/rules/examples/e{}/e{}.md",
-                    rule_number, rule_number
-                )),
+                // Custom rules and plugins are only documented by their description
+                help_uri: rule.1.built_in.then(|| {
+                    format!(
+                        "https://github.com/denzyldick/phanalist/blob/main/src/rules/examples/e{}/e{}.md",
+                        rule_number, rule_number
+                    )
+                }),
                 id: rule.0.clone(),
                 message_strings: None,
                 name: Some(rule.0.clone()),
@@ -50,7 +117,7 @@ impl OutputFormatter for Sarif {
                 relationships: None,
                 short_description: Some(multiformat_message),
             });
-            rule_to_index.insert(rule.0, current_index as i64);
+            rule_to_index.insert(rule.0.clone(), current_index as i64);
         }
         let tool_component = ToolComponent {
             associated_component: None,
@@ -89,7 +156,7 @@ impl OutputFormatter for Sarif {
         };
 
         let mut t = vec![];
-        for (key, violations) in &results.files {
+        for (key, violations) in results.sorted_files() {
             for violation in violations {
                 let analysis_target = ArtifactLocation {
                     uri: Some(String::from(key).replace("./", "")),
@@ -127,7 +194,12 @@ impl OutputFormatter for Sarif {
                 let location = sarif::Location {
                     annotations: None,
                     id: None,
-                    logical_locations: None,
+                    logical_locations: violation.symbol.as_ref().map(|symbol| {
+                        vec![sarif::LogicalLocation {
+                            fully_qualified_name: Some(symbol.clone()),
+                            ..Default::default()
+                        }]
+                    }),
                     message: None,
                     physical_location: Some(physical_location),
                     properties: None,
@@ -147,15 +219,18 @@ impl OutputFormatter for Sarif {
                     guid: None,
                     hosted_viewer_uri: None,
                     kind: Some(serde_json::Value::String(String::from("fail"))),
-                    level: Some(serde_json::Value::String(String::from("warning"))),
+                    level: Some(level(violation.severity)),
                     locations: Some(vec![location]),
                     message,
                     occurrence_count: None,
-                    partial_fingerprints: None,
+                    partial_fingerprints: Some(std::collections::BTreeMap::from([(
+                        String::from("phanalist/v1"),
+                        violation.fingerprint.clone(),
+                    )])),
                     properties: None,
                     provenance: None,
                     rank: None,
-                    related_locations: None,
+                    related_locations: related_locations(violation),
                     rule: None,
                     rule_id: Some(violation.rule.clone()),
                     rule_index: rule_to_index.get(&violation.rule).copied(),
@@ -169,6 +244,43 @@ impl OutputFormatter for Sarif {
             }
         }
 
+        let mut notifications = vec![];
+        for (key, errors) in &results.parse_errors {
+            for error in errors {
+                let physical_location = PhysicalLocation {
+                    artifact_location: Some(ArtifactLocation {
+                        uri: Some(String::from(key).replace("./", "")),
+                        ..Default::default()
+                    }),
+                    region: Some(sarif::Region {
+                        start_line: Some((error.start_line as i64).max(1)),
+                        start_column: Some((error.start_column as i64).max(1)),
+                        end_line: Some((error.end_line as i64).max(1)),
+                        end_column: Some((error.end_column as i64).max(1)),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                };
+                notifications.push(sarif::Notification {
+                    associated_rule: None,
+                    descriptor: None,
+                    exception: None,
+                    level: Some(serde_json::Value::String(String::from("error"))),
+                    locations: Some(vec![sarif::Location {
+                        physical_location: Some(physical_location),
+                        ..Default::default()
+                    }]),
+                    message: Message {
+                        text: Some(error.message.clone()),
+                        ..Default::default()
+                    },
+                    properties: None,
+                    thread_id: None,
+                    time_utc: None,
+                });
+            }
+        }
+
         let runs = vec![Run {
             addresses: None,
             artifacts: None,
@@ -205,7 +317,11 @@ impl OutputFormatter for Sarif {
                 stdout: None,
                 stdout_stderr: None,
                 tool_configuration_notifications: None,
-                tool_execution_notifications: None,
+                tool_execution_notifications: if notifications.is_empty() {
+                    None
+                } else {
+                    Some(notifications)
+                },
                 working_directory: None,
             }]),
             language: Some("en".to_string()),
@@ -233,7 +349,32 @@ impl OutputFormatter for Sarif {
             runs,
             version: serde_json::Value::String("2.1.0".to_string()),
         };
-        let message = serde_json::json!(s);
-        println!("{}", message);
+        serde_json::to_writer(&mut *out, &s)?;
+        writeln!(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rule_level_follows_the_configured_severity() {
+        let rules = BTreeMap::from([(
+            String::from("E0009"),
+            RuleInfo {
+                description: String::from("Cognitive complexity"),
+                severity: Severity::Critical,
+                built_in: true,
+                ..Default::default()
+            },
+        )]);
+        let mut out = vec![];
+        Sarif::output(&mut out, &Results::default(), &rules).unwrap();
+
+        let sarif: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let rule = &sarif["runs"][0]["tool"]["driver"]["rules"][0];
+        assert_eq!(rule["id"], "E0009");
+        assert_eq!(rule["defaultConfiguration"]["level"], "error");
     }
 }
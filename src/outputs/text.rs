@@ -1,19 +1,48 @@
+use std::collections::BTreeMap;
+use std::io::{self, Write};
+
 use crate::results::Results;
 
-use cli_table::{format::Justify, Cell, Style, Table};
-use colored::Colorize;
+use cli_table::{format::Justify, Cell, ColorChoice, Style, Table};
+use colored::{control::SHOULD_COLORIZE, ColoredString, Colorize};
 
 use human_bytes::human_bytes;
 use memory_stats::memory_stats;
 
-use super::OutputFormatter;
-use crate::rules;
+use super::{OutputFormatter, RuleInfo};
 
 pub struct Text {}
 impl OutputFormatter for Text {
-    fn output(results: &mut Results) {
-        Self::output_files_with_violations(results);
-        Self::output_summary(results);
+    fn output(
+        out: &mut dyn Write,
+        results: &Results,
+        rules: &BTreeMap<String, RuleInfo>,
+    ) -> io::Result<()> {
+        Self::output_with_colors(out, results, rules, true)
+    }
+}
+
+/// The styled text, or the plain one when colors are off.
+fn paint(text: ColoredString, colors: bool) -> ColoredString {
+    if colors {
+        text
+    } else {
+        text.clear()
+    }
+}
+
+impl Text {
+    /// Like `output`, without colors when `colors` is false, e.g. for report
+    /// files. Otherwise, colors depend on the terminal.
+    pub fn output_with_colors(
+        out: &mut dyn Write,
+        results: &Results,
+        rules: &BTreeMap<String, RuleInfo>,
+        colors: bool,
+    ) -> io::Result<()> {
+        Self::output_parse_errors(out, results, colors)?;
+        Self::output_files_with_violations(out, results, colors)?;
+        Self::output_summary(out, results, rules, colors)?;
 
         let memory_usage = if let Some(usage) = memory_stats() {
             human_bytes(usage.physical_mem as f64)
@@ -21,57 +50,116 @@ impl OutputFormatter for Text {
             "N/A".to_string()
         };
 
-        println!(
+        writeln!(
+            out,
             "Analysed {} files in {:.2?}, memory usage: {}",
             results.total_files_count,
             results.duration.unwrap(),
             memory_usage
-        );
+        )
     }
-}
 
-impl Text {
-    fn output_files_with_violations(results: &Results) {
-        for (path, violations) in &results.files {
+    fn output_parse_errors(out: &mut dyn Write, results: &Results, colors: bool) -> io::Result<()> {
+        for (path, errors) in &results.parse_errors {
+            writeln!(
+                out,
+                "{}, detected {} parse errors:",
+                paint(path.blue().bold(), colors),
+                paint(errors.len().to_string().as_str().red().bold(), colors)
+            )?;
+            for error in errors {
+                if error.start_line == 0 {
+                    writeln!(out, "  {}", paint(error.message.bold(), colors))?;
+                } else {
+                    writeln!(
+                        out,
+                        "  {}\t{}",
+                        paint(
+                            format!("{}:{}", error.start_line, error.start_column + 1)
+                                .blue()
+                                .bold(),
+                            colors
+                        ),
+                        paint(error.message.bold(), colors)
+                    )?;
+                }
+            }
+            writeln!(out)?;
+        }
+
+        Ok(())
+    }
+
+    fn output_files_with_violations(
+        out: &mut dyn Write,
+        results: &Results,
+        colors: bool,
+    ) -> io::Result<()> {
+        for (path, violations) in results.sorted_files() {
             if !violations.is_empty() {
-                println!(
+                writeln!(
+                    out,
                     "{}, detected {} violations:",
-                    path.blue().bold(),
-                    violations.len().to_string().as_str().red().bold()
-                );
-                let line_symbol = "|".blue().bold();
+                    paint(path.blue().bold(), colors),
+                    paint(violations.len().to_string().as_str().red().bold(), colors)
+                )?;
+                let line_symbol = paint("|".blue().bold(), colors);
                 for suggestion in violations {
-                    println!(
-                        "  {}:\t{}",
-                        suggestion.rule.yellow().bold(),
-                        suggestion.suggestion.bold()
-                    );
-                    println!(
+                    writeln!(
+                        out,
+                        "  {} ({}):\t{}",
+                        paint(suggestion.rule.yellow().bold(), colors),
+                        suggestion.severity,
+                        paint(suggestion.suggestion.bold(), colors)
+                    )?;
+                    writeln!(
+                        out,
                         "  {}\t{} {}",
-                        format!("{}:{}", suggestion.start_line, suggestion.start_column + 1)
-                            .blue()
-                            .bold(),
+                        paint(
+                            format!("{}:{}", suggestion.start_line, suggestion.start_column + 1)
+                                .blue()
+                                .bold(),
+                            colors
+                        ),
                         line_symbol,
                         suggestion.line
-                    );
+                    )?;
+                    for related in &suggestion.related_locations {
+                        writeln!(
+                            out,
+                            "  \t{} {}:{}:{} {}",
+                            line_symbol,
+                            related.path,
+                            related.start_line,
+                            related.start_column + 1,
+                            related.message
+                        )?;
+                    }
                 }
-                println!()
+                writeln!(out)?;
             }
         }
+
+        Ok(())
     }
 
-    fn output_summary(results: &Results) {
-        let all_rules = rules::all_rules();
+    fn output_summary(
+        out: &mut dyn Write,
+        results: &Results,
+        rules: &BTreeMap<String, RuleInfo>,
+        colors: bool,
+    ) -> io::Result<()> {
         let mut rows = vec![];
 
-        let mut sorted_codes_count = results.codes_count.clone().into_iter().collect::<Vec<_>>();
-        sorted_codes_count.sort_by_key(|b| std::cmp::Reverse(b.1));
-        for (rule_code, violations) in sorted_codes_count {
-            let rule = all_rules.get(&rule_code).unwrap();
+        for (rule_code, violations) in results.sorted_codes_count() {
+            let description = rules
+                .get(rule_code)
+                .map(|rule| rule.description.as_str())
+                .unwrap_or("Unknown rule");
 
             rows.push(vec![
                 rule_code.as_str().cell(),
-                rule.description().cell(),
+                description.cell(),
                 violations.cell().justify(Justify::Right),
             ]);
         }
@@ -84,8 +172,15 @@ impl Text {
                     "Description".cell().bold(true),
                     "Violations".cell().bold(true),
                 ])
-                .bold(true);
-            println!("{}", table.display().unwrap());
+                .bold(true)
+                .color_choice(if colors && SHOULD_COLORIZE.should_colorize() {
+                    ColorChoice::Always
+                } else {
+                    ColorChoice::Never
+                });
+            writeln!(out, "{}", table.display()?)?;
         }
+
+        Ok(())
     }
 }
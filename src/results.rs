@@ -1,7 +1,13 @@
-use std::collections::HashMap;
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::str::FromStr;
 use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use mago_span::Span;
 
 use crate::debug_stats::RuleTimings;
 use crate::file::File;
@@ -9,22 +15,258 @@ use crate::file::File;
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Violation {
     pub rule: String,
+    #[serde(default)]
+    pub severity: Severity,
     pub line: String,
     pub suggestion: String,
     pub start_line: usize,
     pub start_column: usize,
     pub end_line: usize,
     pub end_column: usize,
+    /// Fully qualified name of the class-like, method or function around the
+    /// violation, e.g. `App\\Service::run`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    /// Identifies the violation across commits, see `fingerprint`.
+    #[serde(default)]
+    pub fingerprint: String,
+    /// Edits which fix the violation, applied by `--fix`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub edits: Vec<Edit>,
+    /// Other places the violation involves, possibly in other files.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub related_locations: Vec<RelatedLocation>,
+}
+
+impl Violation {
+    pub fn with_edits(mut self, edits: Vec<Edit>) -> Self {
+        self.edits = edits;
+        self
+    }
+
+    pub fn with_related_locations(mut self, related_locations: Vec<RelatedLocation>) -> Self {
+        self.related_locations = related_locations;
+        self
+    }
+
+    /// Order of the violations in a file: by line, column, then rule.
+    pub fn position_cmp(&self, other: &Violation) -> Ordering {
+        (self.start_line, self.start_column, &self.rule)
+            .cmp(&(other.start_line, other.start_column, &other.rule))
+            .then_with(|| {
+                (self.end_line, self.end_column, &self.suggestion).cmp(&(
+                    other.end_line,
+                    other.end_column,
+                    &other.suggestion,
+                ))
+            })
+    }
+}
+
+/// A place related to a violation, with lines and columns numbered like the
+/// violation's.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RelatedLocation {
+    pub path: String,
+    pub message: String,
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+/// A file which couldn't be read or parsed, so its analysis is incomplete.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    /// 1-based, 0 when the file couldn't be read at all.
+    pub start_line: usize,
+    pub start_column: usize,
+    pub end_line: usize,
+    pub end_column: usize,
+}
+
+impl ParseError {
+    pub fn unreadable(error: &std::io::Error) -> Self {
+        Self {
+            message: format!("Unable to read the file: {}", error),
+            start_line: 0,
+            start_column: 0,
+            end_line: 0,
+            end_column: 0,
+        }
+    }
+}
+
+/// Fingerprint of a violation, which doesn't depend on line numbers so it
+/// survives unrelated edits of the file: the rule code, the file path, the
+/// enclosing class/method or function and the source line with whitespace
+/// normalized.
+pub fn fingerprint(rule: &str, path: &str, symbol: Option<&str>, line: &str) -> String {
+    let path = path.strip_prefix("./").unwrap_or(path);
+    let mut hasher = Sha256::new();
+    for part in [
+        rule,
+        path,
+        symbol.unwrap_or_default(),
+        &normalize_line(line),
+    ] {
+        hasher.update(part.as_bytes());
+        hasher.update([0]);
+    }
+
+    hex(&hasher.finalize()[..16])
+}
+
+/// Make the fingerprints of identical violations in the same file unique,
+/// by hashing the occurrence number into all but the first one.
+pub fn disambiguate_fingerprints(violations: &mut [Violation]) {
+    let mut occurrences: HashMap<String, usize> = HashMap::new();
+    for violation in violations.iter_mut() {
+        let occurrence = occurrences
+            .entry(violation.fingerprint.clone())
+            .or_default();
+        if *occurrence > 0 {
+            let mut hasher = Sha256::new();
+            hasher.update(violation.fingerprint.as_bytes());
+            hasher.update(occurrence.to_string().as_bytes());
+            violation.fingerprint = hex(&hasher.finalize()[..16]);
+        }
+        *occurrence += 1;
+    }
+}
+
+/// The source line with whitespace normalized.
+pub(crate) fn normalize_line(line: &str) -> String {
+    line.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// How serious a violation is, from the least to the most serious.
+#[derive(
+    Debug, Clone, Copy, Default, Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Hash,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Info,
+    #[default]
+    Minor,
+    Major,
+    Critical,
+    Blocker,
+}
+
+impl Severity {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Severity::Info => "info",
+            Severity::Minor => "minor",
+            Severity::Major => "major",
+            Severity::Critical => "critical",
+            Severity::Blocker => "blocker",
+        }
+    }
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl FromStr for Severity {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Severity, Self::Err> {
+        match input {
+            "info" => Ok(Severity::Info),
+            "minor" => Ok(Severity::Minor),
+            "major" => Ok(Severity::Major),
+            "critical" => Ok(Severity::Critical),
+            "blocker" => Ok(Severity::Blocker),
+            _ => Err(format!(
+                "unknown severity {}, possible options: info, minor, major, critical, blocker",
+                input
+            )),
+        }
+    }
+}
+
+/// How the files and their violations are ordered in the outputs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Sort {
+    /// Files by path, violations by line, column and rule.
+    #[default]
+    File,
+    /// Files by path, violations by rule then position.
+    Rule,
+    /// The files with the most serious violations first, violations by
+    /// decreasing severity then position.
+    Severity,
+    /// The files with the most violations first, violations by position.
+    Count,
+}
+
+impl FromStr for Sort {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Sort, Self::Err> {
+        match input {
+            "file" => Ok(Sort::File),
+            "rule" => Ok(Sort::Rule),
+            "severity" => Ok(Sort::Severity),
+            "count" => Ok(Sort::Count),
+            _ => Err(format!(
+                "unknown sort {}, possible options: file, rule, severity, count",
+                input
+            )),
+        }
+    }
+}
+
+/// Replacement of the bytes between `start` and `end` of the file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Edit {
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+impl Edit {
+    pub fn replace(span: Span, replacement: &str) -> Self {
+        Self {
+            start: span.start.offset as usize,
+            end: span.end.offset as usize,
+            replacement: replacement.to_string(),
+        }
+    }
+
+    pub fn insert(offset: u32, text: &str) -> Self {
+        Self {
+            start: offset as usize,
+            end: offset as usize,
+            replacement: text.to_string(),
+        }
+    }
 }
 
 #[derive(Serialize, Debug, Deserialize, Clone, Default)]
 pub struct Results {
-    pub files: HashMap<String, Vec<Violation>>,
-    pub codes_count: HashMap<String, i64>,
+    pub files: BTreeMap<String, Vec<Violation>>,
+    /// Files which couldn't be read or parsed, by path.
+    #[serde(default)]
+    pub parse_errors: BTreeMap<String, Vec<ParseError>>,
+    pub codes_count: BTreeMap<String, i64>,
     pub total_files_count: i64,
     pub duration: Option<Duration>,
     #[serde(skip)]
     pub rule_timings: Option<RuleTimings>,
+    /// The order of the files and violations in the outputs, see `sort`.
+    #[serde(skip)]
+    pub sort: Sort,
 }
 
 impl Results {
@@ -50,11 +292,113 @@ impl Results {
             self.codes_count.insert(violation.rule, rule_count);
         }
 
+        current_file_violations.sort_by(Violation::position_cmp);
         self.files.insert(path, current_file_violations);
     }
 
-    pub fn has_any_violations(&self) -> bool {
-        self.codes_count.values().any(|&c| c > 0)
+    pub fn add_parse_errors(&mut self, path: String, mut errors: Vec<ParseError>) {
+        if !errors.is_empty() {
+            self.parse_errors
+                .entry(path)
+                .or_default()
+                .append(&mut errors);
+        }
+    }
+
+    /// Merge the results collected by another analysis thread.
+    pub fn merge(&mut self, other: Results) {
+        for (path, mut violations) in other.files {
+            let file_violations = self.files.entry(path).or_default();
+            file_violations.append(&mut violations);
+            file_violations.sort_by(Violation::position_cmp);
+        }
+        for (path, errors) in other.parse_errors {
+            self.add_parse_errors(path, errors);
+        }
+        for (code, count) in other.codes_count {
+            *self.codes_count.entry(code).or_default() += count;
+        }
+        self.total_files_count += other.total_files_count;
+        self.duration = match (self.duration, other.duration) {
+            (Some(duration), Some(other)) => Some(duration + other),
+            (duration, other) => duration.or(other),
+        };
+
+        if let Some(other_timings) = other.rule_timings {
+            self.rule_timings
+                .get_or_insert_with(RuleTimings::default)
+                .merge(other_timings);
+        }
+    }
+
+    /// Order the violations of every file, and the files returned by
+    /// `sorted_files`, with `sort`.
+    pub fn sort(&mut self, sort: Sort) {
+        self.sort = sort;
+        for violations in self.files.values_mut() {
+            match sort {
+                Sort::File | Sort::Count => violations.sort_by(Violation::position_cmp),
+                Sort::Rule => {
+                    violations.sort_by(|a, b| a.rule.cmp(&b.rule).then_with(|| a.position_cmp(b)))
+                }
+                Sort::Severity => violations
+                    .sort_by(|a, b| b.severity.cmp(&a.severity).then_with(|| a.position_cmp(b))),
+            }
+        }
+    }
+
+    /// The files and their violations in the order of `sort`.
+    pub fn sorted_files(&self) -> Vec<(&String, &Vec<Violation>)> {
+        let mut files: Vec<_> = self.files.iter().collect();
+        match self.sort {
+            Sort::File | Sort::Rule => {}
+            Sort::Severity => files.sort_by_key(|(_, violations)| {
+                Reverse(violations.iter().map(|violation| violation.severity).max())
+            }),
+            Sort::Count => files.sort_by_key(|(_, violations)| Reverse(violations.len())),
+        }
+
+        files
+    }
+
+    /// The paths of the files with violations in the order of `sort`, then
+    /// of the files which only have parse errors.
+    pub fn sorted_paths(&self) -> Vec<&String> {
+        let mut paths: Vec<&String> = self
+            .sorted_files()
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect();
+        paths.extend(
+            self.parse_errors
+                .keys()
+                .filter(|path| !self.files.contains_key(*path)),
+        );
+
+        paths
+    }
+
+    /// The number of violations per rule, the most violated rules first
+    /// unless sorted by rule.
+    pub fn sorted_codes_count(&self) -> Vec<(&String, &i64)> {
+        let mut codes: Vec<_> = self.codes_count.iter().collect();
+        if self.sort != Sort::Rule {
+            codes.sort_by_key(|(_, count)| Reverse(**count));
+        }
+
+        codes
+    }
+
+    /// Whether any violation is at least as serious as `severity`.
+    pub fn has_violations_at(&self, severity: Severity) -> bool {
+        self.files
+            .values()
+            .flatten()
+            .any(|violation| violation.severity >= severity)
+    }
+
+    pub fn has_parse_errors(&self) -> bool {
+        !self.parse_errors.is_empty()
     }
 }
 
@@ -69,10 +413,12 @@ mod tests {
     fn get_results() -> Results {
         Results {
             files: Default::default(),
+            parse_errors: Default::default(),
             codes_count: Default::default(),
             total_files_count: 0,
             duration: None,
             rule_timings: None,
+            sort: Sort::File,
         }
     }
     fn get_file<'a>(arena: &'a Bump, name: &str) -> File<'a> {
@@ -81,12 +427,17 @@ mod tests {
     fn get_violation(rule: &str) -> Violation {
         Violation {
             rule: rule.to_string(),
+            severity: Severity::Minor,
+            symbol: None,
+            fingerprint: String::new(),
             line: "Line".to_string(),
             suggestion: "Suggestion".to_string(),
             start_line: 0,
             start_column: 0,
             end_line: 0,
             end_column: 0,
+            edits: vec![],
+            related_locations: vec![],
         }
     }
 
@@ -137,7 +488,7 @@ mod tests {
         results.add_file_violations(&file2, vec![violation3]);
         results.add_file_violations(&file1, vec![violation4]);
 
-        let mut expected_codes_count: HashMap<String, i64> = HashMap::new();
+        let mut expected_codes_count: BTreeMap<String, i64> = BTreeMap::new();
         expected_codes_count.insert("E001".to_string(), 2);
         expected_codes_count.insert("E002".to_string(), 1);
         expected_codes_count.insert("E003".to_string(), 1);
@@ -146,7 +497,52 @@ mod tests {
     }
 
     #[test]
-    fn test_has_any_violations_expected_true() {
+    fn test_merge_expected_codes_count_and_files() {
+        let arena = Bump::new();
+        let file1 = get_file(&arena, "./class1.php");
+        let file2 = get_file(&arena, "./class2.php");
+
+        let mut results = get_results();
+        results.total_files_count = 1;
+        results.add_file_violations(&file1, vec![get_violation("E001")]);
+
+        let mut other = get_results();
+        other.total_files_count = 2;
+        other.add_file_violations(&file1, vec![get_violation("E002")]);
+        other.add_file_violations(&file2, vec![get_violation("E001")]);
+
+        results.merge(other);
+
+        assert_eq!(results.total_files_count, 3);
+        assert_eq!(results.files.get("./class1.php").unwrap().len(), 2);
+        assert_eq!(results.files.get("./class2.php").unwrap().len(), 1);
+        assert_eq!(results.codes_count.get("E001"), Some(&2));
+        assert_eq!(results.codes_count.get("E002"), Some(&1));
+    }
+
+    #[test]
+    fn test_merge_expected_parse_errors() {
+        let error = ParseError {
+            message: "Unexpected token".to_string(),
+            start_line: 3,
+            start_column: 4,
+            end_line: 3,
+            end_column: 5,
+        };
+        let mut results = get_results();
+        results.add_parse_errors("./class1.php".to_string(), vec![]);
+        assert!(!results.has_parse_errors());
+
+        let mut other = get_results();
+        other.add_parse_errors("./class1.php".to_string(), vec![error.clone()]);
+        results.merge(other);
+
+        assert!(results.has_parse_errors());
+        assert_eq!(results.parse_errors.get("./class1.php"), Some(&vec![error]));
+    }
+
+    #[test]
+    fn test_has_violations_at_expected_true() {
         let mut results = get_results();
         let arena = Bump::new();
         let file1 = get_file(&arena, "./class1.php");
@@ -154,12 +550,146 @@ mod tests {
 
         results.add_file_violations(&file1, vec![violation1]);
 
-        assert!(results.has_any_violations());
+        assert!(results.has_violations_at(Severity::Info));
+        assert!(results.has_violations_at(Severity::Minor));
     }
 
     #[test]
-    fn test_has_any_violations_expected_false() {
+    fn test_has_violations_at_expected_false() {
         let results = get_results();
-        assert!(!results.has_any_violations());
+        assert!(!results.has_violations_at(Severity::Info));
+    }
+
+    #[test]
+    fn test_has_violations_at_ignores_less_serious_violations() {
+        let mut results = get_results();
+        let arena = Bump::new();
+        let file1 = get_file(&arena, "./class1.php");
+        let mut violation1 = get_violation("E001");
+        violation1.severity = Severity::Major;
+
+        results.add_file_violations(&file1, vec![violation1]);
+
+        assert!(results.has_violations_at(Severity::Major));
+        assert!(!results.has_violations_at(Severity::Critical));
+    }
+
+    #[test]
+    fn test_severity_from_str() {
+        assert_eq!(Severity::from_str("critical"), Ok(Severity::Critical));
+        assert!(Severity::from_str("fatal").is_err());
+        assert!(Severity::Info < Severity::Blocker);
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut results = get_results();
+        let arena = Bump::new();
+        let violation = |rule: &str, line: usize, severity: Severity| Violation {
+            start_line: line,
+            severity,
+            ..get_violation(rule)
+        };
+        results.add_file_violations(
+            &get_file(&arena, "./a.php"),
+            vec![
+                violation("E002", 9, Severity::Minor),
+                violation("E001", 3, Severity::Minor),
+            ],
+        );
+        results.add_file_violations(
+            &get_file(&arena, "./b.php"),
+            vec![
+                violation("E003", 5, Severity::Minor),
+                violation("E001", 4, Severity::Critical),
+                violation("E001", 2, Severity::Minor),
+            ],
+        );
+        let order = |results: &Results| -> Vec<(String, String, usize)> {
+            results
+                .sorted_files()
+                .into_iter()
+                .flat_map(|(path, violations)| {
+                    violations
+                        .iter()
+                        .map(|v| (path.clone(), v.rule.clone(), v.start_line))
+                })
+                .collect()
+        };
+        let row = |path: &str, rule: &str, line: usize| (path.to_string(), rule.to_string(), line);
+
+        assert_eq!(
+            order(&results),
+            vec![
+                row("./a.php", "E001", 3),
+                row("./a.php", "E002", 9),
+                row("./b.php", "E001", 2),
+                row("./b.php", "E001", 4),
+                row("./b.php", "E003", 5),
+            ]
+        );
+
+        results.sort(Sort::Rule);
+        assert_eq!(order(&results)[3], row("./b.php", "E001", 4));
+        assert_eq!(
+            results.sorted_codes_count(),
+            vec![
+                (&"E001".to_string(), &3),
+                (&"E002".to_string(), &1),
+                (&"E003".to_string(), &1)
+            ]
+        );
+
+        results.sort(Sort::Severity);
+        assert_eq!(order(&results)[0], row("./b.php", "E001", 4));
+
+        results.sort(Sort::Count);
+        assert_eq!(order(&results)[0], row("./b.php", "E001", 2));
+        assert_eq!(Sort::from_str("count"), Ok(Sort::Count));
+        assert!(Sort::from_str("size").is_err());
+    }
+
+    #[test]
+    fn test_fingerprint_ignores_position_and_whitespace() {
+        let fingerprint1 = fingerprint(
+            "E0011",
+            "./src/Service.php",
+            Some("App\\Service::run"),
+            "  @foo();",
+        );
+        let fingerprint2 = fingerprint(
+            "E0011",
+            "src/Service.php",
+            Some("App\\Service::run"),
+            "@foo();",
+        );
+        let other_method = fingerprint(
+            "E0011",
+            "src/Service.php",
+            Some("App\\Service::stop"),
+            "@foo();",
+        );
+
+        assert_eq!(fingerprint1, fingerprint2);
+        assert_ne!(fingerprint1, other_method);
+        assert_eq!(fingerprint1.len(), 32);
+    }
+
+    #[test]
+    fn test_disambiguate_fingerprints() {
+        let mut violations = vec![
+            get_violation("E001"),
+            get_violation("E001"),
+            get_violation("E002"),
+        ];
+        violations[0].fingerprint = "a".to_string();
+        violations[1].fingerprint = "a".to_string();
+        violations[2].fingerprint = "b".to_string();
+
+        disambiguate_fingerprints(&mut violations);
+
+        assert_eq!(violations[0].fingerprint, "a");
+        assert_ne!(violations[1].fingerprint, "a");
+        assert_eq!(violations[2].fingerprint, "b");
     }
 }
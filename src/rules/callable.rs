@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+
+use mago_span::{HasSpan, Span};
+use mago_syntax::ast::*;
+use mago_syntax::walker::Walker;
+use serde::{Deserialize, Serialize};
+
+use crate::file::File;
+
+/// Kinds of callable bodies measured by the complexity and size rules, used as
+/// keys of their per-kind thresholds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CallableKind {
+    Method,
+    Function,
+    Closure,
+    ArrowFunction,
+    PropertyHook,
+}
+
+impl CallableKind {
+    pub fn noun(&self) -> &'static str {
+        match self {
+            CallableKind::Method => "method",
+            CallableKind::Function => "function",
+            CallableKind::Closure => "closure",
+            CallableKind::ArrowFunction => "arrow function",
+            CallableKind::PropertyHook => "property hook",
+        }
+    }
+
+    pub fn title(&self) -> &'static str {
+        match self {
+            CallableKind::Method => "Method",
+            CallableKind::Function => "Function",
+            CallableKind::Closure => "Closure",
+            CallableKind::ArrowFunction => "Arrow function",
+            CallableKind::PropertyHook => "Property hook",
+        }
+    }
+}
+
+pub enum CallableBody<'a> {
+    Block(&'a Block<'a>),
+    Expression(&'a Expression<'a>),
+    Abstract,
+}
+
+/// A method, function, closure, arrow function or property hook.
+pub struct Callable<'a> {
+    pub kind: CallableKind,
+    /// `{closure}` for closures and arrow functions, `$property::get` for hooks.
+    pub name: String,
+    pub span: Span,
+    pub parameters: usize,
+    pub body: CallableBody<'a>,
+}
+
+impl<'a> Callable<'a> {
+    /// Statements of the body, `None` for abstract and expression bodies.
+    pub fn statements(&self) -> Option<&'a Sequence<'a, Statement<'a>>> {
+        match self.body {
+            CallableBody::Block(block) => Some(&block.statements),
+            _ => None,
+        }
+    }
+
+    /// Lines between the braces of a block body, or spanned by an expression body.
+    pub fn lines_of_code(&self, file: &File<'_>) -> usize {
+        let span = match self.body {
+            CallableBody::Block(block) => block.span(),
+            CallableBody::Expression(expression) => expression.span(),
+            CallableBody::Abstract => return 0,
+        };
+        let start_line = file.line_number(span.start.offset);
+        let end_line = file.line_number(span.end.offset);
+
+        match self.body {
+            CallableBody::Block(_) => end_line.saturating_sub(start_line + 1),
+            _ => end_line - start_line + 1,
+        }
+    }
+}
+
+/// Threshold configured for the kind, falling back to the rule's main one.
+pub fn threshold<T: Copy>(
+    default: T,
+    by_kind: &BTreeMap<CallableKind, T>,
+    kind: CallableKind,
+) -> T {
+    by_kind.get(&kind).copied().unwrap_or(default)
+}
+
+/// Every callable declared within the statement, including nested closures and
+/// the methods of anonymous classes.
+pub fn callables<'a>(statement: &'a Statement<'a>) -> Vec<Callable<'a>> {
+    let mut context = Context::default();
+    CallableCollector.walk_statement(statement, &mut context);
+    context.callables
+}
+
+#[derive(Default)]
+struct Context<'a> {
+    callables: Vec<Callable<'a>>,
+    property: Option<String>,
+}
+
+struct CallableCollector;
+
+impl<'a> Walker<'a, 'a, Context<'a>> for CallableCollector {
+    fn walk_in_method(&self, method: &'a Method<'a>, context: &mut Context<'a>) {
+        context.callables.push(Callable {
+            kind: CallableKind::Method,
+            name: String::from_utf8_lossy(method.name.value).into_owned(),
+            span: method.span(),
+            parameters: method.parameter_list.parameters.len(),
+            body: match &method.body {
+                MethodBody::Concrete(block) => CallableBody::Block(block),
+                MethodBody::Abstract(_) => CallableBody::Abstract,
+            },
+        });
+    }
+
+    fn walk_in_function(&self, function: &'a Function<'a>, context: &mut Context<'a>) {
+        context.callables.push(Callable {
+            kind: CallableKind::Function,
+            name: String::from_utf8_lossy(function.name.value).into_owned(),
+            span: function.span(),
+            parameters: function.parameter_list.parameters.len(),
+            body: CallableBody::Block(&function.body),
+        });
+    }
+
+    fn walk_in_closure(&self, closure: &'a Closure<'a>, context: &mut Context<'a>) {
+        context.callables.push(Callable {
+            kind: CallableKind::Closure,
+            name: String::from("{closure}"),
+            span: closure.span(),
+            parameters: closure.parameter_list.parameters.len(),
+            body: CallableBody::Block(&closure.body),
+        });
+    }
+
+    fn walk_in_arrow_function(
+        &self,
+        arrow_function: &'a ArrowFunction<'a>,
+        context: &mut Context<'a>,
+    ) {
+        context.callables.push(Callable {
+            kind: CallableKind::ArrowFunction,
+            name: String::from("{closure}"),
+            span: arrow_function.span(),
+            parameters: arrow_function.parameter_list.parameters.len(),
+            body: CallableBody::Expression(arrow_function.expression),
+        });
+    }
+
+    fn walk_in_hooked_property(
+        &self,
+        hooked_property: &'a HookedProperty<'a>,
+        context: &mut Context<'a>,
+    ) {
+        context.property = Some(variable_name(hooked_property.item.variable()));
+    }
+
+    fn walk_out_hooked_property(
+        &self,
+        _hooked_property: &'a HookedProperty<'a>,
+        context: &mut Context<'a>,
+    ) {
+        context.property = None;
+    }
+
+    fn walk_in_function_like_parameter(
+        &self,
+        parameter: &'a FunctionLikeParameter<'a>,
+        context: &mut Context<'a>,
+    ) {
+        if parameter.hooks.is_some() {
+            context.property = Some(variable_name(&parameter.variable));
+        }
+    }
+
+    fn walk_out_function_like_parameter(
+        &self,
+        parameter: &'a FunctionLikeParameter<'a>,
+        context: &mut Context<'a>,
+    ) {
+        if parameter.hooks.is_some() {
+            context.property = None;
+        }
+    }
+
+    fn walk_in_property_hook(&self, hook: &'a PropertyHook<'a>, context: &mut Context<'a>) {
+        let hook_name = String::from_utf8_lossy(hook.name.value);
+        context.callables.push(Callable {
+            kind: CallableKind::PropertyHook,
+            name: match &context.property {
+                Some(property) => format!("{}::{}", property, hook_name),
+                None => hook_name.into_owned(),
+            },
+            span: hook.span(),
+            parameters: hook
+                .parameter_list
+                .as_ref()
+                .map_or(0, |parameter_list| parameter_list.parameters.len()),
+            body: match &hook.body {
+                PropertyHookBody::Concrete(PropertyHookConcreteBody::Block(block)) => {
+                    CallableBody::Block(block)
+                }
+                PropertyHookBody::Concrete(PropertyHookConcreteBody::Expression(body)) => {
+                    CallableBody::Expression(body.expression)
+                }
+                PropertyHookBody::Abstract(_) => CallableBody::Abstract,
+            },
+        });
+    }
+}
+
+fn variable_name(variable: &DirectVariable<'_>) -> String {
+    String::from_utf8_lossy(variable.name).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use bumpalo::Bump;
+
+    use super::*;
+
+    #[test]
+    fn test_callables() {
+        let content = "<?php\nfunction helper($a) {\n    $f = function () {\n        return fn($x) => $x;\n    };\n}\n\nclass User {\n    public string $name {\n        get => $this->name;\n        set(string $value) {\n            $this->name = $value;\n        }\n    }\n\n    abstract public function run(int $a, int $b);\n}\n\n$object = new class {\n    public function handle() {}\n};\n";
+        let arena = Bump::new();
+        let file = File::new(&arena, PathBuf::from("test.php"), content.to_string());
+
+        let callables: Vec<(CallableKind, String, usize)> = file
+            .ast
+            .unwrap()
+            .statements
+            .iter()
+            .flat_map(callables)
+            .map(|callable| (callable.kind, callable.name, callable.parameters))
+            .collect();
+        assert_eq!(
+            callables,
+            vec![
+                (CallableKind::Function, "helper".to_string(), 1),
+                (CallableKind::Closure, "{closure}".to_string(), 0),
+                (CallableKind::ArrowFunction, "{closure}".to_string(), 1),
+                (CallableKind::PropertyHook, "$name::get".to_string(), 0),
+                (CallableKind::PropertyHook, "$name::set".to_string(), 1),
+                (CallableKind::Method, "run".to_string(), 2),
+                (CallableKind::Method, "handle".to_string(), 0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,448 @@
+use std::collections::HashMap;
+
+use bumpalo::Bump;
+use mago_database::file::FileId;
+use mago_span::{HasSpan, Span};
+use mago_syntax::ast::{Expression, Node, NodeKind, PartialArgument, Statement, Variable};
+use serde::{Deserialize, Serialize};
+
+use crate::file::File;
+use crate::results::{Severity, Violation};
+use crate::rules::visitor::{Scope, Visitor};
+use crate::rules::Rule as RuleTrait;
+
+/// A rule defined in the `custom_rules` of the configuration, reporting the
+/// code matching any of its PHP patterns.
+///
+/// In a pattern, `$_` matches any expression and every other variable but
+/// `$this` is a metavariable: it matches any expression, the same one
+/// wherever it is repeated. `...` matches any arguments of a call, `?` a
+/// single one. For instance `$x->getContainer()->get(...)` or `dd(...)`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub code: String,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    #[serde(default)]
+    pub severity: Severity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub patterns: Vec<String>,
+}
+
+pub struct Rule {
+    code: String,
+    message: String,
+    description: String,
+    severity: Severity,
+    patterns: Vec<Pattern>,
+}
+
+impl Rule {
+    pub fn new(definition: &Definition) -> Result<Self, String> {
+        if definition.code.is_empty() {
+            return Err("the code of a custom rule can't be empty".to_string());
+        }
+
+        let sources: Vec<&String> = definition
+            .pattern
+            .iter()
+            .chain(definition.patterns.iter())
+            .collect();
+        if sources.is_empty() {
+            return Err(format!("custom rule {} has no pattern", definition.code));
+        }
+
+        let mut patterns = vec![];
+        for source in sources {
+            match Pattern::parse(source) {
+                Ok(pattern) => patterns.push(pattern),
+                Err(e) => {
+                    return Err(format!(
+                        "invalid pattern `{}` of custom rule {}: {}",
+                        source, definition.code, e
+                    ))
+                }
+            }
+        }
+
+        Ok(Self {
+            code: definition.code.clone(),
+            message: definition.message.clone(),
+            description: definition.description.clone(),
+            severity: definition.severity,
+            patterns,
+        })
+    }
+}
+
+impl RuleTrait for Rule {
+    fn get_code(&self) -> String {
+        self.code.clone()
+    }
+
+    fn description(&self) -> String {
+        if self.description.is_empty() {
+            return self.message.clone();
+        }
+        self.description.clone()
+    }
+
+    fn get_detailed_explanation(&self) -> Option<String> {
+        None
+    }
+
+    fn severity(&self) -> Severity {
+        self.severity
+    }
+
+    fn do_validate(&self, _file: &File<'_>) -> bool {
+        true
+    }
+
+    fn visitor<'a>(&'a self, file: &'a File<'a>) -> Option<Box<dyn Visitor<'a> + 'a>> {
+        let program = file.ast?;
+        Some(Box::new(PatternMatches {
+            rule: self,
+            file,
+            source: program.source_text,
+            violations: vec![],
+        }))
+    }
+}
+
+struct PatternMatches<'a> {
+    rule: &'a Rule,
+    file: &'a File<'a>,
+    source: &'a [u8],
+    violations: Vec<Violation>,
+}
+
+impl PatternMatches<'_> {
+    fn check(&mut self, node: Node<'_, '_>, statement: bool) {
+        let matched = self
+            .rule
+            .patterns
+            .iter()
+            .filter(|pattern| pattern.statement == statement)
+            .any(|pattern| pattern.root.matches(node, self.source, &mut HashMap::new()));
+        if matched {
+            self.violations.push(self.rule.new_violation(
+                self.file,
+                self.rule.message.clone(),
+                node.span(),
+            ));
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for PatternMatches<'a> {
+    fn enter_statement(&mut self, statement: &'a Statement<'a>, _scope: &Scope) {
+        self.check(Node::Statement(statement), true);
+    }
+
+    fn enter_expression(&mut self, expression: &'a Expression<'a>, _scope: &Scope) {
+        self.check(Node::Expression(expression), false);
+    }
+
+    fn finish(self: Box<Self>) -> Vec<Violation> {
+        self.violations
+    }
+}
+
+/// A pattern compiled from the AST of its PHP code, matched against the
+/// expressions, or the statements when it isn't a single expression.
+struct Pattern {
+    root: PatternNode,
+    statement: bool,
+}
+
+impl Pattern {
+    fn parse(source: &str) -> Result<Self, String> {
+        let source = source.trim();
+        let arena = Bump::new();
+        let file_id = FileId::new(b"pattern");
+
+        let mut program = mago_syntax::parser::parse_file_content(
+            &arena,
+            file_id,
+            format!("<?php {}", source).as_bytes(),
+        );
+        if program.has_errors() {
+            // Expressions are written without the `;` ending their statement
+            program = mago_syntax::parser::parse_file_content(
+                &arena,
+                file_id,
+                format!("<?php {};", source).as_bytes(),
+            );
+        }
+        if let Some(error) = program.errors.first() {
+            return Err(error.to_string());
+        }
+
+        let statements: Vec<&Statement<'_>> = program
+            .statements
+            .iter()
+            .filter(|statement| !matches!(statement, Statement::OpeningTag(_)))
+            .collect();
+        match statements.as_slice() {
+            [Statement::Expression(statement)] => Ok(Self {
+                root: PatternNode::compile(
+                    Node::Expression(statement.expression),
+                    program.source_text,
+                ),
+                statement: false,
+            }),
+            [statement] => Ok(Self {
+                root: PatternNode::compile(Node::Statement(statement), program.source_text),
+                statement: true,
+            }),
+            _ => Err("a pattern has to be a single expression or statement".to_string()),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum PatternNode {
+    /// `$_`, or a variable standing for the expression it first matched.
+    Metavariable(String),
+    /// `...` in an argument list.
+    AnyArguments,
+    Node {
+        kind: NodeKind,
+        /// The code of the nodes without children, like identifiers,
+        /// literals and operators.
+        text: Option<String>,
+        children: Vec<PatternNode>,
+    },
+}
+
+impl PatternNode {
+    fn compile(node: Node<'_, '_>, source: &[u8]) -> Self {
+        match node {
+            Node::Expression(Expression::Variable(Variable::Direct(variable)))
+                if variable.name != b"$this" =>
+            {
+                return PatternNode::Metavariable(
+                    String::from_utf8_lossy(variable.name).into_owned(),
+                );
+            }
+            Node::PartialArgument(PartialArgument::VariadicPlaceholder(_)) => {
+                return PatternNode::AnyArguments;
+            }
+            Node::PartialArgument(PartialArgument::Placeholder(_)) => {
+                return PatternNode::Metavariable("$_".to_string());
+            }
+            _ => {}
+        }
+
+        let children = node.children();
+        let text = children
+            .is_empty()
+            .then(|| span_text(node.span(), source).to_string());
+
+        PatternNode::Node {
+            kind: call_kind(node.kind()),
+            text,
+            children: children
+                .into_iter()
+                .map(|child| PatternNode::compile(child, source))
+                .collect(),
+        }
+    }
+
+    fn matches(
+        &self,
+        node: Node<'_, '_>,
+        source: &[u8],
+        bindings: &mut HashMap<String, String>,
+    ) -> bool {
+        match self {
+            PatternNode::Metavariable(name) if name == "$_" => true,
+            PatternNode::Metavariable(name) => {
+                let text = span_text(node.span(), source);
+                match bindings.get(name) {
+                    Some(bound) => bound == text,
+                    None => {
+                        bindings.insert(name.clone(), text.to_string());
+                        true
+                    }
+                }
+            }
+            PatternNode::AnyArguments => true,
+            PatternNode::Node {
+                kind,
+                text,
+                children,
+            } => {
+                if !same_kind(*kind, node.kind()) {
+                    return false;
+                }
+                if let Some(text) = text {
+                    if !same_text(*kind, text, span_text(node.span(), source)) {
+                        return false;
+                    }
+                }
+
+                matches_all(children, &node.children(), source, bindings)
+            }
+        }
+    }
+}
+
+fn matches_all(
+    patterns: &[PatternNode],
+    nodes: &[Node<'_, '_>],
+    source: &[u8],
+    bindings: &mut HashMap<String, String>,
+) -> bool {
+    match patterns.split_first() {
+        None => nodes.is_empty(),
+        Some((PatternNode::AnyArguments, rest)) => (0..=nodes.len()).any(|skipped| {
+            let mut attempt = bindings.clone();
+            if matches_all(rest, &nodes[skipped..], source, &mut attempt) {
+                *bindings = attempt;
+                return true;
+            }
+            false
+        }),
+        Some((pattern, rest)) => match nodes.split_first() {
+            Some((node, others)) => {
+                pattern.matches(*node, source, bindings)
+                    && matches_all(rest, others, source, bindings)
+            }
+            None => false,
+        },
+    }
+}
+
+/// `foo(...)` is a first-class callable, but in a pattern it stands for the
+/// calls of `foo` with any arguments.
+fn call_kind(kind: NodeKind) -> NodeKind {
+    match kind {
+        NodeKind::PartialApplication => NodeKind::Call,
+        NodeKind::FunctionPartialApplication => NodeKind::FunctionCall,
+        NodeKind::MethodPartialApplication => NodeKind::MethodCall,
+        NodeKind::StaticMethodPartialApplication => NodeKind::StaticMethodCall,
+        NodeKind::PartialArgumentList => NodeKind::ArgumentList,
+        NodeKind::PartialArgument => NodeKind::Argument,
+        kind => kind,
+    }
+}
+
+fn is_identifier(kind: NodeKind) -> bool {
+    matches!(
+        kind,
+        NodeKind::LocalIdentifier
+            | NodeKind::QualifiedIdentifier
+            | NodeKind::FullyQualifiedIdentifier
+    )
+}
+
+fn same_kind(pattern: NodeKind, node: NodeKind) -> bool {
+    pattern == node || (is_identifier(pattern) && is_identifier(node))
+}
+
+/// Names and keywords are case-insensitive, and `\dd` is the same function
+/// as `dd`.
+fn same_text(kind: NodeKind, pattern: &str, text: &str) -> bool {
+    if is_identifier(kind) {
+        return pattern
+            .trim_start_matches('\\')
+            .eq_ignore_ascii_case(text.trim_start_matches('\\'));
+    }
+    if kind == NodeKind::Keyword {
+        return pattern.eq_ignore_ascii_case(text);
+    }
+    pattern == text
+}
+
+fn span_text(span: Span, source: &[u8]) -> &str {
+    let bytes = source
+        .get(span.start.offset as usize..span.end.offset as usize)
+        .unwrap_or_default();
+    std::str::from_utf8(bytes).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::config::Config;
+    use crate::rules::tests::analyze_file_with_config;
+
+    use super::*;
+
+    fn analyze_with_patterns(path: &str, patterns: &[&str]) -> Vec<Violation> {
+        let config = Config {
+            enabled_rules: vec!["C0001".to_string()],
+            custom_rules: vec![Definition {
+                code: "C0001".to_string(),
+                message: "Forbidden code".to_string(),
+                description: String::new(),
+                severity: Severity::Major,
+                pattern: None,
+                patterns: patterns.iter().map(|p| p.to_string()).collect(),
+            }],
+            ..Default::default()
+        };
+
+        analyze_file_with_config(path, &config)
+    }
+
+    #[test]
+    fn calls_with_any_arguments() {
+        let violations = analyze_with_patterns("custom/patterns.php", &["dd(...)"]);
+
+        assert_eq!(violations.len(), 4);
+        assert_eq!(violations[0].rule, "C0001");
+        assert_eq!(violations[0].severity, Severity::Major);
+        assert_eq!(violations[0].suggestion, "Forbidden code");
+    }
+
+    #[test]
+    fn argument_placeholders() {
+        let violations = analyze_with_patterns("custom/patterns.php", &["dd(?, ?)"]);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].start_line, 12);
+    }
+
+    #[test]
+    fn method_chain_with_metavariables() {
+        let violations =
+            analyze_with_patterns("custom/patterns.php", &["$x->getContainer()->get($_)"]);
+
+        assert_eq!(violations.len(), 2);
+    }
+
+    #[test]
+    fn repeated_metavariable() {
+        let violations = analyze_with_patterns("custom/patterns.php", &["$a === $a"]);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].start_line, 23);
+    }
+
+    #[test]
+    fn statement_pattern() {
+        let violations = analyze_with_patterns("custom/patterns.php", &["echo $_;"]);
+
+        assert_eq!(violations.len(), 1);
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        let mut definition: Definition =
+            serde_json::from_value(json!({"code": "C0001", "message": "Forbidden code"})).unwrap();
+        assert!(Rule::new(&definition).is_err());
+
+        definition.pattern = Some("dd(".to_string());
+        assert!(Rule::new(&definition).is_err());
+
+        definition.pattern = Some("dd($x); dump($x)".to_string());
+        assert!(Rule::new(&definition).is_err());
+    }
+}
@@ -0,0 +1,345 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use mago_span::{HasSpan, Span};
+use mago_syntax::ast::*;
+
+use crate::file::File;
+use crate::results::Violation;
+use crate::rules::visitor::{Scope, Traversal, Visitor};
+use crate::symbols::is_builtin_type;
+
+/// How the code depends on a class-like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DependencyKind {
+    Use,
+    Extends,
+    Implements,
+    TraitUse,
+    TypeHint,
+    New,
+    StaticCall,
+    StaticAccess,
+    Instanceof,
+}
+
+impl DependencyKind {
+    pub fn noun(&self) -> &'static str {
+        match self {
+            DependencyKind::Use => "use",
+            DependencyKind::Extends => "extends",
+            DependencyKind::Implements => "implements",
+            DependencyKind::TraitUse => "trait use",
+            DependencyKind::TypeHint => "type hint",
+            DependencyKind::New => "new",
+            DependencyKind::StaticCall => "static call",
+            DependencyKind::StaticAccess => "static access",
+            DependencyKind::Instanceof => "instanceof",
+        }
+    }
+}
+
+/// A class-like referenced by the code, named as it is written, except for
+/// the `use` imports which are fully qualified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Dependency {
+    pub name: String,
+    pub kind: DependencyKind,
+    pub span: Span,
+}
+
+impl Dependency {
+    fn new(name: &[u8], kind: DependencyKind, span: Span) -> Option<Self> {
+        let name = String::from_utf8_lossy(name).into_owned();
+        if name.is_empty() || is_builtin_type(name.trim_start_matches('\\')) {
+            return None;
+        }
+
+        Some(Self { name, kind, span })
+    }
+
+    /// The fully qualified name of the class-like.
+    pub fn resolve(&self, file: &File<'_>) -> String {
+        match self.kind {
+            DependencyKind::Use => self.name.trim_start_matches('\\').to_string(),
+            _ => file
+                .resolver_at(self.span.start.offset)
+                .resolve_class(&self.name),
+        }
+    }
+}
+
+/// The class-likes a statement depends on by itself, without its children:
+/// the parents of a class-like and the classes imported by `use`.
+pub fn statement_dependencies(statement: &Statement<'_>) -> Vec<Dependency> {
+    let mut dependencies = vec![];
+    match statement {
+        Statement::Class(class) => {
+            parents(&class.extends, &class.implements, &mut dependencies);
+        }
+        Statement::Interface(interface) => {
+            parents(&interface.extends, &None, &mut dependencies);
+        }
+        Statement::Enum(r#enum) => {
+            parents(&None, &r#enum.implements, &mut dependencies);
+        }
+        Statement::Use(r#use) => use_dependencies(r#use, &mut dependencies),
+        _ => {}
+    }
+
+    dependencies
+}
+
+/// The traits used by a class-like member.
+pub fn member_dependencies(member: &ClassLikeMember<'_>) -> Vec<Dependency> {
+    match member {
+        ClassLikeMember::TraitUse(trait_use) => trait_use
+            .trait_names
+            .iter()
+            .filter_map(|name| Dependency::new(name.value(), DependencyKind::TraitUse, name.span()))
+            .collect(),
+        _ => vec![],
+    }
+}
+
+/// The class-like named by a type hint. The parts of union, intersection
+/// and nullable hints are visited on their own.
+pub fn hint_dependency(hint: &Hint<'_>) -> Option<Dependency> {
+    match hint {
+        Hint::Identifier(identifier) => Dependency::new(
+            identifier.value(),
+            DependencyKind::TypeHint,
+            identifier.span(),
+        ),
+        _ => None,
+    }
+}
+
+/// The class-likes an expression depends on by itself, without its
+/// sub-expressions: instantiated, statically called or accessed classes, the
+/// right side of `instanceof` and the parents of anonymous classes.
+pub fn expression_dependencies(expression: &Expression<'_>) -> Vec<Dependency> {
+    let mut dependencies = vec![];
+    let class = match expression {
+        Expression::Instantiation(instantiation) => {
+            Some((instantiation.class, DependencyKind::New))
+        }
+        Expression::Call(Call::StaticMethod(method)) => {
+            Some((method.class, DependencyKind::StaticCall))
+        }
+        Expression::PartialApplication(PartialApplication::StaticMethod(partial)) => {
+            Some((partial.class, DependencyKind::StaticCall))
+        }
+        Expression::Access(Access::ClassConstant(constant)) => {
+            Some((constant.class, DependencyKind::StaticAccess))
+        }
+        Expression::Access(Access::StaticProperty(property)) => {
+            Some((property.class, DependencyKind::StaticAccess))
+        }
+        Expression::Binary(binary) if matches!(binary.operator, BinaryOperator::Instanceof(_)) => {
+            Some((binary.rhs, DependencyKind::Instanceof))
+        }
+        Expression::AnonymousClass(anonymous_class) => {
+            parents(
+                &anonymous_class.extends,
+                &anonymous_class.implements,
+                &mut dependencies,
+            );
+            None
+        }
+        _ => None,
+    };
+    if let Some((class, kind)) = class {
+        dependencies.extend(class_expression(class, kind));
+    }
+
+    dependencies
+}
+
+fn class_expression(expression: &Expression<'_>, kind: DependencyKind) -> Option<Dependency> {
+    match expression {
+        Expression::Identifier(identifier) => {
+            Dependency::new(identifier.value(), kind, identifier.span())
+        }
+        Expression::Parenthesized(parenthesized) => {
+            class_expression(parenthesized.expression, kind)
+        }
+        _ => None,
+    }
+}
+
+fn parents(
+    extends: &Option<Extends<'_>>,
+    implements: &Option<Implements<'_>>,
+    dependencies: &mut Vec<Dependency>,
+) {
+    if let Some(extends) = extends {
+        for parent in extends.types.iter() {
+            dependencies.extend(Dependency::new(
+                parent.value(),
+                DependencyKind::Extends,
+                parent.span(),
+            ));
+        }
+    }
+    if let Some(implements) = implements {
+        for interface in implements.types.iter() {
+            dependencies.extend(Dependency::new(
+                interface.value(),
+                DependencyKind::Implements,
+                interface.span(),
+            ));
+        }
+    }
+}
+
+/// The classes imported by a `use` statement, not its functions and
+/// constants.
+fn use_dependencies(r#use: &Use<'_>, dependencies: &mut Vec<Dependency>) {
+    let mut import = |prefix: Option<&[u8]>, item: &UseItem<'_>| {
+        let name = match prefix {
+            Some(prefix) => [prefix, b"\\", item.name.value()].concat(),
+            None => item.name.value().to_vec(),
+        };
+        dependencies.extend(Dependency::new(&name, DependencyKind::Use, item.span()));
+    };
+
+    match &r#use.items {
+        UseItems::Sequence(sequence) => {
+            for item in sequence.items.iter() {
+                import(None, item);
+            }
+        }
+        UseItems::TypedSequence(_) | UseItems::TypedList(_) => {}
+        UseItems::MixedList(list) => {
+            for item in list.items.iter().filter(|item| item.r#type.is_none()) {
+                import(Some(list.namespace.value()), &item.item);
+            }
+        }
+    }
+}
+
+/// A class-like declared in a file and the class-likes it depends on.
+pub struct ClassLikeDependencies {
+    /// The fully qualified name.
+    pub name: String,
+    pub is_class: bool,
+    /// The first reference to each class-like depended on, by fully
+    /// qualified name, excluding the class-like itself.
+    pub dependencies: BTreeMap<String, Span>,
+}
+
+/// The class-likes declared in a file, with their dependencies. The parents
+/// of an anonymous class are dependencies of the enclosing class-like, its
+/// body isn't.
+pub fn class_like_dependencies(file: &File<'_>) -> Vec<ClassLikeDependencies> {
+    let mut class_likes = vec![];
+    if let Some(program) = file.ast {
+        let mut visitors: Vec<Box<dyn Visitor<'_> + '_>> = vec![Box::new(Collector {
+            file,
+            stack: vec![],
+            class_likes: &mut class_likes,
+        })];
+        Traversal::new(&mut visitors, false).walk(program);
+    }
+
+    class_likes
+}
+
+/// The class-likes each class of a file depends on, by fully qualified name.
+pub fn class_dependencies(file: &File<'_>) -> HashMap<String, HashSet<String>> {
+    class_like_dependencies(file)
+        .into_iter()
+        .filter(|class_like| class_like.is_class)
+        .map(|class_like| {
+            let dependencies = class_like.dependencies.into_keys().collect();
+            (class_like.name, dependencies)
+        })
+        .collect()
+}
+
+struct Collector<'c, 'f> {
+    file: &'c File<'f>,
+    /// `None` for the anonymous classes, innermost last.
+    stack: Vec<Option<ClassLikeDependencies>>,
+    class_likes: &'c mut Vec<ClassLikeDependencies>,
+}
+
+impl Collector<'_, '_> {
+    fn add(&mut self, dependencies: Vec<Dependency>) {
+        let class_like = match self.stack.last_mut() {
+            Some(Some(class_like)) => class_like,
+            _ => return,
+        };
+        for dependency in dependencies {
+            let name = dependency.resolve(self.file);
+            if !name.eq_ignore_ascii_case(&class_like.name) {
+                class_like
+                    .dependencies
+                    .entry(name)
+                    .or_insert(dependency.span);
+            }
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for Collector<'_, '_> {
+    fn enter_statement(&mut self, statement: &'a Statement<'a>, scope: &Scope) {
+        let declared = match statement {
+            Statement::Class(class) => Some((class.name.value, true)),
+            Statement::Interface(interface) => Some((interface.name.value, false)),
+            Statement::Trait(r#trait) => Some((r#trait.name.value, false)),
+            Statement::Enum(r#enum) => Some((r#enum.name.value, false)),
+            _ => None,
+        };
+        if let Some((name, is_class)) = declared {
+            let name = String::from_utf8_lossy(name);
+            let name = match scope.namespace() {
+                Some(namespace) => format!("{}\\{}", namespace, name),
+                None => name.into_owned(),
+            };
+            self.stack.push(Some(ClassLikeDependencies {
+                name,
+                is_class,
+                dependencies: BTreeMap::new(),
+            }));
+        }
+        self.add(statement_dependencies(statement));
+    }
+
+    fn leave_statement(&mut self, statement: &'a Statement<'a>, _scope: &Scope) {
+        if let Statement::Class(_)
+        | Statement::Interface(_)
+        | Statement::Trait(_)
+        | Statement::Enum(_) = statement
+        {
+            if let Some(Some(class_like)) = self.stack.pop() {
+                self.class_likes.push(class_like);
+            }
+        }
+    }
+
+    fn enter_member(&mut self, member: &'a ClassLikeMember<'a>, _scope: &Scope) {
+        self.add(member_dependencies(member));
+    }
+
+    fn visit_hint(&mut self, hint: &'a Hint<'a>, _scope: &Scope) {
+        self.add(hint_dependency(hint).into_iter().collect());
+    }
+
+    fn enter_expression(&mut self, expression: &'a Expression<'a>, _scope: &Scope) {
+        self.add(expression_dependencies(expression));
+        if let Expression::AnonymousClass(_) = expression {
+            self.stack.push(None);
+        }
+    }
+
+    fn leave_expression(&mut self, expression: &'a Expression<'a>, _scope: &Scope) {
+        if let Expression::AnonymousClass(_) = expression {
+            self.stack.pop();
+        }
+    }
+
+    fn finish(self: Box<Self>) -> Vec<Violation> {
+        vec![]
+    }
+}
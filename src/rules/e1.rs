@@ -1,7 +1,8 @@
+use mago_span::Span;
 use mago_syntax::ast::{OpeningTag, Statement};
 
 use crate::file::File;
-use crate::results::Violation;
+use crate::results::{Edit, Violation};
 
 pub(crate) static CODE: &str = "E0001";
 static DESCRIPTION: &str = "Opening tag position";
@@ -38,12 +39,18 @@ impl crate::rules::Rule for Rule {
 
             let line = file.line_number(span.start.offset);
             let column = file.column_number(span.start.offset);
+            let edits = Self::leading_whitespace_edit(file, span)
+                .into_iter()
+                .collect::<Vec<_>>();
 
             if line > 1 {
                 let suggestion = String::from(
                     "The opening tag is not on the right line. This should always be the first line in a PHP file.",
                 );
-                violations.push(self.new_violation(file, suggestion, span));
+                violations.push(
+                    self.new_violation(file, suggestion, span)
+                        .with_edits(edits.clone()),
+                );
             }
 
             if column > 0 {
@@ -51,7 +58,7 @@ impl crate::rules::Rule for Rule {
                     "The opening tag doesn't start at the right column: {}.",
                     column + 1
                 );
-                violations.push(self.new_violation(file, suggestion, span));
+                violations.push(self.new_violation(file, suggestion, span).with_edits(edits));
             }
         }
 
@@ -69,9 +76,25 @@ impl crate::rules::Rule for Rule {
     }
 }
 
+impl Rule {
+    /// Removes what precedes the opening tag, as long as it is only whitespace.
+    fn leading_whitespace_edit(file: &File<'_>, tag: Span) -> Option<Edit> {
+        let before = file.ast?.source_text.get(..tag.start.offset as usize)?;
+        if !before.iter().all(|b| b.is_ascii_whitespace()) {
+            return None;
+        }
+
+        Some(Edit {
+            start: 0,
+            end: before.len(),
+            replacement: String::new(),
+        })
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::rules::tests::analyze_file_for_rule;
+    use crate::rules::tests::{analyze_file_for_rule, fix_file_for_rule};
 
     use super::*;
 
@@ -101,6 +124,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn fix_removes_whitespace_before_opening_tag() {
+        let fixed = fix_file_for_rule("e1/full_opening_tag_not_first_line.php", CODE);
+        assert!(fixed.starts_with("<?php"));
+
+        let fixed = fix_file_for_rule("e1/full_opening_tag_not_first_column.php", CODE);
+        assert!(fixed.starts_with("<?php"));
+    }
+
     #[test]
     fn short_opening_tag_valid() {
         let violations = analyze_file_for_rule("e1/short_opening_tag_valid.php", CODE);
@@ -1,13 +1,14 @@
-use mago_span::HasSpan;
+use std::collections::BTreeMap;
+
 use mago_syntax::ast::{
-    ClassLikeMember, ForBody, ForeachBody, IfBody, MethodBody, Sequence, Statement, SwitchBody,
-    SwitchCase, WhileBody,
+    ForBody, ForeachBody, IfBody, Sequence, Statement, SwitchBody, SwitchCase, WhileBody,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::file::File;
-use crate::results::Violation;
+use crate::results::{Severity, Violation};
+use crate::rules::callable::{callables, threshold, CallableKind};
 
 pub(crate) static CODE: &str = "E0010";
 static DESCRIPTION: &str = "Npath complexity";
@@ -15,11 +16,16 @@ static DESCRIPTION: &str = "Npath complexity";
 #[derive(Deserialize, Serialize)]
 pub struct Settings {
     max_paths: i64,
+    #[serde(default)]
+    max_paths_by_kind: BTreeMap<CallableKind, i64>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
-        Settings { max_paths: 200 }
+        Settings {
+            max_paths: 200,
+            max_paths_by_kind: BTreeMap::new(),
+        }
     }
 }
 
@@ -37,6 +43,10 @@ impl crate::rules::Rule for Rule {
         String::from(DESCRIPTION)
     }
 
+    fn severity(&self) -> Severity {
+        Severity::Major
+    }
+
     fn do_validate(&self, _file: &File<'_>) -> bool {
         true
     }
@@ -51,24 +61,31 @@ impl crate::rules::Rule for Rule {
     fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
         let mut violations = Vec::new();
 
-        if let Statement::Class(class) = statement {
-            for member in class.members.iter() {
-                if let ClassLikeMember::Method(method) = member {
-                    if let MethodBody::Concrete(block) = &method.body {
-                        let npath = calculate_npath(&block.statements);
-                        if npath > self.settings.max_paths {
-                            let suggestion = format!(
-                                "The body of {} method has {} paths. Reduce the amount of paths.",
-                                String::from_utf8_lossy(method.name.value), npath,
-                            );
-                            violations.push(self.new_violation(file, suggestion, method.span()));
-                        }
-                    }
+        for callable in callables(statement) {
+            if let Some(statements) = callable.statements() {
+                let npath = calculate_npath(statements);
+                let max_paths = threshold(
+                    self.settings.max_paths,
+                    &self.settings.max_paths_by_kind,
+                    callable.kind,
+                );
+                if npath > max_paths {
+                    let suggestion = format!(
+                        "The body of {} {} has {} paths. Reduce the amount of paths.",
+                        callable.name,
+                        callable.kind.noun(),
+                        npath,
+                    );
+                    violations.push(self.new_violation(file, suggestion, callable.span));
                 }
             }
         }
         violations
     }
+
+    fn validate_top_level_statements(&self) -> bool {
+        true
+    }
 }
 
 fn calculate_npath(statements: &Sequence<'_, Statement<'_>>) -> i64 {
@@ -187,7 +204,7 @@ fn calculate_statement_npath(statement: &Statement<'_>) -> i64 {
 
 #[cfg(test)]
 mod tests {
-    use crate::rules::tests::analyze_file_for_rule;
+    use crate::rules::tests::{analyze_file_for_rule, analyze_file_for_rule_with_settings};
 
     use super::*;
 
@@ -201,4 +218,20 @@ mod tests {
                 .to_string()
         );
     }
+
+    #[test]
+    fn closure_over_kind_threshold() {
+        assert!(analyze_file_for_rule("e10/npath_closure.php", CODE).is_empty());
+
+        let violations = analyze_file_for_rule_with_settings(
+            "e10/npath_closure.php",
+            CODE,
+            serde_json::json!({"max_paths": 200, "max_paths_by_kind": {"closure": 2}}),
+        );
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations.first().unwrap().suggestion,
+            "The body of {closure} closure has 3 paths. Reduce the amount of paths.".to_string()
+        );
+    }
 }
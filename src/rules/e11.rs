@@ -1,8 +1,10 @@
 use mago_span::HasSpan;
-use mago_syntax::ast::{Call, Expression, Statement, UnaryPrefixOperator};
+use mago_syntax::ast::{Expression, UnaryPrefixOperator};
 
 use crate::file::File;
-use crate::results::Violation;
+use crate::results::{Edit, Severity, Violation};
+use crate::rules::visitor::{Scope, Visitor};
+use crate::rules::Rule as RuleTrait;
 
 pub(crate) static CODE: &str = "E0011";
 static DESCRIPTION: &str = "Detect the error suppression symbol: @";
@@ -10,7 +12,7 @@ static DESCRIPTION: &str = "Detect the error suppression symbol: @";
 #[derive(Default)]
 pub struct Rule {}
 
-impl crate::rules::Rule for Rule {
+impl RuleTrait for Rule {
     fn get_code(&self) -> String {
         String::from(CODE)
     }
@@ -19,53 +21,51 @@ impl crate::rules::Rule for Rule {
         String::from(DESCRIPTION)
     }
 
+    fn severity(&self) -> Severity {
+        Severity::Major
+    }
+
     fn do_validate(&self, _file: &File<'_>) -> bool {
         true
     }
 
-    fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
-        let mut violations = Vec::new();
-        let flatten_statements = self.flatten_statements_to_validate(statement);
-
-        for stmt in flatten_statements {
-            if let Statement::Expression(expr_stmt) = stmt {
-                check_expression(file, self, expr_stmt.expression, &mut violations);
-            }
-            if let Statement::Return(ret) = stmt {
-                if let Some(value) = ret.value {
-                    check_expression(file, self, value, &mut violations);
-                }
-            }
-        }
-
-        violations
+    fn visitor<'a>(&'a self, file: &'a File<'a>) -> Option<Box<dyn Visitor<'a> + 'a>> {
+        Some(Box::new(ErrorSuppressions {
+            rule: self,
+            file,
+            violations: vec![],
+        }))
     }
 }
 
-fn check_expression(
-    file: &File<'_>,
-    rule: &dyn crate::rules::Rule,
-    expr: &Expression<'_>,
-    violations: &mut Vec<Violation>,
-) {
-    match expr {
-        Expression::UnaryPrefix(prefix) => {
-            if let UnaryPrefixOperator::ErrorControl(_) = prefix.operator {
+struct ErrorSuppressions<'a> {
+    rule: &'a Rule,
+    file: &'a File<'a>,
+    violations: Vec<Violation>,
+}
+
+impl<'a> Visitor<'a> for ErrorSuppressions<'a> {
+    fn enter_expression(&mut self, expression: &'a Expression<'a>, _scope: &Scope) {
+        if let Expression::UnaryPrefix(prefix) = expression {
+            if let UnaryPrefixOperator::ErrorControl(operator) = prefix.operator {
                 let suggestion = "Error supression(@) symbol found. Remove it.".to_string();
-                violations.push(rule.new_violation(file, suggestion, prefix.span()));
+                self.violations.push(
+                    self.rule
+                        .new_violation(self.file, suggestion, prefix.span())
+                        .with_edits(vec![Edit::replace(operator, "")]),
+                );
             }
-            check_expression(file, rule, prefix.operand, violations);
-        }
-        Expression::Call(Call::Method(m)) => {
-            check_expression(file, rule, m.object, violations);
         }
-        _ => {}
+    }
+
+    fn finish(self: Box<Self>) -> Vec<Violation> {
+        self.violations
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::rules::tests::analyze_file_for_rule;
+    use crate::rules::tests::{analyze_file_for_rule, fix_file_for_rule};
 
     use super::*;
 
@@ -75,4 +75,19 @@ mod tests {
 
         assert!(violations.len().gt(&0));
     }
+
+    #[test]
+    fn nested_in_expressions() {
+        let violations = analyze_file_for_rule("e11/detect_@_in_expressions.php", CODE);
+
+        assert_eq!(violations.len(), 3);
+    }
+
+    #[test]
+    fn fix_removes_error_suppression() {
+        let fixed = fix_file_for_rule("e11/detect_@.php", CODE);
+
+        assert!(fixed.contains("        $this->fetch();"));
+        assert!(!fixed.contains('@'));
+    }
 }
@@ -4,7 +4,9 @@ use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::file::File;
-use crate::results::Violation;
+use crate::results::{Severity, Violation};
+use crate::rules::callable::CallableKind;
+use crate::rules::visitor::{Scope, Visitor};
 use crate::rules::Rule as RuleTrait;
 
 pub(crate) static CODE: &str = "E0012";
@@ -44,6 +46,10 @@ impl RuleTrait for Rule {
         String::from(DESCRIPTION)
     }
 
+    fn severity(&self) -> Severity {
+        Severity::Critical
+    }
+
     fn set_config(&mut self, json: &Value) {
         match serde_json::from_value(json.to_owned()) {
             Ok(settings) => self.settings = settings,
@@ -52,238 +58,137 @@ impl RuleTrait for Rule {
     }
 
     fn do_validate(&self, file: &File<'_>) -> bool {
-        if let Some(ns) = &file.namespace {
-            return crate::rules::do_validate_namespace(
-                ns.clone(),
-                &self.settings.include_namespaces,
-                &self.settings.exclude_namespaces,
-            );
-        }
-
-        true
+        file.declared_symbols
+            .iter()
+            .any(|symbol| self.is_namespace_included(symbol.namespace.as_deref()))
     }
 
-    fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
-        let mut violations = Vec::new();
-
-        if let Statement::Class(class) = statement {
-            if self.implements_reset_interface(class) {
-                return violations;
-            }
-
-            for member in class.members.iter() {
-                if let ClassLikeMember::Method(method) = member {
-                    if method.name.value == b"__construct" {
-                        continue;
-                    }
+    fn visitor<'a>(&'a self, file: &'a File<'a>) -> Option<Box<dyn Visitor<'a> + 'a>> {
+        Some(Box::new(PropertyAssignments {
+            rule: self,
+            file,
+            services: vec![],
+            violations: vec![],
+        }))
+    }
+}
 
-                    if let MethodBody::Concrete(block) = &method.body {
-                        for stmt in block.statements.iter() {
-                            self.find_property_assignments(file, stmt, &mut violations);
-                        }
-                    }
-                }
-            }
-        }
+/// Finds the assignments to the properties of services outside of their
+/// constructor, including in closures and nested expressions.
+struct PropertyAssignments<'a> {
+    rule: &'a Rule,
+    file: &'a File<'a>,
+    /// Whether each enclosing class-like is a service, innermost last.
+    services: Vec<bool>,
+    violations: Vec<Violation>,
+}
 
-        violations
+impl<'a> PropertyAssignments<'a> {
+    fn in_service_method(&self, scope: &Scope) -> bool {
+        self.services.last() == Some(&true)
+            && matches!(
+                scope.named_function_like(),
+                Some((CallableKind::Method, name)) if name != "__construct"
+            )
     }
 }
 
-impl Rule {
-    fn implements_reset_interface(&self, class: &Class<'_>) -> bool {
-        if self.settings.reset_interfaces.is_empty() {
-            return false;
-        }
-
-        if let Some(implements) = &class.implements {
-            for interface in implements.types.iter() {
-                let name = identifier_name(interface);
-                for reset_interface in &self.settings.reset_interfaces {
-                    if name.ends_with(reset_interface) {
-                        return true;
-                    }
-                }
+impl<'a> Visitor<'a> for PropertyAssignments<'a> {
+    fn enter_statement(&mut self, statement: &'a Statement<'a>, scope: &Scope) {
+        match statement {
+            Statement::Class(class) => self.services.push(
+                self.rule.is_namespace_included(scope.namespace())
+                    && !self.rule.implements_reset_interface(class),
+            ),
+            Statement::Interface(_) | Statement::Trait(_) | Statement::Enum(_) => {
+                self.services.push(false)
             }
+            _ => {}
         }
-        false
     }
 
-    fn find_property_assignments(
-        &self,
-        file: &File<'_>,
-        statement: &Statement<'_>,
-        violations: &mut Vec<Violation>,
-    ) {
-        if let Statement::Expression(expr_stmt) = statement {
-            self.check_expression(file, expr_stmt.expression, violations);
+    fn leave_statement(&mut self, statement: &'a Statement<'a>, _scope: &Scope) {
+        if let Statement::Class(_)
+        | Statement::Interface(_)
+        | Statement::Trait(_)
+        | Statement::Enum(_) = statement
+        {
+            self.services.pop();
         }
+    }
 
-        match statement {
-            Statement::Block(block) => {
-                for stmt in block.statements.iter() {
-                    self.find_property_assignments(file, stmt, violations);
-                }
-            }
-            Statement::If(if_stmt) => match &if_stmt.body {
-                IfBody::Statement(body) => {
-                    self.find_property_assignments(file, body.statement, violations);
-                    for clauses in body.else_if_clauses.iter() {
-                        self.find_property_assignments(file, clauses.statement, violations);
-                    }
-                    if let Some(else_clause) = &body.else_clause {
-                        self.find_property_assignments(file, else_clause.statement, violations);
-                    }
-                }
-                IfBody::ColonDelimited(body) => {
-                    for stmt in body.statements.iter() {
-                        self.find_property_assignments(file, stmt, violations);
-                    }
-                    for clauses in body.else_if_clauses.iter() {
-                        for stmt in clauses.statements.iter() {
-                            self.find_property_assignments(file, stmt, violations);
-                        }
-                    }
-                    if let Some(else_clause) = &body.else_clause {
-                        for stmt in else_clause.statements.iter() {
-                            self.find_property_assignments(file, stmt, violations);
-                        }
-                    }
-                }
-            },
-            Statement::While(while_stmt) => match &while_stmt.body {
-                WhileBody::Statement(body) => {
-                    self.find_property_assignments(file, body, violations);
-                }
-                WhileBody::ColonDelimited(body) => {
-                    for stmt in body.statements.iter() {
-                        self.find_property_assignments(file, stmt, violations);
-                    }
-                }
-            },
-            Statement::DoWhile(do_while) => {
-                self.find_property_assignments(file, do_while.statement, violations);
-            }
-            Statement::Foreach(foreach) => match &foreach.body {
-                ForeachBody::Statement(body) => {
-                    self.find_property_assignments(file, body, violations);
-                }
-                ForeachBody::ColonDelimited(body) => {
-                    for stmt in body.statements.iter() {
-                        self.find_property_assignments(file, stmt, violations);
-                    }
-                }
-            },
-            Statement::For(for_stmt) => match &for_stmt.body {
-                ForBody::Statement(body) => {
-                    self.find_property_assignments(file, body, violations);
-                }
-                ForBody::ColonDelimited(body) => {
-                    for stmt in body.statements.iter() {
-                        self.find_property_assignments(file, stmt, violations);
-                    }
-                }
-            },
-            Statement::Switch(switch) => {
-                let cases = match &switch.body {
-                    SwitchBody::BraceDelimited(body) => &body.cases,
-                    SwitchBody::ColonDelimited(body) => &body.cases,
-                };
-                for case in cases.iter() {
-                    let statements = match case {
-                        SwitchCase::Expression(c) => &c.statements,
-                        SwitchCase::Default(c) => &c.statements,
-                    };
-                    for stmt in statements.iter() {
-                        self.find_property_assignments(file, stmt, violations);
-                    }
-                }
-            }
-            Statement::Try(try_stmt) => {
-                for stmt in try_stmt.block.statements.iter() {
-                    self.find_property_assignments(file, stmt, violations);
-                }
-                for catch in try_stmt.catch_clauses.iter() {
-                    for stmt in catch.block.statements.iter() {
-                        self.find_property_assignments(file, stmt, violations);
-                    }
-                }
-                if let Some(finally) = &try_stmt.finally_clause {
-                    for stmt in finally.block.statements.iter() {
-                        self.find_property_assignments(file, stmt, violations);
-                    }
-                }
-            }
-            Statement::Return(ret) => {
-                if let Some(value) = ret.value {
-                    self.check_expression(file, value, violations);
-                }
-            }
-            _ => {}
+    fn enter_expression(&mut self, expression: &'a Expression<'a>, scope: &Scope) {
+        if let Expression::AnonymousClass(_) = expression {
+            self.services.push(false);
+            return;
+        }
+        if !self.in_service_method(scope) {
+            return;
         }
-    }
 
-    fn check_expression(
-        &self,
-        file: &File<'_>,
-        expression: &Expression<'_>,
-        violations: &mut Vec<Violation>,
-    ) {
         match expression {
             Expression::Assignment(assignment) => {
-                self.check_assignment_lhs(file, assignment.lhs, violations);
-                self.check_expression(file, assignment.rhs, violations);
+                self.rule
+                    .check_assignment_lhs(self.file, assignment.lhs, &mut self.violations);
             }
             Expression::UnaryPrefix(prefix) => {
                 if let UnaryPrefixOperator::PreIncrement(_) | UnaryPrefixOperator::PreDecrement(_) =
                     prefix.operator
                 {
-                    self.check_assignment_lhs(file, prefix.operand, violations);
+                    self.rule
+                        .check_assignment_lhs(self.file, prefix.operand, &mut self.violations);
                 }
-                self.check_expression(file, prefix.operand, violations);
             }
             Expression::UnaryPostfix(postfix) => {
                 // UnaryPostfixOperator only has PostIncrement/PostDecrement, both of
                 // which are assignment-producing, so any postfix reaches the LHS check.
                 let _: &UnaryPostfixOperator = &postfix.operator;
-                self.check_assignment_lhs(file, postfix.operand, violations);
+                self.rule
+                    .check_assignment_lhs(self.file, postfix.operand, &mut self.violations);
             }
-            Expression::Call(call) => match call {
-                Call::Function(f) => {
-                    self.check_argument_list(file, &f.argument_list, violations);
-                }
-                Call::Method(m) => {
-                    self.check_expression(file, m.object, violations);
-                    self.check_argument_list(file, &m.argument_list, violations);
-                }
-                Call::NullSafeMethod(m) => {
-                    self.check_expression(file, m.object, violations);
-                    self.check_argument_list(file, &m.argument_list, violations);
-                }
-                Call::StaticMethod(m) => {
-                    self.check_argument_list(file, &m.argument_list, violations);
-                }
-            },
             _ => {}
         }
     }
 
-    fn check_argument_list(
-        &self,
-        file: &File<'_>,
-        argument_list: &ArgumentList<'_>,
-        violations: &mut Vec<Violation>,
-    ) {
-        for argument in argument_list.arguments.iter() {
-            match argument {
-                Argument::Positional(arg) => {
-                    self.check_expression(file, arg.value, violations);
-                }
-                Argument::Named(arg) => {
-                    self.check_expression(file, arg.value, violations);
+    fn leave_expression(&mut self, expression: &'a Expression<'a>, _scope: &Scope) {
+        if let Expression::AnonymousClass(_) = expression {
+            self.services.pop();
+        }
+    }
+
+    fn finish(self: Box<Self>) -> Vec<Violation> {
+        self.violations
+    }
+}
+
+impl Rule {
+    fn is_namespace_included(&self, namespace: Option<&str>) -> bool {
+        match namespace {
+            Some(namespace) => crate::rules::do_validate_namespace(
+                namespace.to_string(),
+                &self.settings.include_namespaces,
+                &self.settings.exclude_namespaces,
+            ),
+            None => true,
+        }
+    }
+
+    fn implements_reset_interface(&self, class: &Class<'_>) -> bool {
+        if self.settings.reset_interfaces.is_empty() {
+            return false;
+        }
+
+        if let Some(implements) = &class.implements {
+            for interface in implements.types.iter() {
+                let name = identifier_name(interface);
+                for reset_interface in &self.settings.reset_interfaces {
+                    if name.ends_with(reset_interface) {
+                        return true;
+                    }
                 }
             }
         }
+        false
     }
 
     fn check_assignment_lhs(
@@ -343,3 +248,44 @@ fn is_self_or_static_or_class(class_id: &Expression<'_>) -> bool {
         _ => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::tests::analyze_file_for_rule;
+
+    use super::*;
+
+    #[test]
+    fn set_in_method() {
+        let violations = analyze_file_for_rule("e12/set_in_method.php", CODE);
+
+        assert_eq!(violations.len(), 1);
+    }
+
+    #[test]
+    fn set_in_constructor() {
+        let violations = analyze_file_for_rule("e12/set_in_constructor.php", CODE);
+
+        assert_eq!(violations.len(), 0);
+    }
+
+    #[test]
+    fn set_in_nested_expressions() {
+        for path in [
+            "e12/set_in_assigment.php",
+            "e12/set_in_null_coalescing.php",
+            "e12/set_in_return_nested_method.php",
+        ] {
+            let violations = analyze_file_for_rule(path, CODE);
+
+            assert_eq!(violations.len(), 1, "{path}");
+        }
+    }
+
+    #[test]
+    fn set_in_method_with_reset_interface() {
+        let violations = analyze_file_for_rule("e12/set_in_method_wth_reset_interface.php", CODE);
+
+        assert_eq!(violations.len(), 0);
+    }
+}
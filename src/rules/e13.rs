@@ -1,17 +1,19 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::HashSet;
 
 use mago_span::{HasSpan, Span};
 use mago_syntax::ast::*;
 
 use crate::file::File;
 use crate::results::Violation;
+use crate::rules::visitor::{Scope, Visitor};
+use crate::rules::Rule as RuleTrait;
 
 pub(crate) static CODE: &str = "E0013";
 static DESCRIPTION: &str = "Private method not being called.";
 
 pub struct Rule {}
 
-impl crate::rules::Rule for Rule {
+impl RuleTrait for Rule {
     fn get_code(&self) -> String {
         String::from(CODE)
     }
@@ -24,89 +26,110 @@ impl crate::rules::Rule for Rule {
         true
     }
 
-    fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
-        let mut violations = Vec::new();
-
-        if let Statement::Class(class) = statement {
-            // Collect all private method names and their spans
-            let mut private_methods: HashMap<String, Span> = HashMap::new();
-            // Collect all method names that are called anywhere in the class
-            let mut called_methods: HashSet<String> = HashSet::new();
-
-            for member in class.members.iter() {
-                if let ClassLikeMember::Method(method) = member {
-                    let method_name = String::from_utf8_lossy(method.name.value).into_owned();
-                    let is_private = method
-                        .modifiers
-                        .iter()
-                        .any(|m| matches!(m, Modifier::Private(_)));
-
-                    if is_private {
-                        private_methods.insert(method_name, method.span());
-                    }
+    fn visitor<'a>(&'a self, file: &'a File<'a>) -> Option<Box<dyn Visitor<'a> + 'a>> {
+        Some(Box::new(PrivateMethodCalls {
+            rule: self,
+            file,
+            classes: vec![],
+            violations: vec![],
+        }))
+    }
+}
 
-                    // Scan method body for method calls
-                    if let MethodBody::Concrete(block) = &method.body {
-                        for stmt in block.statements.iter() {
-                            let flat = self.flatten_statements_to_validate(stmt);
-                            for s in flat {
-                                if let Statement::Expression(expr_stmt) = s {
-                                    self.collect_called_methods(
-                                        expr_stmt.expression,
-                                        &mut called_methods,
-                                    );
-                                }
-                                if let Statement::Return(ret) = s {
-                                    if let Some(value) = ret.value {
-                                        self.collect_called_methods(value, &mut called_methods);
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-            }
+#[derive(Default)]
+struct ClassMethods {
+    /// Private methods in declaration order, with their spans.
+    private_methods: Vec<(String, Span)>,
+    /// Methods called anywhere in the class.
+    called_methods: HashSet<String>,
+}
 
-            // Report private methods that are never called
-            for (name, span) in &private_methods {
-                if !called_methods.contains(name) {
-                    let message = format!("The private method {} is not being called. ", name);
-                    violations.push(self.new_violation(file, message, *span));
-                }
+struct PrivateMethodCalls<'a> {
+    rule: &'a Rule,
+    file: &'a File<'a>,
+    /// `None` for the class-likes other than classes, innermost last.
+    classes: Vec<Option<ClassMethods>>,
+    violations: Vec<Violation>,
+}
+
+impl<'a> Visitor<'a> for PrivateMethodCalls<'a> {
+    fn enter_statement(&mut self, statement: &'a Statement<'a>, _scope: &Scope) {
+        match statement {
+            Statement::Class(_) => self.classes.push(Some(ClassMethods::default())),
+            Statement::Interface(_) | Statement::Trait(_) | Statement::Enum(_) => {
+                self.classes.push(None)
             }
+            _ => {}
         }
-
-        violations
     }
-}
 
-impl Rule {
-    fn collect_called_methods(&self, expr: &Expression<'_>, called: &mut HashSet<String>) {
-        match expr {
-            Expression::Call(Call::Method(m)) => {
-                // $this->methodName()
-                if let ClassLikeMemberSelector::Identifier(id) = &m.method {
-                    called.insert(String::from_utf8_lossy(id.value).into_owned());
-                }
-                self.collect_called_methods(m.object, called);
-                for arg in m.argument_list.arguments.iter() {
-                    match arg {
-                        Argument::Positional(a) => {
-                            self.collect_called_methods(a.value, called);
-                        }
-                        Argument::Named(a) => {
-                            self.collect_called_methods(a.value, called);
+    fn leave_statement(&mut self, statement: &'a Statement<'a>, _scope: &Scope) {
+        match statement {
+            Statement::Class(_) => {
+                if let Some(Some(class)) = self.classes.pop() {
+                    for (name, span) in class.private_methods {
+                        if !class.called_methods.contains(&name) {
+                            let message =
+                                format!("The private method {} is not being called. ", name);
+                            self.violations
+                                .push(self.rule.new_violation(self.file, message, span));
                         }
                     }
                 }
             }
-            Expression::Binary(bin) => {
-                self.collect_called_methods(bin.lhs, called);
-                self.collect_called_methods(bin.rhs, called);
+            Statement::Interface(_) | Statement::Trait(_) | Statement::Enum(_) => {
+                self.classes.pop();
             }
             _ => {}
         }
     }
+
+    fn enter_member(&mut self, member: &'a ClassLikeMember<'a>, _scope: &Scope) {
+        if let (ClassLikeMember::Method(method), Some(Some(class))) =
+            (member, self.classes.last_mut())
+        {
+            let is_private = method
+                .modifiers
+                .iter()
+                .any(|m| matches!(m, Modifier::Private(_)));
+            if is_private {
+                let name = String::from_utf8_lossy(method.name.value).into_owned();
+                class.private_methods.push((name, method.span()));
+            }
+        }
+    }
+
+    fn enter_expression(&mut self, expression: &'a Expression<'a>, _scope: &Scope) {
+        let selector = match expression {
+            Expression::AnonymousClass(_) => {
+                self.classes.push(None);
+                return;
+            }
+            // $this->method(), $object?->method(), self::method()
+            Expression::Call(Call::Method(call)) => &call.method,
+            Expression::Call(Call::NullSafeMethod(call)) => &call.method,
+            Expression::Call(Call::StaticMethod(call)) => &call.method,
+            _ => return,
+        };
+
+        if let (ClassLikeMemberSelector::Identifier(id), Some(Some(class))) =
+            (selector, self.classes.last_mut())
+        {
+            class
+                .called_methods
+                .insert(String::from_utf8_lossy(id.value).into_owned());
+        }
+    }
+
+    fn leave_expression(&mut self, expression: &'a Expression<'a>, _scope: &Scope) {
+        if let Expression::AnonymousClass(_) = expression {
+            self.classes.pop();
+        }
+    }
+
+    fn finish(self: Box<Self>) -> Vec<Violation> {
+        self.violations
+    }
 }
 
 #[cfg(test)]
@@ -121,4 +144,11 @@ mod tests {
         println!("{}", violations.len());
         assert!(violations.len().eq(&3));
     }
+
+    #[test]
+    fn called_in_expressions() {
+        let violations =
+            analyze_file_for_rule("e13/private_method_called_in_expressions.php", CODE);
+        assert_eq!(violations.len(), 0);
+    }
 }
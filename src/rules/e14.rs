@@ -1,66 +1,65 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use mago_span::{HasSpan, Span};
 use mago_syntax::ast::*;
 
 use crate::file::File;
 use crate::results::Violation;
+use crate::rules::visitor::{Scope, Visitor};
 use crate::rules::Rule as RuleTrait;
+use crate::symbols::{ClassKind, ClassSymbol, FileSymbols, NameResolver, SymbolTable};
 
 pub(crate) static CODE: &str = "E0014";
 static DESCRIPTION: &str =
     "Law of Demeter violation. Method chaining should be avoided unless returning the same object type.";
 
-/// Contains all collected types: methods, properties, and interface identities.
-#[derive(Default, Clone)]
+/// Name given to anonymous classes, as PHP does.
+static ANONYMOUS_CLASS: &str = "class@anonymous";
+
+/// The types known while validating a file: the symbols of the file, which
+/// take precedence over the ones of the project, and how the names used in
+/// the current namespace resolve.
 pub struct TypeRegistry {
-    pub methods: HashMap<String, HashMap<String, String>>,
-    pub properties: HashMap<String, HashMap<String, String>>,
-    pub interfaces: std::collections::HashSet<String>,
+    local: SymbolTable,
+    symbols: Arc<SymbolTable>,
+    resolver: NameResolver,
+}
+
+impl TypeRegistry {
+    fn class(&self, name: &str) -> Option<&ClassSymbol> {
+        self.local.class(name).or_else(|| self.symbols.class(name))
+    }
+
+    fn method_return(&self, class: &str, method: &str) -> Option<String> {
+        self.local
+            .method(class, method)
+            .or_else(|| self.symbols.method(class, method))?
+            .return_type
+            .clone()
+    }
+
+    fn property_type(&self, class: &str, property: &str) -> Option<String> {
+        self.local
+            .property(class, property)
+            .or_else(|| self.symbols.property(class, property))?
+            .r#type
+            .clone()
+    }
+
+    fn is_interface(&self, name: &str) -> bool {
+        self.class(name)
+            .is_some_and(|class| class.kind == ClassKind::Interface)
+    }
 }
 
 /// Maps local variable name → resolved type string
 type VarTypes = HashMap<String, String>;
 
 #[derive(Default)]
-pub struct Rule {
-    pub global_registry: std::sync::Mutex<TypeRegistry>,
-    /// Memoizes `resolve_object_type` by expression span for the duration of a
-    /// single `validate` call. Without it, the chain-fluency check re-resolves
-    /// the receiver of every chain link, making a chain of N calls cost
-    /// `O(phi^N)` and hanging on long fluent chains. Cleared per `validate`
-    /// because spans only carry byte offsets (no file id), so entries from one
-    /// file must not leak into the next.
-    resolve_cache: std::sync::Mutex<HashMap<Span, Option<String>>>,
-}
+pub struct Rule {}
 
 impl crate::rules::Rule for Rule {
-    fn index_file(&self, file: &File<'_>) {
-        if let Some(program) = file.ast {
-            let mut file_registry = TypeRegistry::default();
-            for statement in program.statements.iter() {
-                self.collect_types(statement, &mut file_registry);
-            }
-            if let Ok(mut global) = self.global_registry.lock() {
-                for (class_name, methods) in file_registry.methods {
-                    global
-                        .methods
-                        .entry(class_name)
-                        .or_default()
-                        .extend(methods);
-                }
-                for (class_name, props) in file_registry.properties {
-                    global
-                        .properties
-                        .entry(class_name)
-                        .or_default()
-                        .extend(props);
-                }
-                global.interfaces.extend(file_registry.interfaces);
-            }
-        }
-    }
-
     fn get_code(&self) -> String {
         String::from(CODE)
     }
@@ -73,177 +72,208 @@ impl crate::rules::Rule for Rule {
         true
     }
 
-    fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
-        let mut violations = Vec::new();
-
-        // Start each validation with an empty type cache; see `resolve_cache`.
-        if let Ok(mut cache) = self.resolve_cache.lock() {
-            cache.clear();
+    fn visitor<'a>(&'a self, file: &'a File<'a>) -> Option<Box<dyn Visitor<'a> + 'a>> {
+        let mut local = SymbolTable::default();
+        if let Some(program) = file.ast {
+            local.merge(FileSymbols::collect(program));
         }
 
-        // Build the type registry from ALL statements in the file so that cross-file
-        // references (e.g. a class using a trait defined elsewhere in the same file)
-        // are resolvable. Fall back to building from just this statement if no AST.
-        let mut registry = TypeRegistry::default();
-        if let Some(program) = file.ast {
-            for s in program.statements.iter() {
-                self.collect_types(s, &mut registry);
+        Some(Box::new(MethodChains {
+            rule: self,
+            file,
+            registry: TypeRegistry {
+                local,
+                symbols: file.symbols.clone(),
+                resolver: file.resolver_at(0),
+            },
+            class_likes: vec![],
+            var_types: vec![VarTypes::new()],
+            types: HashMap::new(),
+            violations: vec![],
+        }))
+    }
+
+    fn uses_symbols(&self) -> bool {
+        true
+    }
+}
+
+/// A class-like enclosing the code being visited.
+struct ClassLike {
+    /// Qualified name, `ANONYMOUS_CLASS` for anonymous classes.
+    name: String,
+    /// Return types of its methods and of the ones of its traits.
+    method_returns: HashMap<String, String>,
+}
+
+/// Follows the method chains of a file, with the types of the variables
+/// assigned along the way. Every link of a chain is checked when visited.
+struct MethodChains<'a> {
+    rule: &'a Rule,
+    file: &'a File<'a>,
+    registry: TypeRegistry,
+    /// Innermost last.
+    class_likes: Vec<ClassLike>,
+    /// Variables of the enclosing function-likes, innermost last, the first
+    /// ones being those of the top-level code.
+    var_types: Vec<VarTypes>,
+    /// Memoizes `object_type` by expression span. Without it, the fluency
+    /// check of every chain link resolves the receivers of the whole chain
+    /// again, making a chain of N calls cost `O(phi^N)` and hanging on long
+    /// fluent chains. Spans only carry byte offsets, which is fine as a
+    /// visitor sees a single file.
+    types: HashMap<Span, Option<String>>,
+    violations: Vec<Violation>,
+}
+
+impl<'a> Visitor<'a> for MethodChains<'a> {
+    fn enter_statement(&mut self, statement: &'a Statement<'a>, _scope: &Scope) {
+        match statement {
+            Statement::Namespace(namespace) => {
+                self.registry.resolver = self.file.resolver_at(namespace.span().start.offset);
             }
-        } else {
-            match statement {
-                Statement::Namespace(ns) => {
-                    for s in ns.statements().iter() {
-                        self.collect_types(s, &mut registry);
-                    }
-                }
-                _ => {
-                    self.collect_types(statement, &mut registry);
-                }
+            Statement::Class(class) => self.enter_class_like(Some(&class.name), &class.members),
+            Statement::Interface(interface) => {
+                self.enter_class_like(Some(&interface.name), &interface.members)
             }
-        }
-
-        if let Ok(global) = self.global_registry.lock() {
-            for (class_name, methods) in global.methods.iter() {
-                registry
-                    .methods
-                    .entry(class_name.clone())
-                    .or_default()
-                    .extend(methods.clone());
+            Statement::Trait(r#trait) => {
+                self.enter_class_like(Some(&r#trait.name), &r#trait.members)
             }
-            for (class_name, props) in global.properties.iter() {
-                registry
-                    .properties
-                    .entry(class_name.clone())
-                    .or_default()
-                    .extend(props.clone());
+            Statement::Enum(r#enum) => self.enter_class_like(Some(&r#enum.name), &r#enum.members),
+            Statement::Function(function) => {
+                self.enter_function_like(&function.parameter_list, false)
             }
-            registry.interfaces.extend(global.interfaces.clone());
+            _ => {}
         }
+    }
 
+    fn leave_statement(&mut self, statement: &'a Statement<'a>, _scope: &Scope) {
         match statement {
-            Statement::Namespace(ns) => {
-                for s in ns.statements().iter() {
-                    self.validate_statement(file, s, &registry, &mut violations);
-                }
+            Statement::Class(_)
+            | Statement::Interface(_)
+            | Statement::Trait(_)
+            | Statement::Enum(_) => {
+                self.class_likes.pop();
             }
-            _ => {
-                self.validate_statement(file, statement, &registry, &mut violations);
+            Statement::Function(_) => {
+                self.var_types.pop();
             }
+            _ => {}
         }
-
-        violations
     }
 
-    fn traverse_statements_to_validate<'a>(
-        &'a self,
-        flatten_statements: &mut Vec<&'a Statement<'a>>,
-        statement: &'a Statement<'a>,
-    ) {
-        // Only push top-level; we handle recursion ourselves to maintain class context.
-        flatten_statements.push(statement);
+    fn enter_member(&mut self, member: &'a ClassLikeMember<'a>, _scope: &Scope) {
+        if let ClassLikeMember::Method(method) = member {
+            self.enter_function_like(&method.parameter_list, false);
+        }
     }
-}
 
-impl Rule {
-    // -------------------------------------------------------------------------
-    // Phase 1: Build TypeRegistry from all class/trait/interface declarations
-    // -------------------------------------------------------------------------
+    fn leave_member(&mut self, member: &'a ClassLikeMember<'a>, _scope: &Scope) {
+        if let ClassLikeMember::Method(_) = member {
+            self.var_types.pop();
+        }
+    }
 
-    fn collect_types(&self, statement: &Statement<'_>, registry: &mut TypeRegistry) {
-        match statement {
-            Statement::Namespace(ns) => {
-                for s in ns.statements().iter() {
-                    self.collect_types(s, registry);
-                }
+    fn enter_expression(&mut self, expression: &'a Expression<'a>, _scope: &Scope) {
+        match expression {
+            Expression::AnonymousClass(class) => self.enter_class_like(None, &class.members),
+            Expression::Closure(closure) => {
+                self.enter_function_like(&closure.parameter_list, false)
             }
-            Statement::Class(class) => {
-                let name = String::from_utf8_lossy(class.name.value).into_owned();
-                let mut method_map = HashMap::new();
-                let mut prop_map = HashMap::new();
-                for member in class.members.iter() {
-                    match member {
-                        ClassLikeMember::Method(m) => {
-                            if let Some(hint) = &m.return_type_hint {
-                                if let Some(t) = self.extract_type_hint(&hint.hint) {
-                                    method_map.insert(String::from_utf8_lossy(m.name.value).into_owned(), t);
-                                }
-                            }
-                        }
-                        ClassLikeMember::Property(p) => {
-                            if let Some(hint) = p.hint() {
-                                if let Some(t) = self.extract_type_hint(hint) {
-                                    for var in p.variables() {
-                                        let prop_name =
-                                            std::str::from_utf8(var.name).unwrap_or_default().trim_start_matches('$').to_string();
-                                        prop_map.insert(prop_name, t.clone());
-                                    }
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                registry.methods.insert(name.clone(), method_map);
-                registry.properties.insert(name, prop_map);
+            // Arrow functions capture the variables of the enclosing scope
+            Expression::ArrowFunction(arrow_function) => {
+                self.enter_function_like(&arrow_function.parameter_list, true)
             }
-            Statement::Trait(trait_def) => {
-                let name = String::from_utf8_lossy(trait_def.name.value).into_owned();
-                let mut method_map = HashMap::new();
-                let mut prop_map = HashMap::new();
-                for member in trait_def.members.iter() {
-                    match member {
-                        ClassLikeMember::Method(m) => {
-                            if let Some(hint) = &m.return_type_hint {
-                                if let Some(t) = self.extract_type_hint(&hint.hint) {
-                                    method_map.insert(String::from_utf8_lossy(m.name.value).into_owned(), t);
-                                }
-                            }
-                        }
-                        ClassLikeMember::Property(p) => {
-                            if let Some(hint) = p.hint() {
-                                if let Some(t) = self.extract_type_hint(hint) {
-                                    for var in p.variables() {
-                                        let prop_name =
-                                            std::str::from_utf8(var.name).unwrap_or_default().trim_start_matches('$').to_string();
-                                        prop_map.insert(prop_name, t.clone());
-                                    }
-                                }
-                            }
-                        }
-                        _ => {}
-                    }
-                }
-                registry.methods.insert(name.clone(), method_map);
-                registry.properties.insert(name, prop_map);
+            Expression::Call(Call::Method(call)) => {
+                self.check_method_call(call.object, &call.method, call.span())
             }
-            Statement::Interface(iface) => {
-                let name = String::from_utf8_lossy(iface.name.value).into_owned();
-                let mut method_map = HashMap::new();
-                for member in iface.members.iter() {
-                    if let ClassLikeMember::Method(m) = member {
-                        if let Some(hint) = &m.return_type_hint {
-                            if let Some(t) = self.extract_type_hint(&hint.hint) {
-                                method_map.insert(String::from_utf8_lossy(m.name.value).into_owned(), t);
-                            }
-                        }
+            Expression::Call(Call::NullSafeMethod(call)) => {
+                self.check_method_call(call.object, &call.method, call.span())
+            }
+            _ => {}
+        }
+    }
+
+    fn leave_expression(&mut self, expression: &'a Expression<'a>, _scope: &Scope) {
+        match expression {
+            Expression::AnonymousClass(_) => {
+                self.class_likes.pop();
+            }
+            Expression::Closure(_) | Expression::ArrowFunction(_) => {
+                self.var_types.pop();
+            }
+            // Once the right-hand side has been checked with the previous types
+            Expression::Assignment(assignment) => {
+                if let Expression::Variable(Variable::Direct(d)) = assignment.lhs {
+                    let var_name = String::from_utf8_lossy(d.name).into_owned();
+                    let rhs_type = self.expression_type(assignment.rhs);
+                    if let Some(var_types) = self.var_types.last_mut() {
+                        match rhs_type {
+                            Some(t) => var_types.insert(var_name, t),
+                            None => var_types.remove(&var_name),
+                        };
                     }
                 }
-                registry.interfaces.insert(name.clone());
-                registry.methods.insert(name, method_map);
             }
             _ => {}
         }
     }
 
+    fn finish(self: Box<Self>) -> Vec<Violation> {
+        self.violations
+    }
+}
+
+impl MethodChains<'_> {
     // -------------------------------------------------------------------------
-    // Phase 2: Build merged method map for a class (including trait methods)
+    // Scopes: the enclosing class-likes and function-likes
     // -------------------------------------------------------------------------
 
+    fn enter_class_like(
+        &mut self,
+        name: Option<&LocalIdentifier<'_>>,
+        members: &Sequence<'_, ClassLikeMember<'_>>,
+    ) {
+        let name = match name {
+            Some(name) => self
+                .registry
+                .resolver
+                .qualify(&String::from_utf8_lossy(name.value)),
+            None => String::from(ANONYMOUS_CLASS),
+        };
+        let method_returns = self.build_class_method_map(&name, members);
+        self.class_likes.push(ClassLike {
+            name,
+            method_returns,
+        });
+    }
+
+    fn enter_function_like(&mut self, parameters: &FunctionLikeParameterList<'_>, inherit: bool) {
+        let mut var_types = match self.var_types.last() {
+            Some(var_types) if inherit => var_types.clone(),
+            _ => VarTypes::new(),
+        };
+        for param in parameters.parameters.iter() {
+            if let Some(hint) = &param.hint {
+                if let Some(t) = self.extract_type_hint(hint) {
+                    var_types.insert(String::from_utf8_lossy(param.variable.name).into_owned(), t);
+                }
+            }
+        }
+        self.var_types.push(var_types);
+    }
+
+    /// Name of the innermost class-like, empty outside of them.
+    fn current_class(&self) -> &str {
+        self.class_likes
+            .last()
+            .map_or("", |class_like| class_like.name.as_str())
+    }
+
     fn build_class_method_map(
         &self,
         class_name: &str,
         members: &Sequence<'_, ClassLikeMember<'_>>,
-        registry: &TypeRegistry,
     ) -> HashMap<String, String> {
         let mut map: HashMap<String, String> = HashMap::new();
 
@@ -260,12 +290,17 @@ impl Rule {
                 ClassLikeMember::TraitUse(trait_use) => {
                     // Merge methods from used traits
                     for trait_name_id in trait_use.trait_names.iter() {
-                        let trait_name = String::from_utf8_lossy(trait_name_id.value()).into_owned();
-                        if let Some(trait_map) = registry.methods.get(&trait_name) {
-                            for (method_name, ret_type) in trait_map {
-                                // Don't override class's own method definitions
-                                map.entry(method_name.clone())
-                                    .or_insert_with(|| ret_type.clone());
+                        let trait_name = self
+                            .registry
+                            .resolver
+                            .resolve_class(&String::from_utf8_lossy(trait_name_id.value()));
+                        if let Some(trait_def) = self.registry.class(&trait_name) {
+                            for method in &trait_def.methods {
+                                if let Some(ret_type) = &method.return_type {
+                                    // Don't override class's own method definitions
+                                    map.entry(method.name.clone())
+                                        .or_insert_with(|| ret_type.clone());
+                                }
                             }
                         }
                     }
@@ -274,15 +309,11 @@ impl Rule {
             }
         }
 
-        // Normalize "self" in return types: if a method says it returns the class name itself,
-        // treat it as "self" for the lookup later ONLY within the same class context.
-        // Actually, it's better to keep it as "self" and contextualize it during resolution,
-        // or just leave the class name.
-        let class_owned = class_name.to_string();
+        // Contextualize "self" to the actual class name to prevent false
+        // positives across classes
         for val in map.values_mut() {
             if *val == "self" || *val == "static" {
-                // Contextualize "self" to the actual class name to prevent false positives across classes
-                *val = class_owned.clone();
+                *val = class_name.to_string();
             }
         }
 
@@ -290,760 +321,29 @@ impl Rule {
     }
 
     // -------------------------------------------------------------------------
-    // Phase 3: Top-level statement validation dispatcher
+    // Chain links: `->method()` and `?->method()`
     // -------------------------------------------------------------------------
 
-    fn validate_statement(
-        &self,
-        file: &File<'_>,
-        statement: &Statement<'_>,
-        registry: &TypeRegistry,
-        violations: &mut Vec<Violation>,
-    ) {
-        match statement {
-            Statement::Class(class) => {
-                let class_name = String::from_utf8_lossy(class.name.value).into_owned();
-                let method_map = self.build_class_method_map(&class_name, &class.members, registry);
-
-                for member in class.members.iter() {
-                    if let ClassLikeMember::Method(method) = member {
-                        if let MethodBody::Concrete(block) = &method.body {
-                            let mut var_types = VarTypes::new();
-                            self.track_parameters(&method.parameter_list, &mut var_types);
-                            for stmt in block.statements.iter() {
-                                self.check_statement(
-                                    file,
-                                    stmt,
-                                    &method_map,
-                                    &class_name,
-                                    registry,
-                                    &mut var_types,
-                                    violations,
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-            Statement::Trait(trait_def) => {
-                let trait_name = String::from_utf8_lossy(trait_def.name.value).into_owned();
-                let method_map =
-                    self.build_class_method_map(&trait_name, &trait_def.members, registry);
-
-                for member in trait_def.members.iter() {
-                    if let ClassLikeMember::Method(method) = member {
-                        if let MethodBody::Concrete(block) = &method.body {
-                            let mut var_types = VarTypes::new();
-                            self.track_parameters(&method.parameter_list, &mut var_types);
-                            for stmt in block.statements.iter() {
-                                self.check_statement(
-                                    file,
-                                    stmt,
-                                    &method_map,
-                                    &trait_name,
-                                    registry,
-                                    &mut var_types,
-                                    violations,
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-            Statement::Function(func) => {
-                let mut var_types = VarTypes::new();
-                self.track_parameters(&func.parameter_list, &mut var_types);
-                for stmt in func.body.statements.iter() {
-                    self.check_statement(
-                        file,
-                        stmt,
-                        &HashMap::new(),
-                        "",
-                        registry,
-                        &mut var_types,
-                        violations,
-                    );
-                }
-            }
-            _ => {
-                let mut var_types = VarTypes::new();
-                self.check_statement(
-                    file,
-                    statement,
-                    &HashMap::new(),
-                    "",
-                    registry,
-                    &mut var_types,
-                    violations,
-                );
-            }
-        }
-    }
-
-    // -------------------------------------------------------------------------
-    // Phase 4: Statement checker (with variable type tracking)
-    // -------------------------------------------------------------------------
-
-    #[allow(clippy::too_many_arguments)]
-    fn check_statement(
-        &self,
-        file: &File<'_>,
-        statement: &Statement<'_>,
-        method_map: &HashMap<String, String>,
-        current_class: &str,
-        registry: &TypeRegistry,
-        var_types: &mut VarTypes,
-        violations: &mut Vec<Violation>,
+    fn check_method_call(
+        &mut self,
+        object: &Expression<'_>,
+        method_selector: &ClassLikeMemberSelector<'_>,
+        span: Span,
     ) {
-        match statement {
-            Statement::Expression(expr_stmt) => {
-                if let Expression::Assignment(assign) = expr_stmt.expression {
-                    let rhs_type = self.check_expression(
-                        file,
-                        assign.rhs,
-                        method_map,
-                        current_class,
-                        registry,
-                        var_types,
-                        violations,
-                    );
-                    if let Expression::Variable(Variable::Direct(d)) = assign.lhs {
-                        let var_name = String::from_utf8_lossy(d.name).into_owned();
-                        if let Some(t) = rhs_type {
-                            var_types.insert(var_name, t);
-                        } else {
-                            var_types.remove(&var_name);
-                        }
-                    }
-                    self.check_expression(
-                        file,
-                        assign.lhs,
-                        method_map,
-                        current_class,
-                        registry,
-                        var_types,
-                        violations,
-                    );
-                } else {
-                    self.check_expression(
-                        file,
-                        expr_stmt.expression,
-                        method_map,
-                        current_class,
-                        registry,
-                        var_types,
-                        violations,
-                    );
-                }
-            }
-            Statement::Return(ret_stmt) => {
-                if let Some(expr) = ret_stmt.value {
-                    self.check_expression(
-                        file,
-                        expr,
-                        method_map,
-                        current_class,
-                        registry,
-                        var_types,
-                        violations,
-                    );
-                }
-            }
-            Statement::Echo(echo_stmt) => {
-                for expr in echo_stmt.values.iter() {
-                    self.check_expression(
-                        file,
-                        expr,
-                        method_map,
-                        current_class,
-                        registry,
-                        var_types,
-                        violations,
-                    );
-                }
-            }
-            Statement::If(if_stmt) => {
-                self.check_expression(
-                    file,
-                    if_stmt.condition,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                match &if_stmt.body {
-                    IfBody::Statement(body) => {
-                        self.check_statement(
-                            file,
-                            body.statement,
-                            method_map,
-                            current_class,
-                            registry,
-                            var_types,
-                            violations,
-                        );
-                        for clause in body.else_if_clauses.iter() {
-                            self.check_expression(
-                                file,
-                                clause.condition,
-                                method_map,
-                                current_class,
-                                registry,
-                                var_types,
-                                violations,
-                            );
-                            self.check_statement(
-                                file,
-                                clause.statement,
-                                method_map,
-                                current_class,
-                                registry,
-                                var_types,
-                                violations,
-                            );
-                        }
-                        if let Some(else_clause) = &body.else_clause {
-                            self.check_statement(
-                                file,
-                                else_clause.statement,
-                                method_map,
-                                current_class,
-                                registry,
-                                var_types,
-                                violations,
-                            );
-                        }
-                    }
-                    IfBody::ColonDelimited(body) => {
-                        for s in body.statements.iter() {
-                            self.check_statement(
-                                file,
-                                s,
-                                method_map,
-                                current_class,
-                                registry,
-                                var_types,
-                                violations,
-                            );
-                        }
-                        for clause in body.else_if_clauses.iter() {
-                            self.check_expression(
-                                file,
-                                clause.condition,
-                                method_map,
-                                current_class,
-                                registry,
-                                var_types,
-                                violations,
-                            );
-                            for s in clause.statements.iter() {
-                                self.check_statement(
-                                    file,
-                                    s,
-                                    method_map,
-                                    current_class,
-                                    registry,
-                                    var_types,
-                                    violations,
-                                );
-                            }
-                        }
-                        if let Some(else_clause) = &body.else_clause {
-                            for s in else_clause.statements.iter() {
-                                self.check_statement(
-                                    file,
-                                    s,
-                                    method_map,
-                                    current_class,
-                                    registry,
-                                    var_types,
-                                    violations,
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-            Statement::While(while_stmt) => {
-                self.check_expression(
-                    file,
-                    while_stmt.condition,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                match &while_stmt.body {
-                    WhileBody::Statement(body) => {
-                        self.check_statement(
-                            file,
-                            body,
-                            method_map,
-                            current_class,
-                            registry,
-                            var_types,
-                            violations,
-                        );
-                    }
-                    WhileBody::ColonDelimited(body) => {
-                        for s in body.statements.iter() {
-                            self.check_statement(
-                                file,
-                                s,
-                                method_map,
-                                current_class,
-                                registry,
-                                var_types,
-                                violations,
-                            );
-                        }
-                    }
-                }
-            }
-            Statement::DoWhile(do_while) => {
-                self.check_expression(
-                    file,
-                    do_while.condition,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                self.check_statement(
-                    file,
-                    do_while.statement,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-            }
-            Statement::Switch(switch) => {
-                self.check_expression(
-                    file,
-                    switch.expression,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                let cases = match &switch.body {
-                    SwitchBody::BraceDelimited(body) => &body.cases,
-                    SwitchBody::ColonDelimited(body) => &body.cases,
-                };
-                for case in cases.iter() {
-                    match case {
-                        SwitchCase::Expression(c) => {
-                            self.check_expression(
-                                file,
-                                c.expression,
-                                method_map,
-                                current_class,
-                                registry,
-                                var_types,
-                                violations,
-                            );
-                            for s in c.statements.iter() {
-                                self.check_statement(
-                                    file,
-                                    s,
-                                    method_map,
-                                    current_class,
-                                    registry,
-                                    var_types,
-                                    violations,
-                                );
-                            }
-                        }
-                        SwitchCase::Default(c) => {
-                            for s in c.statements.iter() {
-                                self.check_statement(
-                                    file,
-                                    s,
-                                    method_map,
-                                    current_class,
-                                    registry,
-                                    var_types,
-                                    violations,
-                                );
-                            }
-                        }
-                    }
-                }
-            }
-            Statement::Foreach(foreach) => {
-                self.check_expression(
-                    file,
-                    foreach.expression,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                match &foreach.body {
-                    ForeachBody::Statement(body) => {
-                        self.check_statement(
-                            file,
-                            body,
-                            method_map,
-                            current_class,
-                            registry,
-                            var_types,
-                            violations,
-                        );
-                    }
-                    ForeachBody::ColonDelimited(body) => {
-                        for s in body.statements.iter() {
-                            self.check_statement(
-                                file,
-                                s,
-                                method_map,
-                                current_class,
-                                registry,
-                                var_types,
-                                violations,
-                            );
-                        }
-                    }
-                }
-            }
-            Statement::Block(block) => {
-                for s in block.statements.iter() {
-                    self.check_statement(
-                        file,
-                        s,
-                        method_map,
-                        current_class,
-                        registry,
-                        var_types,
-                        violations,
-                    );
-                }
-            }
-            _ => {}
-        }
-    }
-
-    // -------------------------------------------------------------------------
-    // Phase 5: Expression checker — core LoD detection logic
-    //
-    // Returns Some(type_name) if this expression has a known/resolved type,
-    // None if the type is unknown (which triggers violation on any chaining).
-    // -------------------------------------------------------------------------
-
-    #[allow(clippy::too_many_arguments)]
-    fn check_expression(
-        &self,
-        file: &File<'_>,
-        expression: &Expression<'_>,
-        method_map: &HashMap<String, String>,
-        current_class: &str,
-        registry: &TypeRegistry,
-        var_types: &VarTypes,
-        violations: &mut Vec<Violation>,
-    ) -> Option<String> {
-        match expression {
-            // ------------------------------------------------------------------
-            // Binary expressions: check both sides
-            // ------------------------------------------------------------------
-            Expression::Binary(binary) => {
-                self.check_expression(
-                    file,
-                    binary.lhs,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                self.check_expression(
-                    file,
-                    binary.rhs,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                None
-            }
-
-            // ------------------------------------------------------------------
-            // Variable: resolve $this → "self", other vars via var_types
-            // ------------------------------------------------------------------
-            Expression::Variable(v) => {
-                if let Variable::Direct(d) = v {
-                    if d.name == b"$this" {
-                        return Some("self".to_string());
-                    }
-                    let name = std::str::from_utf8(d.name).unwrap_or_default();
-                    return var_types.get(name).cloned();
-                }
-                None
-            }
-
-            Expression::Instantiation(new_expr) => {
-                let class_name = self.identifier_type_name(new_expr.class);
-                if let Some(arg_list) = &new_expr.argument_list {
-                    for arg in arg_list.arguments.iter() {
-                        self.check_argument_expr(
-                            file,
-                            arg,
-                            method_map,
-                            current_class,
-                            registry,
-                            var_types,
-                            violations,
-                        );
-                    }
-                }
-                class_name
-            }
-
-            // ------------------------------------------------------------------
-            // Call expressions: method, null-safe method, static method, function
-            // ------------------------------------------------------------------
-            Expression::Call(call) => self.check_call(
-                file,
-                call,
-                method_map,
-                current_class,
-                registry,
-                var_types,
-                violations,
-            ),
-
-            // ------------------------------------------------------------------
-            // Property / NullSafe property access: $obj->prop or $obj?->prop
-            // This is a LoD violation if $obj is not self/current_class,
-            // because you're reaching into a foreign object's internals.
-            // The result type is always unknown (we don't track property types).
-            // ------------------------------------------------------------------
-            Expression::Access(access) => self.check_access(
-                file,
-                access,
-                method_map,
-                current_class,
-                registry,
-                var_types,
-                violations,
-            ),
-
-            // ------------------------------------------------------------------
-            // Ternary / conditional: check all branches
-            // ------------------------------------------------------------------
-            Expression::Conditional(cond) => {
-                self.check_expression(
-                    file,
-                    cond.condition,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                if let Some(then_expr) = cond.then {
-                    self.check_expression(
-                        file,
-                        then_expr,
-                        method_map,
-                        current_class,
-                        registry,
-                        var_types,
-                        violations,
-                    );
-                }
-                self.check_expression(
-                    file,
-                    cond.r#else,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                None
-            }
-
-            // ------------------------------------------------------------------
-            // Parenthesised: unwrap and recurse
-            // ------------------------------------------------------------------
-            Expression::Parenthesized(p) => self.check_expression(
-                file,
-                p.expression,
-                method_map,
-                current_class,
-                registry,
-                var_types,
-                violations,
-            ),
-
-            // ------------------------------------------------------------------
-            // Unary prefix/postfix: recurse into operand
-            // ------------------------------------------------------------------
-            Expression::UnaryPrefix(u) => {
-                self.check_expression(
-                    file,
-                    u.operand,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                None
-            }
-            Expression::UnaryPostfix(u) => {
-                self.check_expression(
-                    file,
-                    u.operand,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                None
-            }
-
-            _ => None,
+        if let Err(message) = self.method_call_type(object, method_selector) {
+            let violation = self.rule.new_violation(self.file, message, span);
+            self.violations.push(violation);
         }
     }
 
-    // -------------------------------------------------------------------------
-    // Helper: check Call variants
-    // -------------------------------------------------------------------------
-
-    #[allow(clippy::too_many_arguments)]
-    fn check_call(
-        &self,
-        file: &File<'_>,
-        call: &Call<'_>,
-        method_map: &HashMap<String, String>,
-        current_class: &str,
-        registry: &TypeRegistry,
-        var_types: &VarTypes,
-        violations: &mut Vec<Violation>,
-    ) -> Option<String> {
-        match call {
-            Call::Method(mc) => self.check_method_call_generic(
-                file,
-                mc.object,
-                &mc.method,
-                &mc.argument_list,
-                mc.span(),
-                method_map,
-                current_class,
-                registry,
-                var_types,
-                violations,
-            ),
-            Call::NullSafeMethod(mc) => self.check_method_call_generic(
-                file,
-                mc.object,
-                &mc.method,
-                &mc.argument_list,
-                mc.span(),
-                method_map,
-                current_class,
-                registry,
-                var_types,
-                violations,
-            ),
-            Call::StaticMethod(mc) => {
-                // $class::method() — the class expression may be a class name or variable
-                let class_type = self.check_expression(
-                    file,
-                    mc.class,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                // Check arguments
-                for arg in mc.argument_list.arguments.iter() {
-                    self.check_argument_expr(
-                        file,
-                        arg,
-                        method_map,
-                        current_class,
-                        registry,
-                        var_types,
-                        violations,
-                    );
-                }
-                let method_name = self.member_selector_name(&mc.method);
-                self.resolve_method_return(
-                    class_type.as_deref(),
-                    &method_name,
-                    current_class,
-                    method_map,
-                    registry,
-                )
-            }
-            Call::Function(fc) => {
-                for arg in fc.argument_list.arguments.iter() {
-                    self.check_argument_expr(
-                        file,
-                        arg,
-                        method_map,
-                        current_class,
-                        registry,
-                        var_types,
-                        violations,
-                    );
-                }
-                None
-            }
-        }
-    }
-
-    /// Core logic for both `->method()` and `?->method()`.
-    #[allow(clippy::too_many_arguments)]
-    fn check_method_call_generic(
-        &self,
-        file: &File<'_>,
+    /// Type returned by the method call, or the violation when it is a
+    /// chained call on a foreign object.
+    fn method_call_type(
+        &mut self,
         object: &Expression<'_>,
         method_selector: &ClassLikeMemberSelector<'_>,
-        argument_list: &ArgumentList<'_>,
-        span: Span,
-        method_map: &HashMap<String, String>,
-        current_class: &str,
-        registry: &TypeRegistry,
-        var_types: &VarTypes,
-        violations: &mut Vec<Violation>,
-    ) -> Option<String> {
-        // Resolve object type
-        let object_type = self.resolve_object_type(
-            file,
-            object,
-            method_map,
-            current_class,
-            registry,
-            var_types,
-            violations,
-        );
-
-        // Check arguments regardless of violation status
-        for arg in argument_list.arguments.iter() {
-            self.check_argument_expr(
-                file,
-                arg,
-                method_map,
-                current_class,
-                registry,
-                var_types,
-                violations,
-            );
-        }
-
+    ) -> Result<Option<String>, String> {
+        let object_type = self.object_type(object);
         let method_name = self.member_selector_name(method_selector);
 
         // Determine if this is a method chain (`$a->b()->c()`) or a valid first call (`$a->b()`, `$this->foo->b()`)
@@ -1056,389 +356,171 @@ impl Rule {
         if !is_chain {
             // First method call on a base object (property, variable, new, etc.) is allowed.
             // We just resolve its return type for any subsequent chains.
-            return self.resolve_method_return(
-                object_type.as_deref(),
-                &method_name,
-                current_class,
-                method_map,
-                registry,
-            );
+            return Ok(self.resolve_method_return(object_type.as_deref(), &method_name));
         }
 
         // It is a chain. We allow chaining if it results in the current class,
         // or an interface, or if it's a fluent call on a foreign object.
         let is_fluent_on_foreign = match object {
-            Expression::Call(call) => {
-                let prev_object = match call {
-                    Call::Method(mc) => Some(mc.object),
-                    Call::NullSafeMethod(mc) => Some(mc.object),
-                    _ => None,
-                };
-                if let Some(prev_obj) = prev_object {
-                    let prev_type = self.resolve_object_type(
-                        file,
-                        prev_obj,
-                        method_map,
-                        current_class,
-                        registry,
-                        var_types,
-                        &mut Vec::new(),
-                    );
-                    prev_type == object_type
-                } else {
-                    false
-                }
+            Expression::Call(Call::Method(mc)) => self.object_type(mc.object) == object_type,
+            Expression::Call(Call::NullSafeMethod(mc)) => {
+                self.object_type(mc.object) == object_type
             }
             _ => false,
         };
 
         match &object_type {
-            Some(t) if self.is_own_type(t, current_class) || is_fluent_on_foreign => self
-                .resolve_method_return(Some(t), &method_name, current_class, method_map, registry),
-            Some(t) if registry.interfaces.contains(t) => self.resolve_method_return(
-                Some(t),
-                &method_name,
-                current_class,
-                method_map,
-                registry,
-            ),
-            Some(t) => {
-                // Foreign type — chaining is a violation
-                let message = format!(
-                    "Law of Demeter violation. Method '{}' is called on '{}', which is a foreign object.",
-                    method_name, t
-                );
-                violations.push(self.new_violation(file, message, span));
-                None
-            }
-            None => {
-                // Unknown type — conservative: violation
-                let message = format!(
-                    "Law of Demeter violation. Method '{}' is called on an object of unknown type (possible foreign object).",
-                    method_name
-                );
-                violations.push(self.new_violation(file, message, span));
-                None
-            }
+            Some(t)
+                if self.is_own_type(t) || is_fluent_on_foreign || self.registry.is_interface(t) =>
+            {
+                Ok(self.resolve_method_return(Some(t), &method_name))
+            }
+            // Foreign type — chaining is a violation
+            Some(t) => Err(format!(
+                "Law of Demeter violation. Method '{}' is called on '{}', which is a foreign object.",
+                method_name, t
+            )),
+            // Unknown type — conservative: violation
+            None => Err(format!(
+                "Law of Demeter violation. Method '{}' is called on an object of unknown type (possible foreign object).",
+                method_name
+            )),
         }
     }
 
     // -------------------------------------------------------------------------
-    // Helper: resolve the type of the receiver object
+    // Types: `None` when unknown, which makes any chaining on it a violation
     // -------------------------------------------------------------------------
 
-
-    /// Caching wrapper around [`Self::resolve_object_type_uncached`].
-    ///
-    /// The first time a given expression span is resolved it runs the real
-    /// logic (which records any violations into `violations`); later lookups of
-    /// the same span return the cached type without re-descending or re-pushing.
-    /// The fluency check below re-resolves chain links it has already walked, so
-    /// without this an N-link chain costs `O(phi^N)`.
-    #[allow(clippy::too_many_arguments)]
-    fn resolve_object_type(
-        &self,
-        file: &File<'_>,
-        object: &Expression<'_>,
-        method_map: &HashMap<String, String>,
-        current_class: &str,
-        registry: &TypeRegistry,
-        var_types: &VarTypes,
-        violations: &mut Vec<Violation>,
-    ) -> Option<String> {
-        let key = object.span();
-        if let Ok(cache) = self.resolve_cache.lock() {
-            if let Some(cached) = cache.get(&key) {
-                return cached.clone();
-            }
-        }
-
-        let result = self.resolve_object_type_uncached(
-            file,
-            object,
-            method_map,
-            current_class,
-            registry,
-            var_types,
-            violations,
-        );
-
-        if let Ok(mut cache) = self.resolve_cache.lock() {
-            cache.insert(key, result.clone());
+    fn expression_type(&mut self, expression: &Expression<'_>) -> Option<String> {
+        match expression {
+            Expression::Variable(v) => self.variable_type(v),
+            Expression::Instantiation(new_expr) => self.identifier_type_name(new_expr.class),
+            Expression::Call(call) => self.call_type(call),
+            Expression::Parenthesized(p) => self.expression_type(p.expression),
+            _ => None,
         }
-
-        result
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn resolve_object_type_uncached(
-        &self,
-        file: &File<'_>,
-        object: &Expression<'_>,
-        method_map: &HashMap<String, String>,
-        current_class: &str,
-        registry: &TypeRegistry,
-        var_types: &VarTypes,
-        violations: &mut Vec<Violation>,
-    ) -> Option<String> {
-        match object {
-            // $this → "self"
-            Expression::Variable(Variable::Direct(d)) => {
-                if d.name == b"$this" {
-                    Some("self".to_string())
-                } else {
-                    let name = std::str::from_utf8(d.name).unwrap_or_default();
-                    var_types.get(name).cloned()
-                }
-            }
-
-            // new Foo() → "Foo"
-            Expression::Instantiation(new_expr) => {
-                if let Some(arg_list) = &new_expr.argument_list {
-                    for arg in arg_list.arguments.iter() {
-                        self.check_argument_expr(
-                            file,
-                            arg,
-                            method_map,
-                            current_class,
-                            registry,
-                            var_types,
-                            violations,
-                        );
-                    }
-                }
-                self.identifier_type_name(new_expr.class)
+    fn call_type(&mut self, call: &Call<'_>) -> Option<String> {
+        match call {
+            Call::Method(mc) => self.method_call_type(mc.object, &mc.method).ok()?,
+            Call::NullSafeMethod(mc) => self.method_call_type(mc.object, &mc.method).ok()?,
+            Call::StaticMethod(mc) => {
+                // $class::method() — the class expression may be a class name or variable
+                let class_type = self.expression_type(mc.class);
+                let method_name = self.member_selector_name(&mc.method);
+                self.resolve_method_return(class_type.as_deref(), &method_name)
             }
+            Call::Function(_) => None,
+        }
+    }
 
-            // Another method call (chained) → recurse via check_expression
-            Expression::Call(call) => self.check_call(
-                file,
-                call,
-                method_map,
-                current_class,
-                registry,
-                var_types,
-                violations,
-            ),
-
-            // Property access like $this->foo → the type of $this->foo is unknown
-            // (we don't track property types), so any chaining on it is a violation.
-            // But simply accessing $this->foo is NOT itself a violation.
-            Expression::Access(access) => match access {
-                Access::Property(pa) => {
-                    // $obj->prop: first validate the object itself
-                    let obj_type = self.resolve_object_type(
-                        file,
-                        pa.object,
-                        method_map,
-                        current_class,
-                        registry,
-                        var_types,
-                        violations,
-                    );
-                    if let Some(t) = &obj_type {
-                        if self.is_own_type(t, current_class) {
-                            let lookup_type = if t == "self" || t == "static" {
-                                current_class
-                            } else {
-                                t
-                            };
-                            if let Some(mut prop_type) =
-                                registry.properties.get(lookup_type).and_then(|props| {
-                                    let prop_name = self.member_selector_name(&pa.property);
-                                    props.get(prop_name.trim_start_matches('$')).cloned()
-                                })
-                            {
-                                if prop_type == current_class {
-                                    prop_type = "self".to_string();
-                                }
-                                return Some(prop_type);
-                            }
-                            return None;
-                        }
-                    }
-                    None
-                }
-                Access::NullSafeProperty(pa) => {
-                    let obj_type = self.resolve_object_type(
-                        file,
-                        pa.object,
-                        method_map,
-                        current_class,
-                        registry,
-                        var_types,
-                        violations,
-                    );
-                    if let Some(t) = &obj_type {
-                        if self.is_own_type(t, current_class) {
-                            let lookup_type = if t == "self" || t == "static" {
-                                current_class
-                            } else {
-                                t
-                            };
-                            if let Some(mut prop_type) =
-                                registry.properties.get(lookup_type).and_then(|props| {
-                                    let prop_name = self.member_selector_name(&pa.property);
-                                    props.get(prop_name.trim_start_matches('$')).cloned()
-                                })
-                            {
-                                if prop_type == current_class {
-                                    prop_type = "self".to_string();
-                                }
-                                return Some(prop_type);
-                            }
-                            return None;
-                        }
-                    }
-                    None
-                }
-                _ => self.check_expression(
-                    file,
-                    object,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                ),
-            },
-
-            Expression::Parenthesized(p) => self.resolve_object_type(
-                file,
-                p.expression,
-                method_map,
-                current_class,
-                registry,
-                var_types,
-                violations,
-            ),
+    /// Type of the receiver of a method call, memoized; see `types`.
+    fn object_type(&mut self, object: &Expression<'_>) -> Option<String> {
+        let key = object.span();
+        if let Some(cached) = self.types.get(&key) {
+            return cached.clone();
+        }
 
+        let result = match object {
+            Expression::Variable(v) => self.variable_type(v),
+            Expression::Instantiation(new_expr) => self.identifier_type_name(new_expr.class),
+            Expression::Call(call) => self.call_type(call),
+            // Property access like $this->foo is typed only for the own
+            // properties. Simply accessing it is NOT itself a violation.
+            Expression::Access(Access::Property(pa)) => self.property_type(pa.object, &pa.property),
+            Expression::Access(Access::NullSafeProperty(pa)) => {
+                self.property_type(pa.object, &pa.property)
+            }
+            Expression::Parenthesized(p) => self.object_type(p.expression),
             Expression::Identifier(id) => {
                 let name = String::from_utf8_lossy(id.value()).into_owned();
                 match name.as_str() {
                     "self" | "static" => Some("self".to_string()),
-                    _ => Some(name),
+                    _ => Some(self.registry.resolver.resolve_class(&name)),
                 }
             }
-
             _ => None,
+        };
+
+        self.types.insert(key, result.clone());
+
+        result
+    }
+
+    /// `$this` → "self", other vars via the tracked types.
+    fn variable_type(&self, variable: &Variable<'_>) -> Option<String> {
+        if let Variable::Direct(d) = variable {
+            if d.name == b"$this" {
+                return Some("self".to_string());
+            }
+            let name = std::str::from_utf8(d.name).unwrap_or_default();
+            return self.var_types.last()?.get(name).cloned();
         }
+        None
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn check_access(
-        &self,
-        file: &File<'_>,
-        access: &Access<'_>,
-        method_map: &HashMap<String, String>,
-        current_class: &str,
-        registry: &TypeRegistry,
-        var_types: &VarTypes,
-        violations: &mut Vec<Violation>,
+    fn property_type(
+        &mut self,
+        object: &Expression<'_>,
+        property: &ClassLikeMemberSelector<'_>,
     ) -> Option<String> {
-        match access {
-            Access::Property(pa) => {
-                self.check_expression(
-                    file,
-                    pa.object,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                None
-            }
-            Access::NullSafeProperty(pa) => {
-                self.check_expression(
-                    file,
-                    pa.object,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                None
-            }
-            Access::StaticProperty(pa) => {
-                self.check_expression(
-                    file,
-                    pa.class,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                None
-            }
-            Access::ClassConstant(pa) => {
-                self.check_expression(
-                    file,
-                    pa.class,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-                None
-            }
+        let obj_type = self.object_type(object)?;
+        if !self.is_own_type(&obj_type) {
+            return None;
+        }
+
+        let current_class = self.current_class();
+        let lookup_type = if obj_type == "self" || obj_type == "static" {
+            current_class
+        } else {
+            &obj_type
+        };
+        let prop_name = self.member_selector_name(property);
+        let prop_type = self
+            .registry
+            .property_type(lookup_type, prop_name.trim_start_matches('$'))?;
+        if prop_type == current_class {
+            return Some("self".to_string());
         }
+        Some(prop_type)
     }
 
     fn resolve_method_return(
         &self,
         object_type: Option<&str>,
         method_name: &str,
-        current_class: &str,
-        method_map: &HashMap<String, String>,
-        registry: &TypeRegistry,
     ) -> Option<String> {
-        if let Some(mut t) = object_type {
-            if t == "self" || t == "static" {
-                t = current_class;
-            }
-            if self.is_own_type(t, current_class) {
-                if let Some(ret) = method_map.get(method_name) {
-                    return Some(ret.clone());
-                }
-                if let Some(type_map) = registry.methods.get(t) {
-                    if let Some(ret) = type_map.get(method_name) {
-                        return Some(ret.clone());
-                    }
-                }
-            } else if let Some(type_map) = registry.methods.get(t) {
-                if let Some(ret) = type_map.get(method_name) {
-                    return Some(ret.clone());
-                }
-            }
+        let current_class = self.current_class();
+        let mut t = object_type?;
+        if t == "self" || t == "static" {
+            t = current_class;
         }
-        None
-    }
-
-    fn track_parameters(
-        &self,
-        parameters: &FunctionLikeParameterList<'_>,
-        var_types: &mut VarTypes,
-    ) {
-        for param in parameters.parameters.iter() {
-            if let Some(hint) = &param.hint {
-                if let Some(t) = self.extract_type_hint(hint) {
-                    var_types.insert(String::from_utf8_lossy(param.variable.name).into_owned(), t);
-                }
+        if self.is_own_type(t) {
+            let method_returns = self
+                .class_likes
+                .last()
+                .map(|class_like| &class_like.method_returns);
+            if let Some(ret) =
+                method_returns.and_then(|method_returns| method_returns.get(method_name))
+            {
+                return Some(ret.clone());
             }
         }
+        self.registry.method_return(t, method_name)
     }
 
-    fn is_own_type(&self, t: &str, current_class: &str) -> bool {
+    fn is_own_type(&self, t: &str) -> bool {
+        let current_class = self.current_class();
         t == "self" || t == "static" || (!current_class.is_empty() && t == current_class)
     }
 
     fn member_selector_name(&self, selector: &ClassLikeMemberSelector<'_>) -> String {
         match selector {
-            ClassLikeMemberSelector::Identifier(local_id) => String::from_utf8_lossy(local_id.value).into_owned(),
+            ClassLikeMemberSelector::Identifier(local_id) => {
+                String::from_utf8_lossy(local_id.value).into_owned()
+            }
             ClassLikeMemberSelector::Variable(v) => {
                 if let Variable::Direct(d) = v {
                     String::from_utf8_lossy(d.name).into_owned()
@@ -1454,52 +536,23 @@ impl Rule {
 
     fn identifier_type_name(&self, expr: &Expression<'_>) -> Option<String> {
         if let Expression::Identifier(id) = expr {
-            Some(String::from_utf8_lossy(id.value()).into_owned())
+            Some(
+                self.registry
+                    .resolver
+                    .resolve_class(&String::from_utf8_lossy(id.value())),
+            )
         } else {
             None
         }
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn check_argument_expr(
-        &self,
-        file: &File<'_>,
-        arg: &Argument<'_>,
-        method_map: &HashMap<String, String>,
-        current_class: &str,
-        registry: &TypeRegistry,
-        var_types: &VarTypes,
-        violations: &mut Vec<Violation>,
-    ) {
-        match arg {
-            Argument::Positional(a) => {
-                self.check_expression(
-                    file,
-                    a.value,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-            }
-            Argument::Named(a) => {
-                self.check_expression(
-                    file,
-                    a.value,
-                    method_map,
-                    current_class,
-                    registry,
-                    var_types,
-                    violations,
-                );
-            }
-        }
-    }
-
     fn extract_type_hint(&self, hint: &Hint<'_>) -> Option<String> {
         match hint {
-            Hint::Identifier(id) => Some(String::from_utf8_lossy(id.value()).into_owned()),
+            Hint::Identifier(id) => Some(
+                self.registry
+                    .resolver
+                    .resolve_class(&String::from_utf8_lossy(id.value())),
+            ),
             Hint::Self_(_) => Some("self".to_string()),
             Hint::Static(_) => Some("static".to_string()),
             Hint::Parent(_) => Some("parent".to_string()),
@@ -1515,6 +568,7 @@ mod tests {
 
     use super::*;
     use crate::rules::tests::analyze_file_for_rule;
+    use crate::rules::visitor::Traversal;
 
     #[test]
     fn valid_fluent_interface() {
@@ -1544,6 +598,15 @@ mod tests {
         );
     }
 
+    #[test]
+    fn invalid_nested_chaining() {
+        let violations = analyze_file_for_rule("e14/invalid_nested.php", CODE);
+        assert_eq!(
+            violations.iter().map(|v| v.start_line).collect::<Vec<_>>(),
+            vec![15, 20]
+        );
+    }
+
     #[test]
     fn valid_trait_fluent_interface() {
         let violations = analyze_file_for_rule("e14/valid_trait_fluent.php", CODE);
@@ -1596,8 +659,8 @@ mod tests {
     ///
     /// A self-returning setter chained many times is valid (every link resolves
     /// to the current class), so it must report no violations. Before the fix
-    /// this hung: `check_method_call_generic` re-resolved the receiver type for
-    /// every link (via `is_fluent_on_foreign`), giving `O(phi^N)` cost in chain
+    /// this hung: the fluency check re-resolved the receiver type for every
+    /// link (via `is_fluent_on_foreign`), giving `O(phi^N)` cost in chain
     /// length. The fixture has 80 chained calls, which never terminated before.
     #[test]
     fn long_fluent_chain_terminates() {
@@ -1622,16 +685,16 @@ mod tests {
         let path2 = std::path::PathBuf::from("./src/rules/examples/e14/usage.php");
         let content2 = "<?php class App { public function run(DB $db) { $db->query()->query(); } }"
             .to_string();
-        let file2 = File::new(&arena, path2, content2);
+        let mut file2 = File::new(&arena, path2, content2);
 
-        crate::rules::Rule::index_file(&rule, &file1);
-        crate::rules::Rule::index_file(&rule, &file2);
+        let mut symbols = SymbolTable::default();
+        symbols.merge(FileSymbols::collect(file1.ast.unwrap()));
+        symbols.merge(FileSymbols::collect(file2.ast.unwrap()));
+        file2.symbols = Arc::new(symbols);
 
-        let violations = crate::rules::Rule::validate(
-            &rule,
-            &file2,
-            file2.ast.unwrap().statements.first().unwrap(),
-        );
+        let mut visitors = vec![rule.visitor(&file2).unwrap()];
+        Traversal::new(&mut visitors, false).walk(file2.ast.unwrap());
+        let violations = visitors.pop().unwrap().finish();
         assert!(
             violations.is_empty(),
             "Expected no violations for cross-file injection, got: {:?}",
@@ -7,6 +7,7 @@ use serde_json::Value;
 
 use crate::file::File;
 use crate::results::Violation;
+use crate::rules::visitor::{Scope, Visitor};
 use crate::rules::Rule as RuleTrait;
 
 pub(crate) static CODE: &str = "E0015";
@@ -65,37 +66,6 @@ impl Dsu {
     }
 }
 
-/// Represents a "node" in the cohesion graph.
-/// Could be a method or a property hook.
-enum MethodNode<'a> {
-    Method(&'a Method<'a>),
-    Hook(&'a PropertyHook<'a>, String), // hook, property_name
-}
-
-impl<'a> MethodNode<'a> {
-    fn name(&self) -> String {
-        match self {
-            MethodNode::Method(m) => String::from_utf8_lossy(m.name.value).into_owned(),
-            MethodNode::Hook(h, prop) => format!("{}::{}", prop, String::from_utf8_lossy(h.name.value)),
-        }
-    }
-
-    fn body_statements(&self) -> Option<&'a [Statement<'a>]> {
-        match self {
-            MethodNode::Method(m) => match &m.body {
-                MethodBody::Concrete(block) => Some(block.statements.iter().as_slice()),
-                _ => None,
-            },
-            MethodNode::Hook(h, _) => match &h.body {
-                PropertyHookBody::Concrete(PropertyHookConcreteBody::Block(block)) => {
-                    Some(block.statements.iter().as_slice())
-                }
-                _ => None,
-            },
-        }
-    }
-}
-
 impl RuleTrait for Rule {
     fn get_code(&self) -> String {
         String::from(CODE)
@@ -112,329 +82,231 @@ impl RuleTrait for Rule {
         }
     }
 
-    fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
-        let mut violations = Vec::new();
-
-        if let Statement::Class(class) = statement {
-            let mut nodes = Vec::new();
-            let mut property_names = HashSet::new();
-
-            for member in class.members.iter() {
-                match member {
-                    ClassLikeMember::Method(method) => {
-                        let name = String::from_utf8_lossy(method.name.value).into_owned();
-                        if !name.starts_with("__") {
-                            nodes.push(MethodNode::Method(method));
-                        }
-                    }
-                    ClassLikeMember::Property(prop) => {
-                        let is_static = prop
-                            .modifiers()
-                            .iter()
-                            .any(|m| matches!(m, Modifier::Static(_)));
-                        if !is_static {
-                            let vars = prop.variables();
-                            for var in vars {
-                                let prop_name = String::from_utf8_lossy(var.name).into_owned();
-                                property_names.insert(prop_name.clone());
-                            }
-
-                            if let Property::Hooked(h) = prop {
-                                let prop_name = String::from_utf8_lossy(h.item.variable().name).into_owned();
-                                for hook in h.hook_list.hooks.iter() {
-                                    nodes.push(MethodNode::Hook(hook, prop_name.clone()));
-                                }
-                            }
-                        }
-                    }
-                    _ => {}
-                }
-            }
+    fn visitor<'a>(&'a self, file: &'a File<'a>) -> Option<Box<dyn Visitor<'a> + 'a>> {
+        Some(Box::new(Cohesion {
+            rule: self,
+            file,
+            classes: vec![],
+            violations: vec![],
+        }))
+    }
+}
 
-            if nodes.is_empty() {
-                return violations;
-            }
+impl Rule {
+    fn validate_class(
+        &self,
+        file: &File<'_>,
+        class: &Class<'_>,
+        graph: ClassGraph,
+    ) -> Option<Violation> {
+        if graph.nodes.is_empty() {
+            return None;
+        }
 
-            let n = nodes.len();
-            let mut dsu = Dsu::new(n);
-            let mut property_to_methods: HashMap<String, Vec<usize>> = HashMap::new();
-            let mut method_to_index: HashMap<String, usize> = HashMap::new();
+        let mut dsu = Dsu::new(graph.nodes.len());
+        let mut property_to_methods: HashMap<&String, Vec<usize>> = HashMap::new();
 
-            for (i, node) in nodes.iter().enumerate() {
-                if let MethodNode::Method(_) = node {
-                    method_to_index.insert(node.name(), i);
+        for (i, node) in graph.nodes.iter().enumerate() {
+            for called in &node.called_methods {
+                if let Some(&j) = graph.methods.get(called) {
+                    dsu.union(i, j);
                 }
             }
-
-            for (i, node) in nodes.iter().enumerate() {
-                let mut used_props = HashSet::new();
-                let mut called_methods = HashSet::new();
-
-                if let Some(stmts) = node.body_statements() {
-                    for stmt in stmts {
-                        for s in self.flatten_statements_to_validate(stmt) {
-                            self.scan_statement(
-                                s,
-                                &property_names,
-                                &mut used_props,
-                                &mut called_methods,
-                            );
-                        }
-                    }
-                } else if let MethodNode::Hook(h, _) = node {
-                    if let PropertyHookBody::Concrete(PropertyHookConcreteBody::Expression(expr)) =
-                        &h.body
-                    {
-                        self.scan_expression(
-                            expr.expression,
-                            &property_names,
-                            &mut used_props,
-                            &mut called_methods,
-                        );
-                    }
-                }
-
-                for prop in used_props {
+            for prop in &node.used_props {
+                if graph.property_names.contains(prop) {
                     property_to_methods.entry(prop).or_default().push(i);
                 }
-
-                for called in called_methods {
-                    if let Some(&j) = method_to_index.get(&called) {
-                        dsu.union(i, j);
-                    }
-                }
             }
+        }
 
-            for methods_using_prop in property_to_methods.values() {
-                if let Some(&first) = methods_using_prop.first() {
-                    for &next in methods_using_prop.iter().skip(1) {
-                        dsu.union(first, next);
-                    }
+        for methods_using_prop in property_to_methods.values() {
+            if let Some(&first) = methods_using_prop.first() {
+                for &next in methods_using_prop.iter().skip(1) {
+                    dsu.union(first, next);
                 }
             }
+        }
 
-            let lcom4 = dsu.count;
-            if lcom4 > self.settings.threshold {
-                let suggestion = format!(
-                    "Class \"{}\" has low cohesion (LCOM4 = {}). Consider splitting it into {} smaller classes.",
-                    String::from_utf8_lossy(class.name.value), lcom4, lcom4
-                );
-                violations.push(self.new_violation(file, suggestion, class.span()));
-            }
+        let lcom4 = dsu.count;
+        if lcom4 > self.settings.threshold {
+            let suggestion = format!(
+                "Class \"{}\" has low cohesion (LCOM4 = {}). Consider splitting it into {} smaller classes.",
+                String::from_utf8_lossy(class.name.value), lcom4, lcom4
+            );
+            return Some(self.new_violation(file, suggestion, class.span()));
         }
 
-        violations
+        None
     }
 }
 
-impl Rule {
-    fn is_this(&self, expr: &Expression<'_>) -> bool {
-        if let Expression::Variable(Variable::Direct(d)) = expr {
-            return d.name == b"$this";
-        }
-        false
+/// A method or a property hook of the cohesion graph, with the members of
+/// the class it uses.
+#[derive(Default)]
+struct Node {
+    used_props: HashSet<String>,
+    called_methods: HashSet<String>,
+}
+
+#[derive(Default)]
+struct ClassGraph {
+    nodes: Vec<Node>,
+    /// Node index by method name, or by `$property::hook` for hooks.
+    index: HashMap<String, usize>,
+    /// Node index of the methods, by name.
+    methods: HashMap<String, usize>,
+    property_names: HashSet<String>,
+}
+
+impl ClassGraph {
+    fn add_node(&mut self, name: String) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(Node::default());
+        self.index.insert(name, index);
+        index
     }
 
-    fn scan_statement(
-        &self,
-        stmt: &Statement<'_>,
-        property_names: &HashSet<String>,
-        used_props: &mut HashSet<String>,
-        called_methods: &mut HashSet<String>,
-    ) {
-        match stmt {
-            Statement::Expression(s) => {
-                self.scan_expression(s.expression, property_names, used_props, called_methods)
+    /// Node of the method or hook being visited, if it is part of the graph.
+    fn current_node(&mut self, scope: &Scope) -> Option<&mut Node> {
+        let (_, name) = scope.named_function_like()?;
+        let index = *self.index.get(name)?;
+        self.nodes.get_mut(index)
+    }
+}
+
+struct Cohesion<'a> {
+    rule: &'a Rule,
+    file: &'a File<'a>,
+    /// `None` for the class-likes other than classes, which have their own
+    /// scope, innermost last.
+    classes: Vec<Option<ClassGraph>>,
+    violations: Vec<Violation>,
+}
+
+impl<'a> Visitor<'a> for Cohesion<'a> {
+    fn enter_statement(&mut self, statement: &'a Statement<'a>, _scope: &Scope) {
+        match statement {
+            Statement::Class(_) => self.classes.push(Some(ClassGraph::default())),
+            Statement::Interface(_) | Statement::Trait(_) | Statement::Enum(_) => {
+                self.classes.push(None)
             }
-            Statement::Return(s) => {
-                if let Some(expr) = s.value {
-                    self.scan_expression(expr, property_names, used_props, called_methods);
+            _ => {}
+        }
+    }
+
+    fn leave_statement(&mut self, statement: &'a Statement<'a>, _scope: &Scope) {
+        match statement {
+            Statement::Class(class) => {
+                if let Some(Some(graph)) = self.classes.pop() {
+                    if let Some(violation) = self.rule.validate_class(self.file, class, graph) {
+                        self.violations.push(violation);
+                    }
                 }
             }
-            Statement::Echo(s) => {
-                for expr in s.values.iter() {
-                    self.scan_expression(expr, property_names, used_props, called_methods);
-                }
+            Statement::Interface(_) | Statement::Trait(_) | Statement::Enum(_) => {
+                self.classes.pop();
             }
             _ => {}
         }
     }
 
-    fn scan_expression(
-        &self,
-        expr: &Expression<'_>,
-        property_names: &HashSet<String>,
-        used_props: &mut HashSet<String>,
-        called_methods: &mut HashSet<String>,
-    ) {
-        match expr {
-            Expression::Call(call) => match call {
-                Call::Method(m) => {
-                    if self.is_this(m.object) {
-                        if let ClassLikeMemberSelector::Identifier(id) = &m.method {
-                            called_methods.insert(String::from_utf8_lossy(id.value).into_owned());
-                        }
-                    }
-                    self.scan_expression(m.object, property_names, used_props, called_methods);
-                    for arg in m.argument_list.arguments.iter() {
-                        self.scan_expression(
-                            arg.value(),
-                            property_names,
-                            used_props,
-                            called_methods,
-                        );
-                    }
-                }
-                Call::NullSafeMethod(m) => {
-                    if self.is_this(m.object) {
-                        if let ClassLikeMemberSelector::Identifier(id) = &m.method {
-                            called_methods.insert(String::from_utf8_lossy(id.value).into_owned());
-                        }
-                    }
-                    self.scan_expression(m.object, property_names, used_props, called_methods);
-                    for arg in m.argument_list.arguments.iter() {
-                        self.scan_expression(
-                            arg.value(),
-                            property_names,
-                            used_props,
-                            called_methods,
-                        );
-                    }
-                }
-                Call::Function(f) => {
-                    self.scan_expression(f.function, property_names, used_props, called_methods);
-                    for arg in f.argument_list.arguments.iter() {
-                        self.scan_expression(
-                            arg.value(),
-                            property_names,
-                            used_props,
-                            called_methods,
-                        );
-                    }
+    fn enter_member(&mut self, member: &'a ClassLikeMember<'a>, _scope: &Scope) {
+        let graph = match self.classes.last_mut() {
+            Some(Some(graph)) => graph,
+            _ => return,
+        };
+
+        match member {
+            ClassLikeMember::Method(method) => {
+                let name = String::from_utf8_lossy(method.name.value).into_owned();
+                if !name.starts_with("__") {
+                    let index = graph.add_node(name.clone());
+                    graph.methods.insert(name, index);
                 }
-                Call::StaticMethod(m) => {
-                    self.scan_expression(m.class, property_names, used_props, called_methods);
-                    for arg in m.argument_list.arguments.iter() {
-                        self.scan_expression(
-                            arg.value(),
-                            property_names,
-                            used_props,
-                            called_methods,
-                        );
-                    }
-                }
-            },
-            Expression::Access(access) => match access {
-                Access::Property(p) => {
-                    if self.is_this(p.object) {
-                        if let ClassLikeMemberSelector::Identifier(id) = &p.property {
-                            let name = format!("${}", String::from_utf8_lossy(id.value));
-                            if property_names.contains(&name) {
-                                used_props.insert(name);
-                            }
-                        }
+            }
+            ClassLikeMember::Property(prop) => {
+                let is_static = prop
+                    .modifiers()
+                    .iter()
+                    .any(|m| matches!(m, Modifier::Static(_)));
+                if !is_static {
+                    for var in prop.variables() {
+                        let prop_name = String::from_utf8_lossy(var.name).into_owned();
+                        graph.property_names.insert(prop_name);
                     }
-                    self.scan_expression(p.object, property_names, used_props, called_methods);
-                }
-                Access::NullSafeProperty(p) => {
-                    if self.is_this(p.object) {
-                        if let ClassLikeMemberSelector::Identifier(id) = &p.property {
-                            let name = format!("${}", String::from_utf8_lossy(id.value));
-                            if property_names.contains(&name) {
-                                used_props.insert(name);
-                            }
+
+                    if let Property::Hooked(h) = prop {
+                        let prop_name = String::from_utf8_lossy(h.item.variable().name);
+                        for hook in h.hook_list.hooks.iter() {
+                            let hook_name = String::from_utf8_lossy(hook.name.value);
+                            graph.add_node(format!("{}::{}", prop_name, hook_name));
                         }
                     }
-                    self.scan_expression(p.object, property_names, used_props, called_methods);
                 }
-                Access::ClassConstant(c) => {
-                    self.scan_expression(c.class, property_names, used_props, called_methods);
-                }
-                Access::StaticProperty(p) => {
-                    self.scan_expression(p.class, property_names, used_props, called_methods);
-                }
-            },
-            Expression::Binary(bin) => {
-                self.scan_expression(bin.lhs, property_names, used_props, called_methods);
-                self.scan_expression(bin.rhs, property_names, used_props, called_methods);
             }
-            Expression::UnaryPrefix(un) => {
-                self.scan_expression(un.operand, property_names, used_props, called_methods);
-            }
-            Expression::UnaryPostfix(un) => {
-                self.scan_expression(un.operand, property_names, used_props, called_methods);
-            }
-            Expression::Assignment(ass) => {
-                self.scan_expression(ass.lhs, property_names, used_props, called_methods);
-                self.scan_expression(ass.rhs, property_names, used_props, called_methods);
-            }
-            Expression::Parenthesized(p) => {
-                self.scan_expression(p.expression, property_names, used_props, called_methods);
-            }
-            Expression::Array(a) => {
-                for element in a.elements.iter() {
-                    if let Some(val) = element.get_value() {
-                        self.scan_expression(val, property_names, used_props, called_methods);
-                    }
-                    if let Some(key) = element.get_key() {
-                        self.scan_expression(key, property_names, used_props, called_methods);
-                    }
+            _ => {}
+        }
+    }
+
+    fn enter_expression(&mut self, expression: &'a Expression<'a>, scope: &Scope) {
+        if let Expression::AnonymousClass(_) = expression {
+            // Anonymous classes have their own scope
+            self.classes.push(None);
+            return;
+        }
+        let node = match self.classes.last_mut() {
+            Some(Some(graph)) => match graph.current_node(scope) {
+                Some(node) => node,
+                None => return,
+            },
+            _ => return,
+        };
+
+        match expression {
+            Expression::Call(Call::Method(m)) if is_this(m.object) => {
+                if let ClassLikeMemberSelector::Identifier(id) = &m.method {
+                    node.called_methods
+                        .insert(String::from_utf8_lossy(id.value).into_owned());
                 }
             }
-            Expression::Closure(c) => {
-                for stmt in c.body.statements.iter() {
-                    for s in self.flatten_statements_to_validate(stmt) {
-                        self.scan_statement(s, property_names, used_props, called_methods);
-                    }
+            Expression::Call(Call::NullSafeMethod(m)) if is_this(m.object) => {
+                if let ClassLikeMemberSelector::Identifier(id) = &m.method {
+                    node.called_methods
+                        .insert(String::from_utf8_lossy(id.value).into_owned());
                 }
             }
-            Expression::ArrowFunction(c) => {
-                self.scan_expression(c.expression, property_names, used_props, called_methods);
-            }
-            Expression::Match(m) => {
-                self.scan_expression(m.expression, property_names, used_props, called_methods);
-                for arm in m.arms.iter() {
-                    match arm {
-                        MatchArm::Expression(a) => {
-                            for cond in a.conditions.iter() {
-                                self.scan_expression(
-                                    cond,
-                                    property_names,
-                                    used_props,
-                                    called_methods,
-                                );
-                            }
-                            self.scan_expression(
-                                a.expression,
-                                property_names,
-                                used_props,
-                                called_methods,
-                            );
-                        }
-                        MatchArm::Default(a) => {
-                            self.scan_expression(
-                                a.expression,
-                                property_names,
-                                used_props,
-                                called_methods,
-                            );
-                        }
-                    }
+            Expression::Access(Access::Property(p)) if is_this(p.object) => {
+                if let ClassLikeMemberSelector::Identifier(id) = &p.property {
+                    node.used_props
+                        .insert(format!("${}", String::from_utf8_lossy(id.value)));
                 }
             }
-            Expression::AnonymousClass(_) => {
-                // Stop! Do not traverse into anonymous classes as they have their own scope
-            }
-            Expression::Conditional(t) => {
-                self.scan_expression(t.condition, property_names, used_props, called_methods);
-                if let Some(then_expr) = t.then {
-                    self.scan_expression(then_expr, property_names, used_props, called_methods);
+            Expression::Access(Access::NullSafeProperty(p)) if is_this(p.object) => {
+                if let ClassLikeMemberSelector::Identifier(id) = &p.property {
+                    node.used_props
+                        .insert(format!("${}", String::from_utf8_lossy(id.value)));
                 }
-                self.scan_expression(t.r#else, property_names, used_props, called_methods);
             }
             _ => {}
         }
     }
+
+    fn leave_expression(&mut self, expression: &'a Expression<'a>, _scope: &Scope) {
+        if let Expression::AnonymousClass(_) = expression {
+            self.classes.pop();
+        }
+    }
+
+    fn finish(self: Box<Self>) -> Vec<Violation> {
+        self.violations
+    }
+}
+
+fn is_this(expr: &Expression<'_>) -> bool {
+    if let Expression::Variable(Variable::Direct(d)) = expr {
+        return d.name == b"$this";
+    }
+    false
 }
 
 #[cfg(test)]
@@ -1,10 +1,12 @@
-use mago_span::HasSpan;
+use std::collections::BTreeMap;
+
 use mago_syntax::ast::*;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::file::File;
-use crate::results::Violation;
+use crate::results::{Severity, Violation};
+use crate::rules::callable::{callables, threshold, CallableKind};
 
 pub(crate) static CODE: &str = "E0016";
 static DESCRIPTION: &str = "Cognitive complexity";
@@ -12,11 +14,16 @@ static DESCRIPTION: &str = "Cognitive complexity";
 #[derive(Deserialize, Serialize)]
 pub struct Settings {
     pub max_complexity: i64,
+    #[serde(default)]
+    pub max_complexity_by_kind: BTreeMap<CallableKind, i64>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
-        Settings { max_complexity: 15 }
+        Settings {
+            max_complexity: 15,
+            max_complexity_by_kind: BTreeMap::new(),
+        }
     }
 }
 
@@ -34,6 +41,10 @@ impl crate::rules::Rule for Rule {
         String::from(DESCRIPTION)
     }
 
+    fn severity(&self) -> Severity {
+        Severity::Major
+    }
+
     fn do_validate(&self, _file: &File<'_>) -> bool {
         true
     }
@@ -48,26 +59,32 @@ impl crate::rules::Rule for Rule {
     fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
         let mut violations = Vec::new();
 
-        if let Statement::Class(class) = statement {
-            for member in class.members.iter() {
-                if let ClassLikeMember::Method(method) = member {
-                    if let MethodBody::Concrete(block) = &method.body {
-                        let complexity = calculate_cognitive_complexity(&block.statements, 0);
+        for callable in callables(statement) {
+            if let Some(statements) = callable.statements() {
+                let complexity = calculate_cognitive_complexity(statements, 0);
+                let max_complexity = threshold(
+                    self.settings.max_complexity,
+                    &self.settings.max_complexity_by_kind,
+                    callable.kind,
+                );
 
-                        if complexity > self.settings.max_complexity {
-                            let suggestion = format!(
-                                "The body of {} method has {} cognitive complexity. Make it easier to understand.",
-                                String::from_utf8_lossy(method.name.value),
-                                complexity,
-                            );
-                            violations.push(self.new_violation(file, suggestion, method.span()));
-                        }
-                    }
+                if complexity > max_complexity {
+                    let suggestion = format!(
+                        "The body of {} {} has {} cognitive complexity. Make it easier to understand.",
+                        callable.name,
+                        callable.kind.noun(),
+                        complexity,
+                    );
+                    violations.push(self.new_violation(file, suggestion, callable.span));
                 }
             }
         }
         violations
     }
+
+    fn validate_top_level_statements(&self) -> bool {
+        true
+    }
 }
 
 fn calculate_cognitive_complexity(statements: &Sequence<'_, Statement<'_>>, nesting: i64) -> i64 {
@@ -277,7 +294,7 @@ fn is_boolean_operator(operator: BinaryOperator) -> bool {
 
 #[cfg(test)]
 mod tests {
-    use crate::rules::tests::analyze_file_for_rule;
+    use crate::rules::tests::{analyze_file_for_rule, analyze_file_for_rule_with_settings};
 
     use super::*;
 
@@ -298,4 +315,20 @@ mod tests {
         let violations = analyze_file_for_rule("e16/simple.php", CODE);
         assert_eq!(violations.len(), 0);
     }
+
+    #[test]
+    fn complex_function() {
+        let violations = analyze_file_for_rule("e16/complex_function.php", CODE);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0]
+            .suggestion
+            .starts_with("The body of veryComplex function has"));
+
+        let violations = analyze_file_for_rule_with_settings(
+            "e16/complex_function.php",
+            CODE,
+            serde_json::json!({"max_complexity": 15, "max_complexity_by_kind": {"function": 20}}),
+        );
+        assert!(violations.is_empty());
+    }
 }
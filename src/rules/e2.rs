@@ -2,7 +2,9 @@ use mago_span::HasSpan;
 use mago_syntax::ast::Statement;
 
 use crate::file::File;
-use crate::results::Violation;
+use crate::results::{Severity, Violation};
+use crate::rules::visitor::{Scope, Visitor};
+use crate::rules::Rule as RuleTrait;
 
 pub(crate) static CODE: &str = "E0002";
 static DESCRIPTION: &str = "Empty catch";
@@ -10,7 +12,7 @@ static SUGGESTION: &str = "There is an empty catch. It's not recommended to catc
 
 pub struct Rule {}
 
-impl crate::rules::Rule for Rule {
+impl RuleTrait for Rule {
     fn get_code(&self) -> String {
         String::from(CODE)
     }
@@ -19,22 +21,46 @@ impl crate::rules::Rule for Rule {
         String::from(DESCRIPTION)
     }
 
+    fn severity(&self) -> Severity {
+        Severity::Major
+    }
+
     fn do_validate(&self, _file: &File<'_>) -> bool {
         true
     }
 
-    fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
-        let mut violations = Vec::new();
+    fn visitor<'a>(&'a self, file: &'a File<'a>) -> Option<Box<dyn Visitor<'a> + 'a>> {
+        Some(Box::new(EmptyCatches {
+            rule: self,
+            file,
+            violations: vec![],
+        }))
+    }
+}
 
+struct EmptyCatches<'a> {
+    rule: &'a Rule,
+    file: &'a File<'a>,
+    violations: Vec<Violation>,
+}
+
+impl<'a> Visitor<'a> for EmptyCatches<'a> {
+    fn enter_statement(&mut self, statement: &'a Statement<'a>, _scope: &Scope) {
         if let Statement::Try(s) = statement {
             for catch in s.catch_clauses.iter() {
                 if catch.block.statements.is_empty() {
-                    violations.push(self.new_violation(file, SUGGESTION.to_string(), catch.span()));
+                    self.violations.push(self.rule.new_violation(
+                        self.file,
+                        SUGGESTION.to_string(),
+                        catch.span(),
+                    ));
                 }
             }
-        };
+        }
+    }
 
-        violations
+    fn finish(self: Box<Self>) -> Vec<Violation> {
+        self.violations
     }
 }
 
@@ -55,6 +81,24 @@ mod tests {
         );
     }
 
+    #[test]
+    fn empty_catch_in_trait() {
+        let violations = analyze_file_for_rule("e2/empty_catch_in_trait.php", CODE);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations.first().unwrap().symbol,
+            Some("Test\\e2\\Retry::run".to_string())
+        );
+    }
+
+    #[test]
+    fn empty_catch_in_closure() {
+        let violations = analyze_file_for_rule("e2/empty_catch_in_closure.php", CODE);
+
+        assert_eq!(violations.len(), 2);
+    }
+
     #[test]
     fn non_empty_catch() {
         let violations = analyze_file_for_rule("e2/non_empty_catch.php", CODE);
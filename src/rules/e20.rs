@@ -1,6 +1,3 @@
-use std::collections::HashMap;
-use std::sync::Mutex;
-
 use mago_span::HasSpan;
 use mago_syntax::ast::*;
 use serde::{Deserialize, Serialize};
@@ -24,24 +21,9 @@ impl Default for Settings {
     }
 }
 
-/// Global index: child class name → parent class name.
 #[derive(Default)]
-struct InheritanceIndex {
-    extends: HashMap<String, String>,
-}
-
 pub struct Rule {
     pub settings: Settings,
-    index: Mutex<InheritanceIndex>,
-}
-
-impl Default for Rule {
-    fn default() -> Self {
-        Self {
-            settings: Settings::default(),
-            index: Mutex::new(InheritanceIndex::default()),
-        }
-    }
 }
 
 impl RuleTrait for Rule {
@@ -64,12 +46,8 @@ impl RuleTrait for Rule {
         }
     }
 
-    fn index_file(&self, file: &File<'_>) {
-        if let Some(program) = file.ast {
-            for statement in program.statements.iter() {
-                self.collect_extends(statement);
-            }
-        }
+    fn uses_symbols(&self) -> bool {
+        true
     }
 
     fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
@@ -77,7 +55,11 @@ impl RuleTrait for Rule {
 
         if let Statement::Class(class) = statement {
             let class_name = String::from_utf8_lossy(class.name.value).into_owned();
-            let depth = self.compute_depth(&class_name);
+            let depth = file.symbols.depth(
+                &file
+                    .resolver_at(class.span().start.offset)
+                    .qualify(&class_name),
+            );
 
             if depth > self.settings.max_depth {
                 let suggestion = format!(
@@ -92,60 +74,6 @@ impl RuleTrait for Rule {
     }
 }
 
-impl Rule {
-    fn collect_extends(&self, statement: &Statement<'_>) {
-        match statement {
-            Statement::Namespace(ns) => {
-                for s in ns.statements().iter() {
-                    self.collect_extends(s);
-                }
-            }
-            Statement::Class(class) => {
-                if let Some(extends) = &class.extends {
-                    for parent in extends.types.iter() {
-                        let child = String::from_utf8_lossy(class.name.value).into_owned();
-                        let parent_str = std::str::from_utf8(parent.value()).unwrap_or_default();
-                        let parent_name = parent_str
-                            .trim_start_matches('\\')
-                            .rsplit('\\')
-                            .next()
-                            .unwrap_or(parent_str)
-                            .to_string();
-                        if let Ok(mut index) = self.index.lock() {
-                            index.extends.insert(child, parent_name);
-                        }
-                    }
-                }
-            }
-            _ => {}
-        }
-    }
-
-    fn compute_depth(&self, class_name: &str) -> usize {
-        let index = match self.index.lock() {
-            Ok(idx) => idx,
-            Err(_) => return 0,
-        };
-
-        let mut depth = 0;
-        let mut current = class_name.to_string();
-        // Guard against circular inheritance — cap at 100 iterations
-        let max_iterations = 100;
-        let mut iterations = 0;
-
-        while let Some(parent) = index.extends.get(&current) {
-            depth += 1;
-            current = parent.clone();
-            iterations += 1;
-            if iterations >= max_iterations {
-                break;
-            }
-        }
-
-        depth
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use crate::rules::tests::analyze_file_for_rule;
@@ -179,4 +107,10 @@ mod tests {
         let violations = analyze_file_for_rule("e20/interface_not_counted.php", CODE);
         assert_eq!(violations.len(), 0);
     }
+
+    #[test]
+    fn same_name_in_other_namespace() {
+        let violations = analyze_file_for_rule("e20/same_name_in_other_namespace.php", CODE);
+        assert_eq!(violations.len(), 0);
+    }
 }
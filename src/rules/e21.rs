@@ -1,6 +1,3 @@
-use std::collections::{HashMap, HashSet};
-use std::sync::Mutex;
-
 use mago_span::HasSpan;
 use mago_syntax::ast::*;
 use serde::{Deserialize, Serialize};
@@ -24,24 +21,9 @@ impl Default for Settings {
     }
 }
 
-/// Global index: parent class name → set of child class names.
 #[derive(Default)]
-struct ChildrenIndex {
-    children: HashMap<String, HashSet<String>>,
-}
-
 pub struct Rule {
     pub settings: Settings,
-    index: Mutex<ChildrenIndex>,
-}
-
-impl Default for Rule {
-    fn default() -> Self {
-        Self {
-            settings: Settings::default(),
-            index: Mutex::new(ChildrenIndex::default()),
-        }
-    }
 }
 
 impl RuleTrait for Rule {
@@ -64,12 +46,8 @@ impl RuleTrait for Rule {
         }
     }
 
-    fn index_file(&self, file: &File<'_>) {
-        if let Some(program) = file.ast {
-            for statement in program.statements.iter() {
-                self.collect_children(statement);
-            }
-        }
+    fn uses_symbols(&self) -> bool {
+        true
     }
 
     fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
@@ -77,7 +55,14 @@ impl RuleTrait for Rule {
 
         if let Statement::Class(class) = statement {
             let class_name = String::from_utf8_lossy(class.name.value).into_owned();
-            let child_count = self.get_child_count(&class_name);
+            let child_count = file
+                .symbols
+                .children(
+                    &file
+                        .resolver_at(class.span().start.offset)
+                        .qualify(&class_name),
+                )
+                .count();
 
             if child_count > self.settings.max_children {
                 let suggestion = format!(
@@ -92,50 +77,6 @@ impl RuleTrait for Rule {
     }
 }
 
-impl Rule {
-    fn collect_children(&self, statement: &Statement<'_>) {
-        match statement {
-            Statement::Namespace(ns) => {
-                for s in ns.statements().iter() {
-                    self.collect_children(s);
-                }
-            }
-            Statement::Class(class) => {
-                if let Some(extends) = &class.extends {
-                    for parent in extends.types.iter() {
-                        let child = String::from_utf8_lossy(class.name.value).into_owned();
-                        let parent_str = std::str::from_utf8(parent.value()).unwrap_or_default();
-                        let parent_name = parent_str
-                            .trim_start_matches('\\')
-                            .rsplit('\\')
-                            .next()
-                            .unwrap_or(parent_str)
-                            .to_string();
-                        if let Ok(mut index) = self.index.lock() {
-                            index
-                                .children
-                                .entry(parent_name)
-                                .or_default()
-                                .insert(child);
-                        }
-                    }
-                }
-            }
-            _ => {}
-        }
-    }
-
-    fn get_child_count(&self, class_name: &str) -> usize {
-        match self.index.lock() {
-            Ok(index) => index
-                .children
-                .get(class_name)
-                .map_or(0, |children| children.len()),
-            Err(_) => 0,
-        }
-    }
-}
-
 #[cfg(test)]
 mod tests {
     use crate::rules::tests::analyze_file_for_rule;
@@ -166,4 +107,10 @@ mod tests {
         let violations = analyze_file_for_rule("e21/interface_not_counted.php", CODE);
         assert_eq!(violations.len(), 0);
     }
+
+    #[test]
+    fn same_name_in_other_namespace() {
+        let violations = analyze_file_for_rule("e21/same_name_in_other_namespace.php", CODE);
+        assert_eq!(violations.len(), 0);
+    }
 }
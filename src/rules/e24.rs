@@ -1,11 +1,12 @@
-use mago_span::HasSpan;
-use mago_syntax::ast::{ClassLikeMember, Statement, MethodBody};
+use std::collections::BTreeMap;
+
+use mago_syntax::ast::Statement;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::file::File;
 use crate::results::Violation;
-use crate::rules::Rule as RuleTrait;
+use crate::rules::callable::{callables, threshold, CallableKind};
 
 pub(crate) static CODE: &str = "E0024";
 static DESCRIPTION: &str = "Lines of Code (LOC) per Method";
@@ -13,11 +14,16 @@ static DESCRIPTION: &str = "Lines of Code (LOC) per Method";
 #[derive(Deserialize, Serialize)]
 pub struct Settings {
     pub max_loc: usize,
+    #[serde(default)]
+    pub max_loc_by_kind: BTreeMap<CallableKind, usize>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
-        Settings { max_loc: 30 }
+        Settings {
+            max_loc: 30,
+            max_loc_by_kind: BTreeMap::new(),
+        }
     }
 }
 
@@ -26,36 +32,6 @@ pub struct Rule {
     pub settings: Settings,
 }
 
-impl Rule {
-    fn check_methods(
-        &self,
-        file: &File<'_>,
-        members: &mago_syntax::ast::Sequence<'_, ClassLikeMember<'_>>,
-        violations: &mut Vec<Violation>,
-    ) {
-        for member in members.iter() {
-            if let ClassLikeMember::Method(method) = member {
-                if let MethodBody::Concrete(block) = &method.body {
-                    let start_line = file.line_number(block.span().start.offset);
-                    let end_line = file.line_number(block.span().end.offset);
-
-                    if end_line <= start_line + 1 {
-                        continue;
-                    }
-                    let loc = end_line - start_line - 1;
-                    if loc > self.settings.max_loc {
-                        let suggestion = format!(
-                            "Method \"{}\" has {} lines of code (max: {}). Consider breaking it into smaller methods.",
-                            String::from_utf8_lossy(method.name.value), loc, self.settings.max_loc
-                        );
-                        violations.push(self.new_violation(file, suggestion, method.span()));
-                    }
-                }
-            }
-        }
-    }
-}
-
 impl crate::rules::Rule for Rule {
     fn get_code(&self) -> String {
         String::from(CODE)
@@ -79,21 +55,28 @@ impl crate::rules::Rule for Rule {
     fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
         let mut violations = Vec::new();
 
-        match statement {
-            Statement::Class(class) => {
-                self.check_methods(file, &class.members, &mut violations);
-            }
-            Statement::Trait(t) => {
-                self.check_methods(file, &t.members, &mut violations);
+        for callable in callables(statement) {
+            let loc = callable.lines_of_code(file);
+            let max_loc = threshold(
+                self.settings.max_loc,
+                &self.settings.max_loc_by_kind,
+                callable.kind,
+            );
+            if loc > max_loc {
+                let suggestion = format!(
+                    "{} \"{}\" has {} lines of code (max: {}). Consider breaking it into smaller functions.",
+                    callable.kind.title(), callable.name, loc, max_loc
+                );
+                violations.push(self.new_violation(file, suggestion, callable.span));
             }
-            Statement::Enum(e) => {
-                self.check_methods(file, &e.members, &mut violations);
-            }
-            _ => {}
         }
 
         violations
     }
+
+    fn validate_top_level_statements(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -114,4 +97,13 @@ mod tests {
         let violations = analyze_file_for_rule("e24/short_method.php", CODE);
         assert!(violations.len().eq(&0));
     }
+
+    #[test]
+    fn long_property_hook() {
+        let violations = analyze_file_for_rule("e24/long_property_hook.php", CODE);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0]
+            .suggestion
+            .starts_with("Property hook \"$celsius::set\" has 32 lines of code"));
+    }
 }
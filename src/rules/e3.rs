@@ -2,7 +2,7 @@ use mago_span::HasSpan;
 use mago_syntax::ast::{ClassLikeMember, Modifier, Sequence, Statement};
 
 use crate::file::File;
-use crate::results::Violation;
+use crate::results::{Edit, Violation};
 
 pub struct Rule {}
 
@@ -40,7 +40,14 @@ impl crate::rules::Rule for Rule {
                             "Method name \"{}\" should be declared with a visibility modifier.",
                             String::from_utf8_lossy(method.name.value)
                         );
-                        violations.push(self.new_violation(file, suggestion, method.span()));
+                        let offset = method
+                            .modifiers
+                            .first()
+                            .map_or(method.function.span.start.offset, |m| m.span().start.offset);
+                        violations.push(
+                            self.new_violation(file, suggestion, method.span())
+                                .with_edits(vec![Edit::insert(offset, "public ")]),
+                        );
                     }
                 }
             }
@@ -64,7 +71,7 @@ impl Rule {
 
 #[cfg(test)]
 mod tests {
-    use crate::rules::tests::analyze_file_for_rule;
+    use crate::rules::tests::{analyze_file_for_rule, fix_file_for_rule};
 
     use super::*;
 
@@ -92,6 +99,13 @@ mod tests {
         );
     }
 
+    #[test]
+    fn fix_adds_public_modifier() {
+        let fixed = fix_file_for_rule("e3/no_method_modifiers.php", CODE);
+
+        assert!(fixed.contains("    public function methodWithoutModifier() {"));
+    }
+
     #[test]
     fn with_modifiers() {
         let violations = analyze_file_for_rule("e3/with_modifiers.php", CODE);
@@ -1,12 +1,13 @@
-use mago_span::HasSpan;
-use mago_syntax::ast::{ClassLikeMember, Statement, MethodBody};
+use std::collections::BTreeMap;
+
+use mago_syntax::ast::Statement;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::file::File;
 use crate::results::Violation;
+use crate::rules::callable::{callables, threshold, CallableKind};
 use crate::rules::e9::calculate_complexity;
-use crate::rules::Rule as RuleTrait;
 
 pub(crate) static CODE: &str = "E0030";
 static DESCRIPTION: &str = "Cyclomatic Complexity Density";
@@ -14,11 +15,16 @@ static DESCRIPTION: &str = "Cyclomatic Complexity Density";
 #[derive(Deserialize, Serialize)]
 pub struct Settings {
     pub max_density: f64,
+    #[serde(default)]
+    pub max_density_by_kind: BTreeMap<CallableKind, f64>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
-        Settings { max_density: 0.3 }
+        Settings {
+            max_density: 0.3,
+            max_density_by_kind: BTreeMap::new(),
+        }
     }
 }
 
@@ -27,41 +33,6 @@ pub struct Rule {
     pub settings: Settings,
 }
 
-impl Rule {
-    fn check_methods(
-        &self,
-        file: &File<'_>,
-        members: &mago_syntax::ast::Sequence<'_, ClassLikeMember<'_>>,
-        violations: &mut Vec<Violation>,
-    ) {
-        for member in members.iter() {
-            if let ClassLikeMember::Method(method) = member {
-                if let MethodBody::Concrete(block) = &method.body {
-                    let start_line = file.line_number(block.span().start.offset);
-                    let end_line = file.line_number(block.span().end.offset);
-
-                    if end_line <= start_line + 1 {
-                        continue;
-                    }
-                    let loc = end_line - start_line - 1;
-
-                    let complexity = 1 + calculate_complexity(&block.statements);
-                    let density = complexity as f64 / loc as f64;
-
-                    if density > self.settings.max_density {
-                        let suggestion = format!(
-                            "Method \"{}\" has complexity density of {:.2} (max: {:.2}). Complexity {} in {} lines. Consider simplifying the logic.",
-                            String::from_utf8_lossy(method.name.value), density, self.settings.max_density,
-                            complexity, loc
-                        );
-                        violations.push(self.new_violation(file, suggestion, method.span()));
-                    }
-                }
-            }
-        }
-    }
-}
-
 impl crate::rules::Rule for Rule {
     fn get_code(&self) -> String {
         String::from(CODE)
@@ -85,21 +56,39 @@ impl crate::rules::Rule for Rule {
     fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
         let mut violations = Vec::new();
 
-        match statement {
-            Statement::Class(class) => {
-                self.check_methods(file, &class.members, &mut violations);
-            }
-            Statement::Trait(t) => {
-                self.check_methods(file, &t.members, &mut violations);
+        for callable in callables(statement) {
+            let Some(statements) = callable.statements() else {
+                continue;
+            };
+            let loc = callable.lines_of_code(file);
+            if loc == 0 {
+                continue;
             }
-            Statement::Enum(e) => {
-                self.check_methods(file, &e.members, &mut violations);
+
+            let complexity = 1 + calculate_complexity(statements);
+            let density = complexity as f64 / loc as f64;
+            let max_density = threshold(
+                self.settings.max_density,
+                &self.settings.max_density_by_kind,
+                callable.kind,
+            );
+
+            if density > max_density {
+                let suggestion = format!(
+                    "{} \"{}\" has complexity density of {:.2} (max: {:.2}). Complexity {} in {} lines. Consider simplifying the logic.",
+                    callable.kind.title(), callable.name, density, max_density,
+                    complexity, loc
+                );
+                violations.push(self.new_violation(file, suggestion, callable.span));
             }
-            _ => {}
         }
 
         violations
     }
+
+    fn validate_top_level_statements(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -120,4 +109,13 @@ mod tests {
         let violations = analyze_file_for_rule("e30/sparse_method.php", CODE);
         assert!(violations.len().eq(&0));
     }
+
+    #[test]
+    fn dense_function() {
+        let violations = analyze_file_for_rule("e30/dense_function.php", CODE);
+        assert_eq!(violations.len(), 1);
+        assert!(violations[0]
+            .suggestion
+            .contains("Function \"denseFunction\""));
+    }
 }
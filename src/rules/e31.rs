@@ -0,0 +1,80 @@
+use mago_syntax::ast::Statement;
+
+use crate::file::File;
+use crate::results::{Severity, Violation};
+
+pub(crate) static CODE: &str = "E0031";
+static DESCRIPTION: &str = "Unused suppression comment";
+
+/// Violations of this rule are produced by `Analyse::analyse_file` once all
+/// the other rules have run, as only then it is known which suppression
+/// comments were needed.
+pub struct Rule {}
+
+impl crate::rules::Rule for Rule {
+    fn get_code(&self) -> String {
+        String::from(CODE)
+    }
+
+    fn description(&self) -> String {
+        String::from(DESCRIPTION)
+    }
+
+    fn severity(&self) -> Severity {
+        Severity::Info
+    }
+
+    fn do_validate(&self, _file: &File<'_>) -> bool {
+        false
+    }
+
+    fn validate(&self, _file: &File<'_>, _statement: &Statement<'_>) -> Vec<Violation> {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::rules::tests::{analyze_file_for_rule, analyze_file_for_rules};
+
+    use super::*;
+
+    #[test]
+    fn used_suppressions() {
+        let violations =
+            analyze_file_for_rules("e31/used_suppressions.php", &[CODE, "E0003", "E0011"]);
+
+        assert!(violations.is_empty(), "{:?}", violations);
+    }
+
+    #[test]
+    fn unused_suppressions() {
+        let violations =
+            analyze_file_for_rules("e31/unused_suppressions.php", &[CODE, "E0003", "E0011"]);
+
+        assert_eq!(violations.len(), 2);
+        assert_eq!(
+            violations[0].suggestion,
+            "The suppression of E0011 doesn't match any violation.".to_string()
+        );
+        assert_eq!(violations[0].start_line, 7);
+        assert_eq!(
+            violations[1].suggestion,
+            "The suppression comment doesn't match any violation.".to_string()
+        );
+    }
+
+    #[test]
+    fn inactive_rules_are_not_reported() {
+        let violations = analyze_file_for_rule("e31/unused_suppressions.php", CODE);
+
+        assert_eq!(violations.len(), 1);
+    }
+
+    #[test]
+    fn ignore_file() {
+        let violations = analyze_file_for_rule("e31/ignore_file.php", "E0003");
+
+        assert!(violations.is_empty());
+    }
+}
@@ -0,0 +1,424 @@
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use mago_syntax::ast::*;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::PathFilter;
+use crate::file::{File, SymbolKind};
+use crate::results::Violation;
+use crate::rules::dependencies::{
+    expression_dependencies, hint_dependency, member_dependencies, statement_dependencies,
+    Dependency,
+};
+use crate::rules::visitor::{Scope, Visitor};
+use crate::rules::Rule as RuleTrait;
+
+pub(crate) static CODE: &str = "E0032";
+static DESCRIPTION: &str = "Architecture layers";
+
+#[derive(Default, Deserialize, Serialize)]
+pub struct Settings {
+    #[serde(default)]
+    pub layers: Vec<Layer>,
+    /// The layers each layer may depend on, besides itself.
+    #[serde(default)]
+    pub allowed: BTreeMap<String, Vec<String>>,
+}
+
+/// A layer holds the class-likes matching any of its criteria.
+#[derive(Clone, Default, Deserialize, Serialize)]
+pub struct Layer {
+    pub name: String,
+    /// Namespace prefixes, e.g. `App\Domain`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub namespaces: Vec<String>,
+    /// Globs of the files declaring the class-likes.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub paths: Vec<String>,
+    /// Regular expressions matching the fully qualified class names.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub classes: Vec<String>,
+}
+
+struct CompiledLayer {
+    name: String,
+    namespaces: Vec<String>,
+    paths: Option<PathFilter>,
+    classes: Vec<Regex>,
+}
+
+impl CompiledLayer {
+    fn new(layer: &Layer) -> Result<Self, regex::Error> {
+        let classes = layer
+            .classes
+            .iter()
+            .map(|class| Regex::new(class))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            name: layer.name.clone(),
+            namespaces: layer
+                .namespaces
+                .iter()
+                .map(|namespace| namespace.trim_matches('\\').to_ascii_lowercase())
+                .collect(),
+            paths: match layer.paths.is_empty() {
+                true => None,
+                false => Some(PathFilter::new(&layer.paths, &[])),
+            },
+            classes,
+        })
+    }
+
+    fn contains(&self, subject: &Subject<'_>) -> bool {
+        let name = subject.name.to_ascii_lowercase();
+        let in_namespace = self.namespaces.iter().any(|namespace| {
+            name.strip_prefix(namespace.as_str())
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('\\'))
+        });
+        let in_path = match (&self.paths, subject.path) {
+            (Some(paths), Some(path)) => paths.is_match(path),
+            _ => false,
+        };
+        let in_classes = subject.class && self.classes.iter().any(|r| r.is_match(subject.name));
+
+        in_namespace || in_path || in_classes
+    }
+}
+
+/// The code depending on a class-like, or the class-like depended on.
+struct Subject<'s> {
+    /// The fully qualified name of the class-like, or of the namespace for
+    /// the code outside of class-likes.
+    name: &'s str,
+    class: bool,
+    /// The file declaring the class-like, unknown for the classes outside of
+    /// the analysed files.
+    path: Option<&'s Path>,
+}
+
+pub struct Rule {
+    pub settings: Settings,
+    layers: Vec<CompiledLayer>,
+    /// The file declaring each class-like, by lowercase fully qualified name.
+    index: RwLock<HashMap<String, PathBuf>>,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self {
+            settings: Settings::default(),
+            layers: vec![],
+            index: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl RuleTrait for Rule {
+    fn get_code(&self) -> String {
+        String::from(CODE)
+    }
+
+    fn description(&self) -> String {
+        String::from(DESCRIPTION)
+    }
+
+    fn do_validate(&self, _file: &File<'_>) -> bool {
+        !self.layers.is_empty()
+    }
+
+    fn set_config(&mut self, json: &Value) {
+        match serde_json::from_value::<Settings>(json.to_owned()) {
+            Ok(settings) => {
+                let layers = settings
+                    .layers
+                    .iter()
+                    .map(CompiledLayer::new)
+                    .collect::<Result<_, _>>();
+                match layers {
+                    Ok(layers) => {
+                        self.layers = layers;
+                        self.settings = settings;
+                    }
+                    Err(e) => self.output_error(e.into()),
+                }
+            }
+            Err(e) => self.output_error(e.into()),
+        }
+    }
+
+    fn index_entry(&self, file: &File<'_>) -> Option<Value> {
+        if self.layers.iter().all(|layer| layer.paths.is_none()) {
+            return None;
+        }
+        let entry: HashMap<String, PathBuf> = file
+            .declared_symbols
+            .iter()
+            .filter(|symbol| !matches!(symbol.kind, SymbolKind::Function | SymbolKind::Constant))
+            .map(|symbol| {
+                (
+                    symbol.fully_qualified_name().to_ascii_lowercase(),
+                    file.path.clone(),
+                )
+            })
+            .collect();
+
+        serde_json::to_value(entry).ok()
+    }
+
+    fn load_index_entry(&self, entry: &Value) {
+        if let Ok(entry) = serde_json::from_value::<HashMap<String, PathBuf>>(entry.to_owned()) {
+            if let Ok(mut index) = self.index.write() {
+                index.extend(entry);
+            }
+        }
+    }
+
+    fn unload_index_entry(&self, entry: &Value) {
+        if let Ok(entry) = serde_json::from_value::<HashMap<String, PathBuf>>(entry.to_owned()) {
+            if let Ok(mut index) = self.index.write() {
+                // The symbol may have been declared again by another file
+                for (symbol, path) in entry {
+                    if index.get(&symbol) == Some(&path) {
+                        index.remove(&symbol);
+                    }
+                }
+            }
+        }
+    }
+
+    fn visitor<'a>(&'a self, file: &'a File<'a>) -> Option<Box<dyn Visitor<'a> + 'a>> {
+        Some(Box::new(LayerDependencies {
+            rule: self,
+            file,
+            violations: vec![],
+        }))
+    }
+}
+
+impl Rule {
+    fn layer_of(&self, subject: &Subject<'_>) -> Option<&str> {
+        self.layers
+            .iter()
+            .find(|layer| layer.contains(subject))
+            .map(|layer| layer.name.as_str())
+    }
+
+    fn is_allowed(&self, layer: &str, target_layer: &str) -> bool {
+        layer == target_layer
+            || self
+                .settings
+                .allowed
+                .get(layer)
+                .is_some_and(|allowed| allowed.iter().any(|allowed| allowed == target_layer))
+    }
+}
+
+struct LayerDependencies<'a> {
+    rule: &'a Rule,
+    file: &'a File<'a>,
+    violations: Vec<Violation>,
+}
+
+impl LayerDependencies<'_> {
+    /// The fully qualified name of the class-like the code belongs to. The
+    /// code outside of class-likes, like the `use` imports, belongs to the
+    /// class-like of the file when it declares a single one.
+    fn source(&self, scope: &Scope, own_name: Option<&[u8]>) -> (String, bool) {
+        let namespace = scope.namespace().unwrap_or_default();
+        let qualify = |name: &str| match namespace {
+            "" => name.to_string(),
+            namespace => format!("{}\\{}", namespace, name),
+        };
+        if let Some(name) = own_name {
+            return (qualify(&String::from_utf8_lossy(name)), true);
+        }
+        if let Some(name) = scope.named_class_like() {
+            return (qualify(name), true);
+        }
+
+        let mut class_likes = self.file.declared_symbols.iter().filter(|symbol| {
+            !matches!(symbol.kind, SymbolKind::Function | SymbolKind::Constant)
+                && symbol.namespace.as_deref().unwrap_or_default() == namespace
+        });
+        match (class_likes.next(), class_likes.next()) {
+            (Some(symbol), None) => (symbol.fully_qualified_name(), true),
+            _ => (namespace.to_string(), false),
+        }
+    }
+
+    fn check(&mut self, dependencies: Vec<Dependency>, scope: &Scope, own_name: Option<&[u8]>) {
+        if dependencies.is_empty() {
+            return;
+        }
+        let (source, class) = self.source(scope, own_name);
+        let layer = match self.rule.layer_of(&Subject {
+            name: &source,
+            class,
+            path: Some(&self.file.path),
+        }) {
+            Some(layer) => layer,
+            None => return,
+        };
+
+        for dependency in dependencies {
+            let target = dependency.resolve(self.file);
+            let path = self
+                .rule
+                .index
+                .read()
+                .ok()
+                .and_then(|index| index.get(&target.to_ascii_lowercase()).cloned());
+            let target_layer = match self.rule.layer_of(&Subject {
+                name: &target,
+                class: true,
+                path: path.as_deref(),
+            }) {
+                Some(target_layer) => target_layer,
+                None => continue,
+            };
+            if self.rule.is_allowed(layer, target_layer) {
+                continue;
+            }
+
+            let subject = match (class, source.is_empty()) {
+                (true, _) => format!("\"{}\"", source),
+                (false, false) => format!("Namespace \"{}\"", source),
+                (false, true) => "The global namespace".to_string(),
+            };
+            let suggestion = format!(
+                "{} of layer \"{}\" depends on \"{}\" of layer \"{}\" ({}), which the layer is not allowed to use.",
+                subject,
+                layer,
+                target,
+                target_layer,
+                dependency.kind.noun()
+            );
+            self.violations.push(
+                self.rule
+                    .new_violation(self.file, suggestion, dependency.span),
+            );
+        }
+    }
+}
+
+impl<'a> Visitor<'a> for LayerDependencies<'a> {
+    fn enter_statement(&mut self, statement: &'a Statement<'a>, scope: &Scope) {
+        // The parents of a class-like are dependencies of the class-like
+        // itself, which isn't in the scope of its statement yet
+        let own_name = match statement {
+            Statement::Class(class) => Some(class.name.value),
+            Statement::Interface(interface) => Some(interface.name.value),
+            Statement::Enum(r#enum) => Some(r#enum.name.value),
+            _ => None,
+        };
+        self.check(statement_dependencies(statement), scope, own_name);
+    }
+
+    fn enter_member(&mut self, member: &'a ClassLikeMember<'a>, scope: &Scope) {
+        self.check(member_dependencies(member), scope, None);
+    }
+
+    fn visit_hint(&mut self, hint: &'a Hint<'a>, scope: &Scope) {
+        self.check(hint_dependency(hint).into_iter().collect(), scope, None);
+    }
+
+    fn enter_expression(&mut self, expression: &'a Expression<'a>, scope: &Scope) {
+        self.check(expression_dependencies(expression), scope, None);
+    }
+
+    fn finish(self: Box<Self>) -> Vec<Violation> {
+        self.violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::rules::tests::analyze_file_for_rule_with_settings;
+
+    use super::*;
+
+    fn settings() -> Value {
+        json!({
+            "layers": [
+                {"name": "Domain", "namespaces": ["App\\Domain"]},
+                {"name": "Infrastructure", "namespaces": ["App\\Infrastructure"]},
+                {"name": "Controller", "classes": ["Controller$"]},
+                {"name": "Application", "namespaces": ["App\\Application"]},
+            ],
+            "allowed": {
+                "Infrastructure": ["Domain"],
+                "Application": ["Domain"],
+                "Controller": ["Application"],
+            },
+        })
+    }
+
+    #[test]
+    fn domain_depending_on_infrastructure() {
+        let violations = analyze_file_for_rule_with_settings("e32/domain.php", CODE, settings());
+
+        let lines = violations.iter().map(|v| v.start_line).collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![5, 7, 11, 13, 15, 17, 18, 20],
+            "{:?}",
+            violations
+        );
+        assert_eq!(
+            violations[0].suggestion,
+            "\"App\\Domain\\Order\" of layer \"Domain\" depends on \"App\\Infrastructure\\Database\" of layer \"Infrastructure\" (use), which the layer is not allowed to use."
+        );
+        assert!(violations[1].suggestion.contains("(implements)"));
+        assert!(violations[2].suggestion.contains("(type hint)"));
+        assert!(violations[3].suggestion.contains("(new)"));
+    }
+
+    #[test]
+    fn controller_using_domain() {
+        let violations =
+            analyze_file_for_rule_with_settings("e32/controller.php", CODE, settings());
+
+        assert_eq!(violations.len(), 1, "{:?}", violations);
+        assert_eq!(violations[0].start_line, 11);
+        assert!(violations[0]
+            .suggestion
+            .contains("\"App\\Http\\OrderController\" of layer \"Controller\" depends on \"App\\Domain\\Order\" of layer \"Domain\""));
+    }
+
+    #[test]
+    fn allowed_dependencies() {
+        let violations =
+            analyze_file_for_rule_with_settings("e32/infrastructure.php", CODE, settings());
+
+        assert!(violations.is_empty(), "{:?}", violations);
+    }
+
+    #[test]
+    fn layers_by_path() {
+        let settings = json!({
+            "layers": [
+                {"name": "Domain", "namespaces": ["App\\Domain"]},
+                {"name": "Examples", "paths": ["**/e32/*.php"]},
+            ],
+        });
+        let violations =
+            analyze_file_for_rule_with_settings("e32/infrastructure.php", CODE, settings);
+
+        assert_eq!(violations.len(), 3, "{:?}", violations);
+        assert!(violations[0].suggestion.contains("of layer \"Examples\""));
+    }
+
+    #[test]
+    fn without_layers() {
+        let violations = analyze_file_for_rule_with_settings("e32/domain.php", CODE, json!({}));
+
+        assert!(violations.is_empty());
+    }
+}
@@ -0,0 +1,500 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::sync::{Arc, RwLock};
+
+use mago_span::Span;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::file::File;
+use crate::results::{RelatedLocation, Violation};
+use crate::rules::dependencies::class_like_dependencies;
+use crate::rules::visitor::Visitor;
+use crate::rules::Rule as RuleTrait;
+use crate::symbols::namespace_of;
+
+pub(crate) static CODE: &str = "E0033";
+static DESCRIPTION: &str = "Circular dependencies";
+
+#[derive(Deserialize, Serialize)]
+pub struct Settings {
+    pub granularity: Granularity,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            granularity: Granularity::Class,
+        }
+    }
+}
+
+/// The nodes of the dependency graph searched for cycles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    Class,
+    Namespace,
+}
+
+/// A class-like of the index and its references to other class-likes.
+#[derive(Clone, Deserialize, Serialize)]
+struct IndexedClassLike {
+    name: String,
+    path: String,
+    references: Vec<Reference>,
+}
+
+/// The first reference of a class-like to another one.
+#[derive(Clone, Deserialize, Serialize)]
+struct Reference {
+    name: String,
+    start: u32,
+    end: u32,
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+}
+
+/// A dependency between two nodes of the graph, through the reference of a
+/// class-like declared in `path`.
+#[derive(Clone)]
+struct Edge {
+    from: String,
+    to: String,
+    path: String,
+    reference: Reference,
+}
+
+/// The shortest cycle through the first node, by name, of a strongly
+/// connected component.
+struct Cycle {
+    edges: Vec<Edge>,
+}
+
+pub struct Rule {
+    pub settings: Settings,
+    /// By lowercase fully qualified name.
+    index: RwLock<HashMap<String, IndexedClassLike>>,
+    /// Found once the index is complete, reset when it changes.
+    cycles: RwLock<Option<Arc<Vec<Cycle>>>>,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self {
+            settings: Settings::default(),
+            index: RwLock::new(HashMap::new()),
+            cycles: RwLock::new(None),
+        }
+    }
+}
+
+impl RuleTrait for Rule {
+    fn get_code(&self) -> String {
+        String::from(CODE)
+    }
+
+    fn description(&self) -> String {
+        String::from(DESCRIPTION)
+    }
+
+    fn do_validate(&self, _file: &File<'_>) -> bool {
+        true
+    }
+
+    fn set_config(&mut self, json: &Value) {
+        match serde_json::from_value(json.to_owned()) {
+            Ok(settings) => self.settings = settings,
+            Err(e) => self.output_error(e.into()),
+        }
+    }
+
+    fn index_entry(&self, file: &File<'_>) -> Option<Value> {
+        file.ast?;
+        let path = file.path.display().to_string();
+        let entry: Vec<IndexedClassLike> = class_like_dependencies(file)
+            .into_iter()
+            .map(|class_like| IndexedClassLike {
+                name: class_like.name,
+                path: path.clone(),
+                references: class_like
+                    .dependencies
+                    .into_iter()
+                    .map(|(name, span)| Reference {
+                        name,
+                        start: span.start.offset,
+                        end: span.end.offset,
+                        start_line: file.line_number(span.start.offset),
+                        start_column: file.column_number(span.start.offset),
+                        end_line: file.line_number(span.end.offset),
+                        end_column: file.column_number(span.end.offset),
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        serde_json::to_value(entry).ok()
+    }
+
+    fn load_index_entry(&self, entry: &Value) {
+        if let Ok(entry) = serde_json::from_value::<Vec<IndexedClassLike>>(entry.to_owned()) {
+            if let Ok(mut index) = self.index.write() {
+                for class_like in entry {
+                    index.insert(class_like.name.to_ascii_lowercase(), class_like);
+                }
+            }
+            if let Ok(mut cycles) = self.cycles.write() {
+                *cycles = None;
+            }
+        }
+    }
+
+    fn unload_index_entry(&self, entry: &Value) {
+        if let Ok(entry) = serde_json::from_value::<Vec<IndexedClassLike>>(entry.to_owned()) {
+            if let Ok(mut index) = self.index.write() {
+                for class_like in entry {
+                    let name = class_like.name.to_ascii_lowercase();
+                    // The class-like may have been declared again by another file
+                    if index
+                        .get(&name)
+                        .is_some_and(|indexed| indexed.path == class_like.path)
+                    {
+                        index.remove(&name);
+                    }
+                }
+            }
+            if let Ok(mut cycles) = self.cycles.write() {
+                *cycles = None;
+            }
+        }
+    }
+
+    fn visitor<'a>(&'a self, file: &'a File<'a>) -> Option<Box<dyn Visitor<'a> + 'a>> {
+        Some(Box::new(Cycles { rule: self, file }))
+    }
+}
+
+impl Rule {
+    fn cycles(&self) -> Arc<Vec<Cycle>> {
+        if let Some(cycles) = self.cycles.read().ok().and_then(|cycles| cycles.clone()) {
+            return cycles;
+        }
+        let cycles = Arc::new(self.find_cycles());
+        if let Ok(mut cached) = self.cycles.write() {
+            *cached = Some(cycles.clone());
+        }
+
+        cycles
+    }
+
+    /// The edges of the graph, by lowercase node and target node. Between two
+    /// namespaces, the edge is the first reference of their class-likes.
+    fn graph(&self) -> BTreeMap<String, BTreeMap<String, Edge>> {
+        let index = match self.index.read() {
+            Ok(index) => index,
+            Err(_) => return BTreeMap::new(),
+        };
+        let mut class_likes = index.iter().collect::<Vec<_>>();
+        class_likes.sort_by(|a, b| a.0.cmp(b.0));
+
+        let mut graph: BTreeMap<String, BTreeMap<String, Edge>> = BTreeMap::new();
+        for (_, class_like) in class_likes {
+            for reference in &class_like.references {
+                let target = match index.get(&reference.name.to_ascii_lowercase()) {
+                    Some(target) => target,
+                    None => continue,
+                };
+                let (from, to) = match self.settings.granularity {
+                    Granularity::Class => (class_like.name.as_str(), target.name.as_str()),
+                    Granularity::Namespace => {
+                        (namespace_of(&class_like.name), namespace_of(&target.name))
+                    }
+                };
+                if from.eq_ignore_ascii_case(to) {
+                    continue;
+                }
+                graph
+                    .entry(from.to_ascii_lowercase())
+                    .or_default()
+                    .entry(to.to_ascii_lowercase())
+                    .or_insert_with(|| Edge {
+                        from: from.to_string(),
+                        to: to.to_string(),
+                        path: class_like.path.clone(),
+                        reference: reference.clone(),
+                    });
+            }
+        }
+
+        graph
+    }
+
+    fn find_cycles(&self) -> Vec<Cycle> {
+        let graph = self.graph();
+
+        strongly_connected_components(&graph)
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .filter_map(|component| shortest_cycle(&graph, &component))
+            .collect()
+    }
+}
+
+/// Tarjan's algorithm, without recursion so deep graphs don't overflow the
+/// stack.
+fn strongly_connected_components<E>(
+    graph: &BTreeMap<String, BTreeMap<String, E>>,
+) -> Vec<BTreeSet<String>> {
+    let nodes = graph
+        .iter()
+        .flat_map(|(node, edges)| std::iter::once(node).chain(edges.keys()))
+        .collect::<BTreeSet<_>>();
+    let successors = |node: &str| graph.get(node).into_iter().flat_map(|edges| edges.keys());
+
+    let mut next_index = 0;
+    let mut indexes: HashMap<&str, usize> = HashMap::new();
+    let mut low_links: HashMap<&str, usize> = HashMap::new();
+    let mut stack: Vec<&str> = vec![];
+    let mut on_stack: BTreeSet<&str> = BTreeSet::new();
+    let mut components = vec![];
+
+    for root in nodes {
+        if indexes.contains_key(root.as_str()) {
+            continue;
+        }
+        // The nodes being visited, with the successors left to visit
+        let mut visits = vec![(root.as_str(), successors(root))];
+        indexes.insert(root, next_index);
+        low_links.insert(root, next_index);
+        next_index += 1;
+        stack.push(root);
+        on_stack.insert(root);
+
+        while let Some((node, successors_left)) = visits.last_mut() {
+            let node = *node;
+            match successors_left.next() {
+                Some(successor) => {
+                    let successor = successor.as_str();
+                    match indexes.get(successor) {
+                        None => {
+                            indexes.insert(successor, next_index);
+                            low_links.insert(successor, next_index);
+                            next_index += 1;
+                            stack.push(successor);
+                            on_stack.insert(successor);
+                            visits.push((successor, successors(successor)));
+                        }
+                        Some(&index) if on_stack.contains(successor) => {
+                            let low_link = low_links[node].min(index);
+                            low_links.insert(node, low_link);
+                        }
+                        Some(_) => {}
+                    }
+                }
+                None => {
+                    visits.pop();
+                    if let Some((parent, _)) = visits.last() {
+                        let low_link = low_links[*parent].min(low_links[node]);
+                        low_links.insert(parent, low_link);
+                    }
+                    if low_links[node] == indexes[node] {
+                        let mut component = BTreeSet::new();
+                        while let Some(member) = stack.pop() {
+                            on_stack.remove(member);
+                            component.insert(member.to_string());
+                            if member == node {
+                                break;
+                            }
+                        }
+                        components.push(component);
+                    }
+                }
+            }
+        }
+    }
+
+    components.sort();
+    components
+}
+
+/// The shortest path from the first node of the component back to itself.
+fn shortest_cycle(
+    graph: &BTreeMap<String, BTreeMap<String, Edge>>,
+    component: &BTreeSet<String>,
+) -> Option<Cycle> {
+    let start = component.first()?;
+    // The node each node is reached from, and the edge between them
+    let mut previous: HashMap<&str, (&str, &Edge)> = HashMap::new();
+    let mut queue = VecDeque::from([start.as_str()]);
+
+    while let Some(node) = queue.pop_front() {
+        for (successor, edge) in graph.get(node).into_iter().flatten() {
+            if successor == start {
+                let mut edges = vec![edge.clone()];
+                let mut current = node;
+                while let Some((node, edge)) = previous.get(current) {
+                    edges.push((*edge).clone());
+                    current = node;
+                }
+                edges.reverse();
+                return Some(Cycle { edges });
+            }
+            if component.contains(successor) && !previous.contains_key(successor.as_str()) {
+                previous.insert(successor, (node, edge));
+                queue.push_back(successor);
+            }
+        }
+    }
+
+    None
+}
+
+/// Cycles are found in the whole index, each is reported once, in the file
+/// of its first reference.
+struct Cycles<'a> {
+    rule: &'a Rule,
+    file: &'a File<'a>,
+}
+
+impl<'a> Visitor<'a> for Cycles<'a> {
+    fn finish(self: Box<Self>) -> Vec<Violation> {
+        let path = self.file.path.display().to_string();
+        let noun = match self.rule.settings.granularity {
+            Granularity::Class => "Classes",
+            Granularity::Namespace => "Namespaces",
+        };
+
+        self.rule
+            .cycles()
+            .iter()
+            .filter(|cycle| cycle.edges[0].path == path)
+            .map(|cycle| {
+                let mut names = cycle
+                    .edges
+                    .iter()
+                    .map(|edge| display_name(&edge.from))
+                    .collect::<Vec<_>>();
+                names.push(display_name(&cycle.edges[0].from));
+                let suggestion = format!(
+                    "{} depend on each other: {}. Break the cycle by extracting an interface or moving the shared code.",
+                    noun,
+                    names.join(" → ")
+                );
+                let related_locations = cycle
+                    .edges
+                    .iter()
+                    .map(|edge| RelatedLocation {
+                        path: edge.path.clone(),
+                        message: format!(
+                            "{} depends on {}",
+                            display_name(&edge.from),
+                            display_name(&edge.to)
+                        ),
+                        start_line: edge.reference.start_line,
+                        start_column: edge.reference.start_column,
+                        end_line: edge.reference.end_line,
+                        end_column: edge.reference.end_column,
+                    })
+                    .collect();
+                let reference = &cycle.edges[0].reference;
+
+                self.rule
+                    .new_violation(
+                        self.file,
+                        suggestion,
+                        Span::dummy(reference.start, reference.end),
+                    )
+                    .with_related_locations(related_locations)
+            })
+            .collect()
+    }
+}
+
+fn display_name(name: &str) -> &str {
+    match name {
+        "" => "\\",
+        name => name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use crate::rules::tests::{analyze_file_for_rule, analyze_file_for_rule_with_settings};
+
+    use super::*;
+
+    #[test]
+    fn class_cycle() {
+        let violations = analyze_file_for_rule("e33/cycles.php", CODE);
+
+        assert_eq!(violations.len(), 1, "{:?}", violations);
+        assert_eq!(
+            violations[0].suggestion,
+            "Classes depend on each other: App\\Billing\\Invoice → App\\Sales\\Order → App\\Billing\\Invoice. Break the cycle by extracting an interface or moving the shared code."
+        );
+        assert_eq!(violations[0].start_line, 26);
+        let related = &violations[0].related_locations;
+        assert_eq!(related.len(), 2);
+        assert_eq!(related[0].start_line, 26);
+        assert_eq!(related[1].start_line, 6);
+        assert_eq!(
+            related[1].message,
+            "App\\Sales\\Order depends on App\\Billing\\Invoice"
+        );
+    }
+
+    #[test]
+    fn namespace_cycle() {
+        let violations = analyze_file_for_rule_with_settings(
+            "e33/cycles.php",
+            CODE,
+            json!({"granularity": "namespace"}),
+        );
+
+        assert_eq!(violations.len(), 1, "{:?}", violations);
+        assert!(violations[0].suggestion.starts_with(
+            "Namespaces depend on each other: App\\Billing → App\\Sales → App\\Billing."
+        ));
+    }
+
+    #[test]
+    fn no_cycles() {
+        let violations = analyze_file_for_rule("e33/no_cycles.php", CODE);
+
+        assert!(violations.is_empty(), "{:?}", violations);
+    }
+
+    #[test]
+    fn components() {
+        let edges = |targets: &[&str]| {
+            targets
+                .iter()
+                .map(|target| (target.to_string(), ()))
+                .collect::<BTreeMap<_, _>>()
+        };
+        let graph = BTreeMap::from([
+            ("a".to_string(), edges(&["b"])),
+            ("b".to_string(), edges(&["c", "d"])),
+            ("c".to_string(), edges(&["a"])),
+            ("d".to_string(), edges(&["e"])),
+            ("e".to_string(), edges(&["d"])),
+            ("f".to_string(), edges(&["a"])),
+        ]);
+
+        let components = strongly_connected_components(&graph)
+            .into_iter()
+            .filter(|component| component.len() > 1)
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            components,
+            vec![
+                BTreeSet::from(["a".to_string(), "b".to_string(), "c".to_string()]),
+                BTreeSet::from(["d".to_string(), "e".to_string()]),
+            ]
+        );
+    }
+}
@@ -1,8 +1,12 @@
-use mago_span::HasSpan;
-use mago_syntax::ast::{ClassLikeMember, Statement};
+use mago_span::{HasSpan, Span};
+use mago_syntax::ast::{
+    AnonymousClass, ClassConstantAccess, ClassLikeConstantSelector, ClassLikeMember, Expression,
+    Identifier, Modifier, Sequence, Statement,
+};
+use mago_syntax::walker::Walker;
 
 use crate::file::File;
-use crate::results::Violation;
+use crate::results::{Edit, Violation};
 use crate::rules::Rule as RuleTrait;
 
 pub struct Rule {}
@@ -38,15 +42,21 @@ impl crate::rules::Rule for Rule {
                 }
             }
             Statement::Class(c) => {
-                collect_member_constants(self, file, &c.members, &mut violations)
+                let owner = (c.name.value, &c.members);
+                collect_member_constants(self, file, owner, &mut violations)
             }
             Statement::Interface(i) => {
-                collect_member_constants(self, file, &i.members, &mut violations)
+                let owner = (i.name.value, &i.members);
+                collect_member_constants(self, file, owner, &mut violations)
             }
             Statement::Trait(t) => {
-                collect_member_constants(self, file, &t.members, &mut violations)
+                let owner = (t.name.value, &t.members);
+                collect_member_constants(self, file, owner, &mut violations)
+            }
+            Statement::Enum(e) => {
+                let owner = (e.name.value, &e.members);
+                collect_member_constants(self, file, owner, &mut violations)
             }
-            Statement::Enum(e) => collect_member_constants(self, file, &e.members, &mut violations),
             _ => {}
         }
 
@@ -57,31 +67,139 @@ impl crate::rules::Rule for Rule {
 fn collect_member_constants(
     rule: &Rule,
     file: &File<'_>,
-    members: &mago_syntax::ast::Sequence<'_, ClassLikeMember<'_>>,
+    owner: (&[u8], &Sequence<'_, ClassLikeMember<'_>>),
     violations: &mut Vec<Violation>,
 ) {
+    let (_, members) = owner;
     for member in members.iter() {
         if let ClassLikeMember::Constant(const_member) = member {
+            let private = const_member
+                .modifiers
+                .iter()
+                .any(|m| matches!(m, Modifier::Private(_)));
             for item in const_member.items.iter() {
                 let name = item.name.value;
                 let name_str = std::str::from_utf8(name).unwrap_or_default();
                 if name_str != name_str.to_uppercase() {
                     let suggestion = format!("The constant {} should be uppercase.", name_str);
-                    violations.push(RuleTrait::new_violation(
-                        rule,
-                        file,
-                        suggestion,
-                        item.name.span(),
-                    ));
+                    let mut violation =
+                        RuleTrait::new_violation(rule, file, suggestion, item.name.span());
+                    // Only private constants can't be referenced from other files
+                    if private {
+                        violation =
+                            violation.with_edits(rename_edits(owner, item.name.span(), name_str));
+                    }
+                    violations.push(violation);
                 }
             }
         }
     }
 }
 
+/// Renames the constant declaration and its references by `self::`,
+/// `static::` or the class-like name within the class-like. No edits are
+/// returned when a reference is ambiguous.
+fn rename_edits(
+    owner: (&[u8], &Sequence<'_, ClassLikeMember<'_>>),
+    name: Span,
+    name_str: &str,
+) -> Vec<Edit> {
+    let (owner_name, members) = owner;
+    let mut references = References {
+        owner: owner_name,
+        name: name_str.as_bytes(),
+        spans: vec![],
+        anonymous_classes: 0,
+        ambiguous: false,
+    };
+    for member in members.iter() {
+        ReferenceWalker.walk_class_like_member(member, &mut references);
+    }
+    if references.ambiguous {
+        return vec![];
+    }
+
+    let uppercase = name_str.to_uppercase();
+    let mut edits = vec![Edit::replace(name, &uppercase)];
+    edits.extend(
+        references
+            .spans
+            .into_iter()
+            .map(|span| Edit::replace(span, &uppercase)),
+    );
+
+    edits
+}
+
+/// References to a constant found in the members of its class-like.
+struct References<'n> {
+    owner: &'n [u8],
+    name: &'n [u8],
+    spans: Vec<Span>,
+    /// Depth of the anonymous classes being walked, in which `self::` and
+    /// `static::` refer to the anonymous class.
+    anonymous_classes: usize,
+    ambiguous: bool,
+}
+
+struct ReferenceWalker;
+
+impl<'a> Walker<'a, 'a, References<'_>> for ReferenceWalker {
+    fn walk_in_anonymous_class(
+        &self,
+        _class: &'a AnonymousClass<'a>,
+        references: &mut References<'_>,
+    ) {
+        references.anonymous_classes += 1;
+    }
+
+    fn walk_out_anonymous_class(
+        &self,
+        _class: &'a AnonymousClass<'a>,
+        references: &mut References<'_>,
+    ) {
+        references.anonymous_classes -= 1;
+    }
+
+    fn walk_in_class_constant_access(
+        &self,
+        access: &'a ClassConstantAccess<'a>,
+        references: &mut References<'_>,
+    ) {
+        let constant = match &access.constant {
+            ClassLikeConstantSelector::Identifier(constant)
+                if constant.value == references.name =>
+            {
+                constant
+            }
+            _ => return,
+        };
+
+        match access.class {
+            Expression::Self_(_) | Expression::Static(_) if references.anonymous_classes == 0 => {
+                references.spans.push(constant.span)
+            }
+            Expression::Self_(_) | Expression::Static(_) => references.ambiguous = true,
+            Expression::Identifier(Identifier::Local(class))
+                if class.value.eq_ignore_ascii_case(references.owner) =>
+            {
+                references.spans.push(constant.span)
+            }
+            // A qualified name may refer to another class-like, so it isn't
+            // safe to rename
+            Expression::Identifier(class)
+                if class.last_segment().eq_ignore_ascii_case(references.owner) =>
+            {
+                references.ambiguous = true
+            }
+            _ => {}
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::rules::tests::analyze_file_for_rule;
+    use crate::rules::tests::{analyze_file_for_rule, fix_file_for_rule};
 
     use super::*;
 
@@ -96,6 +214,19 @@ mod tests {
         );
     }
 
+    #[test]
+    fn fix_renames_private_constant_and_references() {
+        let fixed = fix_file_for_rule("e4/private_constant_references.php", CODE);
+
+        assert!(fixed.contains("private const MAXITEMS = 10;"));
+        assert!(fixed
+            .contains("self::MAXITEMS + static::MAXITEMS + PrivateConstantReferences::MAXITEMS"));
+        assert!(fixed.contains("// self::maxItems is the limit"));
+        assert!(fixed.contains("'self::maxItems: ' . (SELF :: MAXITEMS + Static::MAXITEMS)"));
+        assert!(fixed.contains("public const defaultName = 'name';"));
+        assert!(fixed.contains("return self::defaultName;"));
+    }
+
     #[test]
     fn uppercase_constant() {
         let violations = analyze_file_for_rule("e4/uppercase_constant.php", CODE);
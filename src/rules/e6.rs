@@ -2,7 +2,7 @@ use mago_span::HasSpan;
 use mago_syntax::ast::{ClassLikeMember, Modifier, Property, Statement};
 
 use crate::file::File;
-use crate::results::Violation;
+use crate::results::{Edit, Violation};
 
 pub(crate) static CODE: &str = "E0006";
 static DESCRIPTION: &str = "Property modifiers";
@@ -37,7 +37,10 @@ impl crate::rules::Rule for Rule {
 
                         let suggestion =
                             format!("The variables {} have no modifier.", names.join(", "));
-                        violations.push(self.new_violation(file, suggestion, property.span()));
+                        violations.push(
+                            self.new_violation(file, suggestion, property.span())
+                                .with_edits(Self::visibility_edit(property).into_iter().collect()),
+                        );
                     }
                 }
             }
@@ -48,6 +51,22 @@ impl crate::rules::Rule for Rule {
 }
 
 impl Rule {
+    /// `var` becomes `public`, otherwise `public` is added before the
+    /// modifiers or the type.
+    fn visibility_edit(property: &Property<'_>) -> Option<Edit> {
+        if let Some(var) = property.var() {
+            return Some(Edit::replace(var.span, "public"));
+        }
+
+        let start = property
+            .modifiers()
+            .first()
+            .map(|m| m.span())
+            .or_else(|| property.hint().map(|h| h.span()))
+            .or_else(|| property.variables().first().map(|v| v.span))?;
+        Some(Edit::insert(start.start.offset, "public "))
+    }
+
     fn property_without_visibility(property: &Property<'_>) -> bool {
         !property.modifiers().iter().any(|m| {
             matches!(
@@ -60,7 +79,7 @@ impl Rule {
 
 #[cfg(test)]
 mod tests {
-    use crate::rules::tests::analyze_file_for_rule;
+    use crate::rules::tests::{analyze_file_for_rule, fix_file_for_rule};
 
     use super::*;
 
@@ -75,6 +94,13 @@ mod tests {
         );
     }
 
+    #[test]
+    fn fix_adds_public_modifier() {
+        let fixed = fix_file_for_rule("e6/no_var_modifiers.php", CODE);
+
+        assert!(fixed.contains("    public string $var = 'test';"));
+    }
+
     #[test]
     fn with_modifiers() {
         let violations = analyze_file_for_rule("e6/with_var_modifiers.php", CODE);
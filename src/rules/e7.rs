@@ -1,10 +1,12 @@
-use mago_span::HasSpan;
-use mago_syntax::ast::{ClassLikeMember, Statement};
+use std::collections::BTreeMap;
+
+use mago_syntax::ast::Statement;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::file::File;
 use crate::results::Violation;
+use crate::rules::callable::{callables, threshold, CallableKind};
 
 pub(crate) static CODE: &str = "E0007";
 static DESCRIPTION: &str = "Method parameters count";
@@ -13,6 +15,8 @@ static DESCRIPTION: &str = "Method parameters count";
 pub struct Settings {
     pub check_constructor: bool,
     pub max_parameters: i32,
+    #[serde(default)]
+    pub max_parameters_by_kind: BTreeMap<CallableKind, i32>,
 }
 
 impl Default for Settings {
@@ -20,6 +24,7 @@ impl Default for Settings {
         Settings {
             check_constructor: false,
             max_parameters: 8,
+            max_parameters_by_kind: BTreeMap::new(),
         }
     }
 }
@@ -52,35 +57,39 @@ impl crate::rules::Rule for Rule {
     fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
         let mut violations = Vec::new();
 
-        if let Statement::Class(class) = statement {
-            for member in class.members.iter() {
-                if let ClassLikeMember::Method(method) = member {
-                    let name = method.name.value;
-                    let parameters_count = method.parameter_list.parameters.len();
-
-                    if name == b"__construct" {
-                        if self.settings.check_constructor
-                            && parameters_count > self.settings.max_parameters as usize
-                        {
-                            let suggestion = format!(
-                                "Constructor has too many parameters. More than {} parameters is considered a too much.",
-                                self.settings.max_parameters
-                            );
-                            violations.push(self.new_violation(file, suggestion, method.span()));
-                        }
-                    } else if parameters_count > self.settings.max_parameters as usize {
-                        let suggestion = format!(
-                            "Method {} has too many parameters. More than {} parameters is considered a too much.",
-                            String::from_utf8_lossy(name), self.settings.max_parameters
-                        );
-                        violations.push(self.new_violation(file, suggestion, method.span()));
-                    }
+        for callable in callables(statement) {
+            let max_parameters = threshold(
+                self.settings.max_parameters,
+                &self.settings.max_parameters_by_kind,
+                callable.kind,
+            );
+            if callable.parameters <= max_parameters as usize {
+                continue;
+            }
+
+            if callable.kind == CallableKind::Method && callable.name == "__construct" {
+                if self.settings.check_constructor {
+                    let suggestion = format!(
+                        "Constructor has too many parameters. More than {} parameters is considered a too much.",
+                        max_parameters
+                    );
+                    violations.push(self.new_violation(file, suggestion, callable.span));
                 }
+            } else {
+                let suggestion = format!(
+                    "{} {} has too many parameters. More than {} parameters is considered a too much.",
+                    callable.kind.title(), callable.name, max_parameters
+                );
+                violations.push(self.new_violation(file, suggestion, callable.span));
             }
         }
 
         violations
     }
+
+    fn validate_top_level_statements(&self) -> bool {
+        true
+    }
 }
 
 #[cfg(test)]
@@ -114,4 +123,18 @@ mod tests {
 
         assert!(violations.len().eq(&0));
     }
+
+    #[test]
+    fn function_and_closure_max_params() {
+        let violations = analyze_file_for_rule("e7/function_max_params.php", CODE);
+
+        let suggestions: Vec<&str> = violations.iter().map(|v| v.suggestion.as_str()).collect();
+        assert_eq!(
+            suggestions,
+            vec![
+                "Function build has too many parameters. More than 8 parameters is considered a too much.",
+                "Closure {closure} has too many parameters. More than 8 parameters is considered a too much.",
+            ]
+        );
+    }
 }
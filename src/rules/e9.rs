@@ -1,13 +1,14 @@
-use mago_span::HasSpan;
+use std::collections::BTreeMap;
+
 use mago_syntax::ast::{
-    ClassLikeMember, ForBody, ForeachBody, IfBody, MethodBody, Sequence, Statement, SwitchBody,
-    SwitchCase, WhileBody,
+    ForBody, ForeachBody, IfBody, Sequence, Statement, SwitchBody, SwitchCase, WhileBody,
 };
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::file::File;
-use crate::results::Violation;
+use crate::results::{Severity, Violation};
+use crate::rules::callable::{callables, threshold, CallableKind};
 
 pub(crate) static CODE: &str = "E0009";
 static DESCRIPTION: &str = "Cyclomatic complexity";
@@ -15,11 +16,16 @@ static DESCRIPTION: &str = "Cyclomatic complexity";
 #[derive(Deserialize, Serialize)]
 pub struct Settings {
     pub max_complexity: i64,
+    #[serde(default)]
+    pub max_complexity_by_kind: BTreeMap<CallableKind, i64>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
-        Settings { max_complexity: 10 }
+        Settings {
+            max_complexity: 10,
+            max_complexity_by_kind: BTreeMap::new(),
+        }
     }
 }
 
@@ -37,6 +43,10 @@ impl crate::rules::Rule for Rule {
         String::from(DESCRIPTION)
     }
 
+    fn severity(&self) -> Severity {
+        Severity::Major
+    }
+
     fn do_validate(&self, _file: &File<'_>) -> bool {
         true
     }
@@ -51,27 +61,33 @@ impl crate::rules::Rule for Rule {
     fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation> {
         let mut violations = Vec::new();
 
-        if let Statement::Class(class) = statement {
-            for member in class.members.iter() {
-                if let ClassLikeMember::Method(method) = member {
-                    if let MethodBody::Concrete(block) = &method.body {
-                        // Base complexity is 1 for the method itself
-                        let complexity = 1 + calculate_complexity(&block.statements);
-
-                        if complexity > self.settings.max_complexity {
-                            let suggestion = format!(
-                                "The body of {} method has {} complexity. Make it easier to understand.",
-                                String::from_utf8_lossy(method.name.value),
-                                complexity,
-                            );
-                            violations.push(self.new_violation(file, suggestion, method.span()));
-                        }
-                    }
+        for callable in callables(statement) {
+            if let Some(statements) = callable.statements() {
+                // Base complexity is 1 for the callable itself
+                let complexity = 1 + calculate_complexity(statements);
+                let max_complexity = threshold(
+                    self.settings.max_complexity,
+                    &self.settings.max_complexity_by_kind,
+                    callable.kind,
+                );
+
+                if complexity > max_complexity {
+                    let suggestion = format!(
+                        "The body of {} {} has {} complexity. Make it easier to understand.",
+                        callable.name,
+                        callable.kind.noun(),
+                        complexity,
+                    );
+                    violations.push(self.new_violation(file, suggestion, callable.span));
                 }
             }
         }
         violations
     }
+
+    fn validate_top_level_statements(&self) -> bool {
+        true
+    }
 }
 
 pub(crate) fn calculate_complexity(statements: &Sequence<'_, Statement<'_>>) -> i64 {
@@ -191,7 +207,7 @@ fn calculate_statement_complexity(statement: &Statement<'_>) -> i64 {
 
 #[cfg(test)]
 mod tests {
-    use crate::rules::tests::analyze_file_for_rule;
+    use crate::rules::tests::{analyze_file_for_rule, analyze_file_for_rule_with_settings};
 
     use super::*;
 
@@ -213,4 +229,27 @@ mod tests {
 
         assert!(violations.len().eq(&0));
     }
+
+    #[test]
+    fn complex_closure() {
+        let violations = analyze_file_for_rule("e9/complex_closure.php", CODE);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(
+            violations.first().unwrap().suggestion,
+            "The body of {closure} closure has 11 complexity. Make it easier to understand."
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn complex_closure_within_kind_threshold() {
+        let violations = analyze_file_for_rule_with_settings(
+            "e9/complex_closure.php",
+            CODE,
+            serde_json::json!({"max_complexity": 10, "max_complexity_by_kind": {"closure": 15}}),
+        );
+
+        assert!(violations.is_empty());
+    }
 }
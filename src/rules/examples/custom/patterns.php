@@ -0,0 +1,27 @@
+<?php
+
+namespace App\Controller;
+
+class ProductController extends AbstractController
+{
+    public function show(int $id): Response
+    {
+        $repository = $this->getContainer()->get('product.repository');
+        $product = $repository->find($id);
+        dd($product);
+        \dd($product, $id);
+
+        $logger = $this->getContainer()->get(LoggerInterface::class);
+        $items = array_map(fn ($item) => dd($item), $product->items);
+        $this->getContainer()->has('mailer');
+
+        return new Response(DD($product));
+    }
+
+    public function compare(int $a, int $b): bool
+    {
+        echo $a === $a;
+
+        return $a === $b;
+    }
+}
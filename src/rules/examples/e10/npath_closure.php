@@ -0,0 +1,14 @@
+<?php
+
+namespace Test\e10;
+
+$filter = function ($a, $b) {
+    if ($a) {
+    }
+
+    if ($b) {
+    }
+
+    if ($a === $b) {
+    }
+};
@@ -0,0 +1,13 @@
+<?php
+
+class E11Expressions {
+  public function read(string $path): string {
+    if (!@file_exists($path)) {
+      return '';
+    }
+
+    $content = @file_get_contents($path);
+
+    return trim(@iconv('UTF-8', 'ASCII//TRANSLIT', $content));
+  }
+}
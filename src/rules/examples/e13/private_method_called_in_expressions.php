@@ -0,0 +1,32 @@
+<?php
+
+namespace DeadCode {
+
+  class Report {
+
+    public function render(array $rows): string {
+      $lines = array_map(fn($row) => $this->formatRow($row), $rows);
+
+      return implode("\n", $lines) . self::footer(count($rows));
+    }
+
+    public function total(array $rows): int {
+      return match (true) {
+        empty($rows) => 0,
+        default => $this->sum($rows),
+      };
+    }
+
+    private function formatRow(array $row): string {
+      return implode(', ', $row);
+    }
+
+    private static function footer(int $count): string {
+      return "{$count} rows";
+    }
+
+    private function sum(array $rows): int {
+      return count($rows);
+    }
+  }
+}
@@ -0,0 +1,30 @@
+<?php
+
+// Invalid: Chains nested in blocks, closures and arguments are checked too
+class Order
+{
+    public function getCustomer(): Customer
+    {
+        return new Customer();
+    }
+
+    public function process(): void
+    {
+        try {
+            // Violation: within a try block
+            $name = $this->getCustomer()->getName();
+        } catch (\Exception $e) {
+        }
+
+        // Violation: within an arrow function passed as an argument
+        array_map(fn (Order $order) => $order->getCustomer()->getName(), []);
+    }
+}
+
+class Customer
+{
+    public function getName(): string
+    {
+        return 'Alice';
+    }
+}
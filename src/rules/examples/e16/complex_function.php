@@ -0,0 +1,19 @@
+<?php
+
+function veryComplex($a, $b, $c) {
+    if ($a) {
+        if ($b) {
+            while ($c) {
+                if ($a && $b) {
+                    echo "foo";
+                }
+            }
+        } else if ($c) {
+            echo "bar";
+        } else {
+            echo "baz";
+        }
+    }
+
+    return $a ? $b : $c;
+}
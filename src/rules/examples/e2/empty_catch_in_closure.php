@@ -0,0 +1,19 @@
+<?php
+
+namespace Test\e2;
+
+class Listener {
+    public function register(array $handlers) {
+        return array_map(function ($handler) {
+            try {
+                $handler();
+            } catch(Exception $e) {}
+        }, $handlers);
+    }
+}
+
+function retry(callable $callback) {
+    try {
+        $callback();
+    } catch(Exception $e) {}
+}
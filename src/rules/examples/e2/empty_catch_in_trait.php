@@ -0,0 +1,11 @@
+<?php
+
+namespace Test\e2;
+
+trait Retry {
+    public function run() {
+        try {
+            $this->attempt();
+        } catch(Exception $e) {}
+    }
+}
@@ -0,0 +1,12 @@
+<?php
+
+namespace Test\e20\Vendor {
+    class User {}
+}
+
+namespace Test\e20\App {
+    use Test\e20\Vendor\User as BaseUser;
+
+    class User extends BaseUser {}
+    class Admin extends User {}
+}
@@ -0,0 +1,25 @@
+<?php
+
+namespace Test\e21\Shop {
+    class Model {}
+    class Product extends Model {}
+    class Order extends Model {}
+    class Invoice extends Model {}
+    class Customer extends Model {}
+    class Cart extends Model {}
+    class Payment extends Model {}
+    class Shipment extends Model {}
+    class Review extends Model {}
+}
+
+namespace Test\e21\Blog {
+    class Model {}
+    class Post extends Model {}
+    class Comment extends Model {}
+    class Author extends Model {}
+    class Tag extends Model {}
+    class Category extends Model {}
+    class Page extends Model {}
+    class Media extends Model {}
+    class Menu extends Model {}
+}
@@ -0,0 +1,43 @@
+<?php
+
+namespace Test\e24;
+
+class Temperature
+{
+    public float $celsius {
+        set(float $value) {
+            $value = $value + 1;
+            $value = $value + 2;
+            $value = $value + 3;
+            $value = $value + 4;
+            $value = $value + 5;
+            $value = $value + 6;
+            $value = $value + 7;
+            $value = $value + 8;
+            $value = $value + 9;
+            $value = $value + 10;
+            $value = $value + 11;
+            $value = $value + 12;
+            $value = $value + 13;
+            $value = $value + 14;
+            $value = $value + 15;
+            $value = $value + 16;
+            $value = $value + 17;
+            $value = $value + 18;
+            $value = $value + 19;
+            $value = $value + 20;
+            $value = $value + 21;
+            $value = $value + 22;
+            $value = $value + 23;
+            $value = $value + 24;
+            $value = $value + 25;
+            $value = $value + 26;
+            $value = $value + 27;
+            $value = $value + 28;
+            $value = $value + 29;
+            $value = $value + 30;
+            $value = $value + 31;
+            $this->celsius = $value;
+        }
+    }
+}
@@ -0,0 +1,12 @@
+<?php
+
+namespace App;
+
+function denseFunction($a, $b, $c): int
+{
+    if ($a) { if ($b) { return 1; } else { if ($c) { return 2; } } }
+    if ($a && $b || $c) { return 3; }
+    if (!$a) { return 4; }
+
+    return 0;
+}
@@ -0,0 +1,16 @@
+<?php
+
+// phanalist-ignore-file E0003
+
+namespace Test\e31;
+
+class IgnoreFile
+{
+    function first()
+    {
+    }
+
+    function second()
+    {
+    }
+}
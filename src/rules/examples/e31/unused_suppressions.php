@@ -0,0 +1,13 @@
+<?php
+
+namespace Test\e31;
+
+class UnusedSuppressions
+{
+    // phanalist-ignore-next-line E0011
+    public function fetch(): int
+    {
+        /** @phanalist-ignore */
+        return 1;
+    }
+}
@@ -0,0 +1,21 @@
+<?php
+
+namespace Test\e31;
+
+class UsedSuppressions
+{
+    /**
+     * @phanalist-ignore E0003 kept for BC
+     */
+    function legacy()
+    {
+        // phanalist-ignore-next-line E0011
+        @$this->fetch();
+    }
+
+    public function fetch(): int
+    {
+        // phanalist-ignore-next-line
+        return @intdiv(1, 0);
+    }
+}
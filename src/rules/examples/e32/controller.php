@@ -0,0 +1,14 @@
+<?php
+
+namespace App\Http;
+
+use App\Application\PlaceOrder;
+
+class OrderController
+{
+    public function __invoke(PlaceOrder $placeOrder): void
+    {
+        $order = new \App\Domain\Order();
+        $placeOrder->handle(new Request());
+    }
+}
@@ -0,0 +1,24 @@
+<?php
+
+namespace App\Domain;
+
+use App\Infrastructure\Database;
+
+class Order implements \App\Infrastructure\Persistable
+{
+    private Money $total;
+
+    public function save(Database $database): \DateTimeImmutable
+    {
+        $connection = new \App\Infrastructure\Connection();
+        $logger = function () {
+            return \App\Infrastructure\Logger::get();
+        };
+        if ($database instanceof Database) {
+            echo \App\Infrastructure\Config::VERSION;
+        }
+        $handler = new class extends \App\Infrastructure\Handler {};
+
+        return new \DateTimeImmutable();
+    }
+}
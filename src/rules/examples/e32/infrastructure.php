@@ -0,0 +1,14 @@
+<?php
+
+namespace App\Infrastructure;
+
+use App\Domain\Order;
+
+class OrderRepository
+{
+    public function save(Order $order): void
+    {
+        $connection = new Connection();
+        $connection->insert(\App\Domain\Money::class);
+    }
+}
@@ -0,0 +1,38 @@
+<?php
+
+namespace App\Sales {
+    class Order
+    {
+        public function bill(): \App\Billing\Invoice
+        {
+            return new \App\Billing\Invoice($this);
+        }
+    }
+
+    class Customer
+    {
+        public function orders(): Order
+        {
+            return new Order();
+        }
+    }
+}
+
+namespace App\Billing {
+    use App\Sales\Order;
+
+    class Invoice
+    {
+        public function __construct(private Order $order)
+        {
+        }
+    }
+
+    class Payment
+    {
+        public function invoice(): Invoice
+        {
+            return new Invoice(new \App\Sales\Order());
+        }
+    }
+}
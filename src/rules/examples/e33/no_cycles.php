@@ -0,0 +1,26 @@
+<?php
+
+namespace App\Sales;
+
+use App\Billing\Invoice;
+
+class Order
+{
+    public function bill(): Invoice
+    {
+        return new Invoice($this);
+    }
+
+    public static function create(): self
+    {
+        return new Order();
+    }
+}
+
+class Customer
+{
+    public function orders(): Order
+    {
+        return Order::create();
+    }
+}
@@ -0,0 +1,21 @@
+<?php
+
+namespace Test\e4;
+
+class PrivateConstantReferences {
+    private const maxItems = 10;
+    public const defaultName = 'name';
+
+    public function limit(): int {
+        return self::maxItems + static::maxItems + PrivateConstantReferences::maxItems;
+    }
+
+    public function describe(): string {
+        // self::maxItems is the limit
+        return 'self::maxItems: ' . (SELF :: maxItems + Static::maxItems);
+    }
+
+    public function name(): string {
+        return self::defaultName;
+    }
+}
@@ -0,0 +1,10 @@
+<?php
+
+namespace Test\e7;
+
+function build($a, $b, $c, $d, $e, $f, $g, $h, $i)
+{
+}
+
+$handler = function ($a, $b, $c, $d, $e, $f, $g, $h, $i) {
+};
@@ -0,0 +1,29 @@
+<?php
+
+namespace Test\e9;
+
+$check = function (): bool {
+    if (1 === 1) {
+        if (2 === 2) {
+            if (3 === 3) {
+                if (4 === 4) {
+                    if (5 === 5) {
+                        if (6 === 6) {
+                            if (7 === 7) {
+                                if (8 === 8) {
+                                    if (9 === 9) {
+                                        if (10 === 10) {
+                                            return true;
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    return false;
+};
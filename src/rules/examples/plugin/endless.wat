@@ -0,0 +1,20 @@
+;; A plugin whose validation never returns, to check that it runs out of
+;; fuel instead of hanging the analysis.
+(module
+  (memory (export "memory") 1)
+
+  (data (i32.const 0) "P0002")
+
+  (func (export "alloc") (param $len i32) (result i32)
+    (i32.const 1024))
+
+  (func (export "get_code") (result i64)
+    (i64.const 5))
+
+  (func (export "index_file") (param $ptr i32) (param $len i32) (result i64)
+    (i64.const 5))
+
+  (func (export "validate") (param $ptr i32) (param $len i32) (result i64)
+    (loop $forever
+      (br $forever))
+    (i64.const 0)))
@@ -0,0 +1,47 @@
+;; An example plugin, flagging the first 5 bytes of every file, normally
+;; its opening tag. Index entries echo the settings of the rule.
+(module
+  (memory (export "memory") 1)
+  (global $heap (mut i32) (i32.const 1024))
+  (global $config_ptr (mut i32) (i32.const 0))
+  (global $config_len (mut i32) (i32.const 0))
+
+  (data (i32.const 0) "P0001")
+  (data (i32.const 16) "Flags the opening tag")
+  (data (i32.const 64) "[{\"message\":\"Opening tag\",\"start\":0,\"end\":5}]")
+
+  ;; A bump allocator, growing the memory as needed and never freeing
+  (func (export "alloc") (param $len i32) (result i32)
+    (local $ptr i32)
+    (local.set $ptr (global.get $heap))
+    (global.set $heap (i32.add (global.get $heap) (local.get $len)))
+    (if (i32.gt_u (global.get $heap) (i32.mul (memory.size) (i32.const 65536)))
+      (then
+        (drop (memory.grow
+          (i32.add
+            (i32.div_u
+              (i32.sub (global.get $heap) (i32.mul (memory.size) (i32.const 65536)))
+              (i32.const 65536))
+            (i32.const 1))))))
+    (local.get $ptr))
+
+  (func $pack (param $ptr i32) (param $len i32) (result i64)
+    (i64.or
+      (i64.shl (i64.extend_i32_u (local.get $ptr)) (i64.const 32))
+      (i64.extend_i32_u (local.get $len))))
+
+  (func (export "get_code") (result i64)
+    (call $pack (i32.const 0) (i32.const 5)))
+
+  (func (export "description") (result i64)
+    (call $pack (i32.const 16) (i32.const 21)))
+
+  (func (export "set_config") (param $ptr i32) (param $len i32)
+    (global.set $config_ptr (local.get $ptr))
+    (global.set $config_len (local.get $len)))
+
+  (func (export "index_file") (param $ptr i32) (param $len i32) (result i64)
+    (call $pack (global.get $config_ptr) (global.get $config_len)))
+
+  (func (export "validate") (param $ptr i32) (param $len i32) (result i64)
+    (call $pack (i32.const 64) (i32.const 45))))
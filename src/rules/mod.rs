@@ -1,5 +1,5 @@
 use colored::Colorize;
-use std::collections::HashMap;
+use std::collections::BTreeMap;
 use std::error::Error;
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -10,7 +10,11 @@ use serde_json::Value;
 
 use crate::config::Config;
 use crate::file::File;
-use crate::results::Violation;
+use crate::results::{fingerprint, Severity, Violation};
+use crate::rules::visitor::Visitor;
+pub mod callable;
+pub mod custom;
+pub mod dependencies;
 pub mod e0;
 pub mod e1;
 pub mod e10;
@@ -31,6 +35,7 @@ pub mod e6;
 pub mod e7;
 pub mod e8;
 pub mod e9;
+pub mod plugin;
 pub mod e20;
 pub mod e21;
 pub mod e22;
@@ -42,11 +47,35 @@ pub mod e27;
 pub mod e28;
 pub mod e29;
 pub mod e30;
+pub mod e31;
+pub mod e32;
+pub mod e33;
+pub mod visitor;
+/// Rules are shared by all the analysis threads, so any state collected in
+/// `index_file` has to be synchronized.
+pub trait Rule: Send + Sync {
+    /// Optional hook for cross-file type resolution or indexing: the part of
+    /// the cross-file index contributed by a single file. Called once for
+    /// every file before the main validation pass. Entries are stored in the
+    /// analysis cache, so the index stays complete without parsing unchanged
+    /// files.
+    fn index_entry(&self, _file: &File<'_>) -> Option<Value> {
+        None
+    }
+
+    /// Merge an entry returned by `index_entry` (possibly read from the
+    /// analysis cache) into the cross-file index.
+    fn load_index_entry(&self, _entry: &Value) {}
 
-pub trait Rule {
-    /// Optional hook for cross-file type resolution or indexing.
-    /// Called once for every file before main validation pass.
-    fn index_file(&self, _file: &File<'_>) {}
+    /// Remove an entry merged by `load_index_entry` from the cross-file
+    /// index, when the file it was read from changed.
+    fn unload_index_entry(&self, _entry: &Value) {}
+
+    /// Whether the rule looks up `File::symbols`, so its violations are
+    /// validated again when the project's symbol table changes.
+    fn uses_symbols(&self) -> bool {
+        false
+    }
 
     /// Would be a good idea to have default implementation which extracts the code from struct name
     /// Haven't found a way to implement it
@@ -84,6 +113,12 @@ pub trait Rule {
         None
     }
 
+    /// Severity of the violations of the rule, unless the `severity` setting
+    /// of the rule overrides it.
+    fn severity(&self) -> Severity {
+        Severity::Minor
+    }
+
     fn set_config(&mut self, _json: &Value) {}
 
     fn read_config(&mut self, config: &Config) {
@@ -94,7 +129,7 @@ pub trait Rule {
     }
 
     fn output_error(&self, e: Box<dyn Error>) {
-        println!(
+        eprintln!(
             "{}",
             format!(
                 "Unable to parse config for rule #{}, so default values will be used. Parsing error: {}",
@@ -107,10 +142,21 @@ pub trait Rule {
     }
 
     fn do_validate(&self, file: &File<'_>) -> bool {
-        file.get_fully_qualified_name().is_some()
+        !file.declared_symbols.is_empty()
+    }
+
+    /// Called for every statement returned by `flatten_statements_to_validate`,
+    /// unless the rule provides a `visitor`.
+    fn validate(&self, _file: &File<'_>, _statement: &Statement<'_>) -> Vec<Violation> {
+        vec![]
     }
 
-    fn validate(&self, file: &File<'_>, statement: &Statement<'_>) -> Vec<Violation>;
+    /// Visitor finding the violations of the file in the traversal shared by
+    /// the rules providing one, instead of `validate` being called for the
+    /// flattened statements.
+    fn visitor<'a>(&'a self, _file: &'a File<'a>) -> Option<Box<dyn Visitor<'a> + 'a>> {
+        None
+    }
 
     fn new_violation(&self, file: &File<'_>, suggestion: String, span: Span) -> Violation {
         let start_line = file.line_number(span.start.offset);
@@ -124,21 +170,46 @@ pub trait Rule {
             .cloned()
             .unwrap_or_default();
 
+        let code = self.get_code();
+        let symbol = file.enclosing_symbol(span.start.offset);
+        let fingerprint = fingerprint(
+            &code,
+            &file.path.display().to_string(),
+            symbol.as_deref(),
+            &line,
+        );
+
         Violation {
-            rule: self.get_code(),
+            rule: code,
+            severity: self.severity(),
+            symbol,
+            fingerprint,
             line,
             suggestion,
             start_line,
             start_column,
             end_line,
             end_column,
+            edits: vec![],
+            related_locations: vec![],
         }
     }
 
+    /// Whether `validate` is only called for the top-level statements, for the
+    /// rules looking within them at once, such as the ones built on
+    /// `callable::callables`.
+    fn validate_top_level_statements(&self) -> bool {
+        false
+    }
+
     fn flatten_statements_to_validate<'a>(
         &'a self,
         statement: &'a Statement<'a>,
     ) -> Vec<&'a Statement<'a>> {
+        if self.validate_top_level_statements() {
+            return vec![statement];
+        }
+
         let mut flatten_statements: Vec<&Statement<'a>> = Vec::new();
         self.traverse_statements_to_validate(&mut flatten_statements, statement);
         flatten_statements
@@ -312,12 +383,12 @@ pub(crate) fn do_validate_namespace(
     true
 }
 
-fn add_rule(rules: &mut HashMap<String, Box<dyn Rule>>, rule: Box<dyn Rule>) {
+fn add_rule(rules: &mut BTreeMap<String, Box<dyn Rule>>, rule: Box<dyn Rule>) {
     rules.insert(rule.get_code(), rule as Box<dyn Rule>);
 }
 
-pub fn all_rules() -> HashMap<String, Box<dyn Rule>> {
-    let mut rules: HashMap<String, Box<dyn Rule>> = HashMap::new();
+pub fn all_rules() -> BTreeMap<String, Box<dyn Rule>> {
+    let mut rules: BTreeMap<String, Box<dyn Rule>> = BTreeMap::new();
 
     add_rule(&mut rules, Box::new(e0::Rule {}));
     add_rule(&mut rules, Box::new(e1::Rule {}));
@@ -350,10 +421,33 @@ pub fn all_rules() -> HashMap<String, Box<dyn Rule>> {
     add_rule(&mut rules, Box::default() as Box<e28::Rule>);
     add_rule(&mut rules, Box::default() as Box<e29::Rule>);
     add_rule(&mut rules, Box::default() as Box<e30::Rule>);
+    add_rule(&mut rules, Box::new(e31::Rule {}));
+    add_rule(&mut rules, Box::default() as Box<e32::Rule>);
+    add_rule(&mut rules, Box::default() as Box<e33::Rule>);
 
     rules
 }
 
+/// The rules defined by the configuration, custom rules then plugins, or why
+/// each invalid one can't be used.
+pub fn configured_rules(
+    custom_rules: &[custom::Definition],
+    plugins: &[String],
+) -> Vec<Result<Box<dyn Rule>, String>> {
+    let custom_rules = custom_rules.iter().map(|definition| {
+        custom::Rule::new(definition)
+            .map(|rule| Box::new(rule) as Box<dyn Rule>)
+            .map_err(|e| format!("Unable to use a custom rule: {}", e))
+    });
+    let plugins = plugins.iter().map(|path| {
+        plugin::Rule::load(Path::new(path))
+            .map(|rule| Box::new(rule) as Box<dyn Rule>)
+            .map_err(|e| format!("Unable to load the plugin {}: {}", path, e))
+    });
+
+    custom_rules.chain(plugins).collect()
+}
+
 #[cfg(test)]
 mod tests {
     use bumpalo::Bump;
@@ -364,6 +458,34 @@ mod tests {
     use super::*;
 
     pub(crate) fn analyze_file_for_rule(path: &str, rule_code: &str) -> Vec<Violation> {
+        analyze_file_for_rules(path, &[rule_code])
+    }
+
+    pub(crate) fn analyze_file_for_rules(path: &str, rule_codes: &[&str]) -> Vec<Violation> {
+        let config = Config {
+            enabled_rules: rule_codes.iter().map(|code| code.to_string()).collect(),
+            ..Default::default()
+        };
+
+        analyze_file_with_config(path, &config)
+    }
+
+    /// Analyses the example file with the rule configured by `settings`.
+    pub(crate) fn analyze_file_for_rule_with_settings(
+        path: &str,
+        rule_code: &str,
+        settings: Value,
+    ) -> Vec<Violation> {
+        let mut config = Config {
+            enabled_rules: vec![rule_code.to_string()],
+            ..Default::default()
+        };
+        config.rules.insert(rule_code.to_string(), settings);
+
+        analyze_file_with_config(path, &config)
+    }
+
+    pub(crate) fn analyze_file_with_config(path: &str, config: &Config) -> Vec<Violation> {
         let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
             .join("src")
             .join("rules")
@@ -373,19 +495,26 @@ mod tests {
         let arena = Bump::new();
         let mut file = File::new(&arena, path, content);
 
-        let config = Config {
-            enabled_rules: vec![rule_code.to_string()],
-            ..Default::default()
-        };
-        let analyse = Analyse::new(&config);
+        let analyse = Analyse::new(config);
 
-        for rule in analyse.rules.values() {
-            rule.index_file(&file);
-        }
+        analyse.index_file(&file);
 
         analyse.analyse_file(&mut file, false).0
     }
 
+    /// The content of the example file once the rule's fixes are applied.
+    pub(crate) fn fix_file_for_rule(path: &str, rule_code: &str) -> String {
+        let violations = analyze_file_for_rule(path, rule_code);
+        let content = fs::read_to_string(
+            PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+                .join("src/rules/examples")
+                .join(path),
+        )
+        .unwrap();
+
+        crate::fix::apply_edits(&content, &violations).0
+    }
+
     fn get_ns() -> String {
         "App\\Service\\Search".to_string()
     }
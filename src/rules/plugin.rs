@@ -0,0 +1,421 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Mutex;
+
+use mago_span::Span;
+use mago_syntax::ast::Program;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use wasmi::{Engine, Instance, Linker, Memory, Module, Store, TrapCode, TypedFunc};
+
+use crate::file::{DeclaredSymbol, File};
+use crate::results::Violation;
+use crate::rules::visitor::Visitor;
+use crate::rules::Rule as RuleTrait;
+
+/// A rule compiled to WebAssembly, listed in the `plugins` of the
+/// configuration.
+///
+/// The host and the plugin exchange UTF-8 JSON through the plugin's memory.
+/// The host writes its input to a buffer returned by the plugin's `alloc`,
+/// and the plugin returns its output as `(ptr << 32) | len`, a length of 0
+/// meaning no output. Both buffers are handed to `dealloc`, when exported,
+/// once used.
+///
+/// Exports mirroring the `Rule` trait:
+/// - `memory`, `alloc(len: i32) -> i32` and `get_code() -> i64`
+/// - `validate(ptr: i32, len: i32) -> i64`: the violations of a file, a list
+///   of `{"message": …, "start": …, "end": …}` with byte offsets
+/// - optional `description() -> i64`, `set_config(ptr: i32, len: i32)`,
+///   `index_file(ptr: i32, len: i32) -> i64`, `load_index_entry(ptr: i32,
+///   len: i32)`, `unload_index_entry(ptr: i32, len: i32)` and `dealloc(ptr:
+///   i32, len: i32)`
+///
+/// Files are passed as `FileView`, with their AST serialized.
+///
+/// Every call gets a budget of `FUEL`, so a plugin which doesn't return
+/// fails like the other broken plugins instead of hanging the analysis.
+pub struct Rule {
+    code: String,
+    description: String,
+    instance: Mutex<Plugin>,
+}
+
+/// Fuel of every call into a plugin, roughly the number of WebAssembly
+/// instructions it may execute.
+const FUEL: u64 = 1_000_000_000;
+
+/// A plugin's instance. Calls are serialized, as a WebAssembly instance
+/// can't be shared by the analysis threads.
+struct Plugin {
+    store: Store<()>,
+    instance: Instance,
+    memory: Memory,
+    alloc: TypedFunc<i32, i32>,
+    /// Fuel given to every call, `FUEL` unless lowered by the tests.
+    fuel: u64,
+}
+
+#[derive(Serialize)]
+struct FileView<'a> {
+    path: String,
+    source: String,
+    symbols: &'a [DeclaredSymbol],
+    ast: Option<&'a Program<'a>>,
+}
+
+#[derive(Deserialize)]
+struct PluginViolation {
+    message: String,
+    start: u32,
+    end: u32,
+}
+
+impl Rule {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let wasm = fs::read(path).map_err(|e| e.to_string())?;
+        let mut config = wasmi::Config::default();
+        config.consume_fuel(true);
+        let engine = Engine::new(&config);
+        let module = Module::new(&engine, wasm).map_err(|e| e.to_string())?;
+        let mut store = Store::new(&engine, ());
+        // The start function runs on the same budget as the calls
+        store.set_fuel(FUEL).map_err(|e| e.to_string())?;
+        let instance = Linker::<()>::new(&engine)
+            .instantiate_and_start(&mut store, &module)
+            .map_err(call_error)?;
+        let memory = instance
+            .get_memory(&store, "memory")
+            .ok_or("the plugin doesn't export its memory")?;
+        let alloc = instance
+            .get_typed_func::<i32, i32>(&store, "alloc")
+            .map_err(|e| format!("alloc: {}", e))?;
+
+        let mut plugin = Plugin {
+            store,
+            instance,
+            memory,
+            alloc,
+            fuel: FUEL,
+        };
+        let code = match plugin.call_output("get_code", None)? {
+            Some(code) if !code.is_empty() => code,
+            _ => return Err("the plugin has no code".to_string()),
+        };
+        let description = match plugin.has_export("description") {
+            true => plugin.call_output("description", None)?.unwrap_or_default(),
+            false => String::new(),
+        };
+
+        Ok(Self {
+            code,
+            description,
+            instance: Mutex::new(plugin),
+        })
+    }
+
+    /// Call an export of the plugin, reporting its failures on stderr like
+    /// the invalid configurations, so one broken plugin doesn't stop the
+    /// analysis.
+    fn call(&self, name: &str, input: Option<&str>) -> Option<String> {
+        let mut plugin = self.instance.lock().unwrap();
+        if !plugin.has_export(name) {
+            return None;
+        }
+        match plugin.call_output(name, input) {
+            Ok(output) => output,
+            Err(e) => {
+                eprintln!("Plugin {} failed in {}: {}", self.code, name, e);
+                None
+            }
+        }
+    }
+
+    fn file_view(file: &File<'_>) -> String {
+        let source = match file.ast {
+            Some(program) => String::from_utf8_lossy(program.source_text).into_owned(),
+            None => file.lines.join("\n"),
+        };
+        let view = FileView {
+            path: file.path.display().to_string(),
+            source,
+            symbols: &file.declared_symbols,
+            ast: file.ast,
+        };
+
+        serde_json::to_string(&view).unwrap()
+    }
+
+    fn validate_file(&self, file: &File<'_>) -> Vec<Violation> {
+        let output = match self.call("validate", Some(&Self::file_view(file))) {
+            Some(output) => output,
+            None => return vec![],
+        };
+        let violations: Vec<PluginViolation> = match serde_json::from_str(&output) {
+            Ok(violations) => violations,
+            Err(e) => {
+                eprintln!("Plugin {} returned invalid violations: {}", self.code, e);
+                return vec![];
+            }
+        };
+
+        violations
+            .into_iter()
+            .map(|v| self.new_violation(file, v.message, Span::dummy(v.start, v.end)))
+            .collect()
+    }
+}
+
+impl Plugin {
+    fn has_export(&self, name: &str) -> bool {
+        self.instance.get_func(&self.store, name).is_some()
+    }
+
+    /// Call an export taking no parameter, or the `(ptr, len)` of `input`,
+    /// and returning an optional `(ptr << 32) | len` output.
+    fn call_output(&mut self, name: &str, input: Option<&str>) -> Result<Option<String>, String> {
+        self.refuel()?;
+        let packed = match input {
+            None => self
+                .instance
+                .get_typed_func::<(), i64>(&self.store, name)
+                .and_then(|f| f.call(&mut self.store, ()))
+                .map_err(call_error)?,
+            Some(input) => {
+                let (ptr, len) = self.write(input)?;
+                let result = self
+                    .instance
+                    .get_typed_func::<(i32, i32), i64>(&self.store, name)
+                    .and_then(|f| f.call(&mut self.store, (ptr, len)))
+                    .map_err(call_error);
+                self.dealloc(ptr, len)?;
+                result?
+            }
+        };
+
+        let ptr = (packed >> 32) as u32 as i32;
+        let len = packed as u32 as i32;
+        if len == 0 {
+            return Ok(None);
+        }
+        let output = self.read(ptr, len)?;
+        self.dealloc(ptr, len)?;
+
+        Ok(Some(output))
+    }
+
+    /// Call an export taking the `(ptr, len)` of `input` and returning nothing.
+    fn call_input(&mut self, name: &str, input: &str) -> Result<(), String> {
+        self.refuel()?;
+        let (ptr, len) = self.write(input)?;
+        let result = self
+            .instance
+            .get_typed_func::<(i32, i32), ()>(&self.store, name)
+            .and_then(|f| f.call(&mut self.store, (ptr, len)))
+            .map_err(call_error);
+        self.dealloc(ptr, len)?;
+
+        result
+    }
+
+    /// Give a new budget to the call about to be made, including the
+    /// `alloc` and `dealloc` calls around it.
+    fn refuel(&mut self) -> Result<(), String> {
+        self.store.set_fuel(self.fuel).map_err(|e| e.to_string())
+    }
+
+    fn write(&mut self, input: &str) -> Result<(i32, i32), String> {
+        let len = i32::try_from(input.len()).map_err(|e| e.to_string())?;
+        let ptr = self.alloc.call(&mut self.store, len).map_err(call_error)?;
+        self.memory
+            .write(&mut self.store, ptr as u32 as usize, input.as_bytes())
+            .map_err(|e| e.to_string())?;
+
+        Ok((ptr, len))
+    }
+
+    fn read(&self, ptr: i32, len: i32) -> Result<String, String> {
+        let (ptr, len) = (ptr as u32 as usize, len as u32 as usize);
+        // Checked before allocating, as the length comes from the plugin
+        if ptr.saturating_add(len) > self.memory.data_size(&self.store) {
+            return Err(format!("returned {len} bytes at {ptr}, out of its memory"));
+        }
+        let mut buffer = vec![0; len];
+        self.memory
+            .read(&self.store, ptr, &mut buffer)
+            .map_err(|e| e.to_string())?;
+
+        String::from_utf8(buffer).map_err(|e| e.to_string())
+    }
+
+    fn dealloc(&mut self, ptr: i32, len: i32) -> Result<(), String> {
+        match self
+            .instance
+            .get_typed_func::<(i32, i32), ()>(&self.store, "dealloc")
+        {
+            Ok(dealloc) => dealloc
+                .call(&mut self.store, (ptr, len))
+                .map_err(call_error),
+            Err(_) => Ok(()),
+        }
+    }
+}
+
+/// Why a call into the plugin failed, telling apart the exhausted budgets.
+fn call_error(e: wasmi::Error) -> String {
+    match e.as_trap_code() {
+        Some(TrapCode::OutOfFuel) => format!("exceeded its budget of {} fuel", FUEL),
+        _ => e.to_string(),
+    }
+}
+
+impl RuleTrait for Rule {
+    fn index_entry(&self, file: &File<'_>) -> Option<Value> {
+        let output = self.call("index_file", Some(&Self::file_view(file)))?;
+        serde_json::from_str(&output).ok()
+    }
+
+    fn load_index_entry(&self, entry: &Value) {
+        let mut plugin = self.instance.lock().unwrap();
+        if plugin.has_export("load_index_entry") {
+            if let Err(e) = plugin.call_input("load_index_entry", &entry.to_string()) {
+                eprintln!("Plugin {} failed in load_index_entry: {}", self.code, e);
+            }
+        }
+    }
+
+    fn unload_index_entry(&self, entry: &Value) {
+        let mut plugin = self.instance.lock().unwrap();
+        if plugin.has_export("unload_index_entry") {
+            if let Err(e) = plugin.call_input("unload_index_entry", &entry.to_string()) {
+                eprintln!("Plugin {} failed in unload_index_entry: {}", self.code, e);
+            }
+        }
+    }
+
+    fn get_code(&self) -> String {
+        self.code.clone()
+    }
+
+    fn description(&self) -> String {
+        self.description.clone()
+    }
+
+    fn get_detailed_explanation(&self) -> Option<String> {
+        None
+    }
+
+    fn set_config(&mut self, json: &Value) {
+        let plugin = self.instance.get_mut().unwrap();
+        if plugin.has_export("set_config") {
+            if let Err(e) = plugin.call_input("set_config", &json.to_string()) {
+                self.output_error(e.into());
+            }
+        }
+    }
+
+    fn do_validate(&self, _file: &File<'_>) -> bool {
+        true
+    }
+
+    fn visitor<'a>(&'a self, file: &'a File<'a>) -> Option<Box<dyn Visitor<'a> + 'a>> {
+        Some(Box::new(PluginRun { rule: self, file }))
+    }
+}
+
+/// The plugin gets the whole file, so it only runs once the shared traversal
+/// is over.
+struct PluginRun<'a> {
+    rule: &'a Rule,
+    file: &'a File<'a>,
+}
+
+impl<'a> Visitor<'a> for PluginRun<'a> {
+    fn finish(self: Box<Self>) -> Vec<Violation> {
+        self.rule.validate_file(self.file)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use serde_json::json;
+
+    use crate::config::Config;
+    use crate::results::Severity;
+    use crate::rules::tests::analyze_file_with_config;
+
+    use super::*;
+
+    fn plugin_path() -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("src/rules/examples/plugin/plugin.wat")
+    }
+
+    #[test]
+    fn load() {
+        let rule = Rule::load(&plugin_path()).unwrap();
+
+        assert_eq!(rule.get_code(), "P0001");
+        assert_eq!(rule.description(), "Flags the opening tag");
+        assert!(Rule::load(Path::new("missing.wasm")).is_err());
+    }
+
+    #[test]
+    fn set_config() {
+        let mut rule = Rule::load(&plugin_path()).unwrap();
+        rule.set_config(&json!({"max": 3}));
+
+        // The example plugin echoes its settings in the index entries
+        let entry = rule.call("index_file", Some("{}")).unwrap();
+        assert_eq!(entry, r#"{"max":3}"#);
+    }
+
+    #[test]
+    fn endless_call_runs_out_of_fuel() {
+        let path = plugin_path().with_file_name("endless.wat");
+        let rule = Rule::load(&path).unwrap();
+        rule.instance.lock().unwrap().fuel = 10_000;
+
+        let result = rule
+            .instance
+            .lock()
+            .unwrap()
+            .call_output("validate", Some("{}"));
+        assert_eq!(result, Err(format!("exceeded its budget of {} fuel", FUEL)));
+        // The budget is given again to every call
+        assert!(rule.call("validate", Some("{}")).is_none());
+        assert!(rule.call("index_file", Some("{}")).is_some());
+    }
+
+    #[test]
+    fn read_out_of_memory() {
+        let rule = Rule::load(&plugin_path()).unwrap();
+        let plugin = rule.instance.lock().unwrap();
+
+        assert!(plugin.read(0, -1).is_err());
+        assert!(plugin.read(-1, 1).is_err());
+        assert_eq!(plugin.read(0, 0), Ok(String::new()));
+    }
+
+    #[test]
+    fn violations() {
+        let mut config = Config {
+            enabled_rules: vec!["P0001".to_string()],
+            plugins: vec![plugin_path().display().to_string()],
+            ..Default::default()
+        };
+        config
+            .rules
+            .insert("P0001".to_string(), json!({"severity": "major"}));
+
+        let violations = analyze_file_with_config("e1/full_opening_tag_valid.php", &config);
+
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].rule, "P0001");
+        assert_eq!(violations[0].suggestion, "Opening tag");
+        assert_eq!(violations[0].severity, Severity::Major);
+        assert_eq!(violations[0].start_line, 1);
+        assert_eq!(violations[0].end_column, 5);
+    }
+}
@@ -0,0 +1,426 @@
+use std::time::{Duration, Instant};
+
+use mago_syntax::ast::*;
+use mago_syntax::walker::Walker;
+
+use crate::results::Violation;
+use crate::rules::callable::CallableKind;
+
+/// The declarations enclosing the node being visited.
+#[derive(Debug, Default, Clone)]
+pub struct Scope {
+    namespace: Option<String>,
+    /// `None` for anonymous classes.
+    class_likes: Vec<Option<String>>,
+    /// Names follow `Callable::name`.
+    function_likes: Vec<(CallableKind, String)>,
+    /// Property whose hooks are being visited.
+    property: Option<String>,
+}
+
+impl Scope {
+    /// `None` in the global namespace.
+    pub fn namespace(&self) -> Option<&str> {
+        self.namespace.as_deref()
+    }
+
+    /// Innermost class, interface, trait or enum, `None` outside of them and
+    /// within anonymous classes.
+    #[allow(dead_code)]
+    pub fn class_like(&self) -> Option<&str> {
+        self.class_likes.last()?.as_deref()
+    }
+
+    /// Innermost class-like which isn't anonymous, i.e. the one the
+    /// anonymous classes being visited belong to.
+    pub fn named_class_like(&self) -> Option<&str> {
+        self.class_likes
+            .iter()
+            .rev()
+            .flatten()
+            .next()
+            .map(String::as_str)
+    }
+
+    /// Innermost method, function, closure, arrow function or property hook.
+    #[allow(dead_code)]
+    pub fn function_like(&self) -> Option<(CallableKind, &str)> {
+        let (kind, name) = self.function_likes.last()?;
+        Some((*kind, name))
+    }
+
+    /// Innermost method, function or property hook, i.e. the one the
+    /// closures and arrow functions being visited belong to.
+    pub fn named_function_like(&self) -> Option<(CallableKind, &str)> {
+        self.function_likes
+            .iter()
+            .rev()
+            .find(|(kind, _)| !matches!(kind, CallableKind::Closure | CallableKind::ArrowFunction))
+            .map(|(kind, name)| (*kind, name.as_str()))
+    }
+}
+
+/// Callbacks of a rule in the traversal of a file shared by the rules
+/// providing a visitor.
+///
+/// Nodes are entered before their children and left after them. The scope
+/// passed along is the one the node is declared in, so a class statement or
+/// a method member doesn't see itself in it, while its children do.
+pub trait Visitor<'a> {
+    fn enter_statement(&mut self, _statement: &'a Statement<'a>, _scope: &Scope) {}
+
+    fn leave_statement(&mut self, _statement: &'a Statement<'a>, _scope: &Scope) {}
+
+    fn enter_expression(&mut self, _expression: &'a Expression<'a>, _scope: &Scope) {}
+
+    fn leave_expression(&mut self, _expression: &'a Expression<'a>, _scope: &Scope) {}
+
+    fn enter_member(&mut self, _member: &'a ClassLikeMember<'a>, _scope: &Scope) {}
+
+    fn leave_member(&mut self, _member: &'a ClassLikeMember<'a>, _scope: &Scope) {}
+
+    /// Every type hint, including the parts of union, intersection and
+    /// nullable ones.
+    fn visit_hint(&mut self, _hint: &'a Hint<'a>, _scope: &Scope) {}
+
+    /// The violations found, once the whole file has been visited.
+    fn finish(self: Box<Self>) -> Vec<Violation>;
+}
+
+/// A single walk of a program dispatching every node to all the visitors.
+pub struct Traversal<'v, 'a> {
+    visitors: &'v mut [Box<dyn Visitor<'a> + 'a>],
+    scope: Scope,
+    /// Time spent in each visitor, by index, when measured.
+    pub durations: Option<Vec<Duration>>,
+    /// Number of statements visited.
+    pub statements: usize,
+}
+
+impl<'v, 'a> Traversal<'v, 'a> {
+    pub fn new(visitors: &'v mut [Box<dyn Visitor<'a> + 'a>], timed: bool) -> Self {
+        let durations = timed.then(|| vec![Duration::ZERO; visitors.len()]);
+        Self {
+            visitors,
+            scope: Scope::default(),
+            durations,
+            statements: 0,
+        }
+    }
+
+    pub fn walk(&mut self, program: &'a Program<'a>) {
+        FusedWalker.walk_program(program, self);
+    }
+
+    fn dispatch(&mut self, callback: impl Fn(&mut (dyn Visitor<'a> + 'a), &Scope)) {
+        for (index, visitor) in self.visitors.iter_mut().enumerate() {
+            match &mut self.durations {
+                Some(durations) => {
+                    let start = Instant::now();
+                    callback(visitor.as_mut(), &self.scope);
+                    durations[index] += start.elapsed();
+                }
+                None => callback(visitor.as_mut(), &self.scope),
+            }
+        }
+    }
+
+    fn enter_function_like(&mut self, kind: CallableKind, name: String) {
+        self.scope.function_likes.push((kind, name));
+    }
+
+    fn leave_function_like(&mut self) {
+        self.scope.function_likes.pop();
+    }
+
+    fn enter_class_like(&mut self, name: Option<&LocalIdentifier<'_>>) {
+        let name = name.map(|name| String::from_utf8_lossy(name.value).into_owned());
+        self.scope.class_likes.push(name);
+    }
+
+    fn leave_class_like(&mut self) {
+        self.scope.class_likes.pop();
+    }
+}
+
+struct FusedWalker;
+
+impl<'v, 'a> Walker<'a, 'a, Traversal<'v, 'a>> for FusedWalker {
+    fn walk_in_statement(&self, statement: &'a Statement<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.statements += 1;
+        traversal.dispatch(|visitor, scope| visitor.enter_statement(statement, scope));
+    }
+
+    fn walk_out_statement(&self, statement: &'a Statement<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.dispatch(|visitor, scope| visitor.leave_statement(statement, scope));
+    }
+
+    fn walk_in_expression(
+        &self,
+        expression: &'a Expression<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        traversal.dispatch(|visitor, scope| visitor.enter_expression(expression, scope));
+    }
+
+    fn walk_out_expression(
+        &self,
+        expression: &'a Expression<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        traversal.dispatch(|visitor, scope| visitor.leave_expression(expression, scope));
+    }
+
+    fn walk_in_class_like_member(
+        &self,
+        member: &'a ClassLikeMember<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        traversal.dispatch(|visitor, scope| visitor.enter_member(member, scope));
+    }
+
+    fn walk_out_class_like_member(
+        &self,
+        member: &'a ClassLikeMember<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        traversal.dispatch(|visitor, scope| visitor.leave_member(member, scope));
+    }
+
+    fn walk_in_hint(&self, hint: &'a Hint<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.dispatch(|visitor, scope| visitor.visit_hint(hint, scope));
+    }
+
+    fn walk_in_namespace(&self, namespace: &'a Namespace<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.scope.namespace = namespace
+            .name
+            .as_ref()
+            .map(|name| String::from_utf8_lossy(name.value()).into_owned());
+    }
+
+    fn walk_out_namespace(&self, _namespace: &'a Namespace<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.scope.namespace = None;
+    }
+
+    fn walk_in_class(&self, class: &'a Class<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.enter_class_like(Some(&class.name));
+    }
+
+    fn walk_out_class(&self, _class: &'a Class<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.leave_class_like();
+    }
+
+    fn walk_in_interface(&self, interface: &'a Interface<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.enter_class_like(Some(&interface.name));
+    }
+
+    fn walk_out_interface(&self, _interface: &'a Interface<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.leave_class_like();
+    }
+
+    fn walk_in_trait(&self, r#trait: &'a Trait<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.enter_class_like(Some(&r#trait.name));
+    }
+
+    fn walk_out_trait(&self, _trait: &'a Trait<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.leave_class_like();
+    }
+
+    fn walk_in_enum(&self, r#enum: &'a Enum<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.enter_class_like(Some(&r#enum.name));
+    }
+
+    fn walk_out_enum(&self, _enum: &'a Enum<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.leave_class_like();
+    }
+
+    fn walk_in_anonymous_class(
+        &self,
+        _anonymous_class: &'a AnonymousClass<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        traversal.enter_class_like(None);
+    }
+
+    fn walk_out_anonymous_class(
+        &self,
+        _anonymous_class: &'a AnonymousClass<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        traversal.leave_class_like();
+    }
+
+    fn walk_in_method(&self, method: &'a Method<'a>, traversal: &mut Traversal<'v, 'a>) {
+        let name = String::from_utf8_lossy(method.name.value).into_owned();
+        traversal.enter_function_like(CallableKind::Method, name);
+    }
+
+    fn walk_out_method(&self, _method: &'a Method<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.leave_function_like();
+    }
+
+    fn walk_in_function(&self, function: &'a Function<'a>, traversal: &mut Traversal<'v, 'a>) {
+        let name = String::from_utf8_lossy(function.name.value).into_owned();
+        traversal.enter_function_like(CallableKind::Function, name);
+    }
+
+    fn walk_out_function(&self, _function: &'a Function<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.leave_function_like();
+    }
+
+    fn walk_in_closure(&self, _closure: &'a Closure<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.enter_function_like(CallableKind::Closure, String::from("{closure}"));
+    }
+
+    fn walk_out_closure(&self, _closure: &'a Closure<'a>, traversal: &mut Traversal<'v, 'a>) {
+        traversal.leave_function_like();
+    }
+
+    fn walk_in_arrow_function(
+        &self,
+        _arrow_function: &'a ArrowFunction<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        traversal.enter_function_like(CallableKind::ArrowFunction, String::from("{closure}"));
+    }
+
+    fn walk_out_arrow_function(
+        &self,
+        _arrow_function: &'a ArrowFunction<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        traversal.leave_function_like();
+    }
+
+    fn walk_in_hooked_property(
+        &self,
+        hooked_property: &'a HookedProperty<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        let name = hooked_property.item.variable().name;
+        traversal.scope.property = Some(String::from_utf8_lossy(name).into_owned());
+    }
+
+    fn walk_out_hooked_property(
+        &self,
+        _hooked_property: &'a HookedProperty<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        traversal.scope.property = None;
+    }
+
+    fn walk_in_function_like_parameter(
+        &self,
+        parameter: &'a FunctionLikeParameter<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        if parameter.hooks.is_some() {
+            let name = parameter.variable.name;
+            traversal.scope.property = Some(String::from_utf8_lossy(name).into_owned());
+        }
+    }
+
+    fn walk_out_function_like_parameter(
+        &self,
+        parameter: &'a FunctionLikeParameter<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        if parameter.hooks.is_some() {
+            traversal.scope.property = None;
+        }
+    }
+
+    fn walk_in_property_hook(&self, hook: &'a PropertyHook<'a>, traversal: &mut Traversal<'v, 'a>) {
+        let hook_name = String::from_utf8_lossy(hook.name.value);
+        let name = match &traversal.scope.property {
+            Some(property) => format!("{}::{}", property, hook_name),
+            None => hook_name.into_owned(),
+        };
+        traversal.enter_function_like(CallableKind::PropertyHook, name);
+    }
+
+    fn walk_out_property_hook(
+        &self,
+        _hook: &'a PropertyHook<'a>,
+        traversal: &mut Traversal<'v, 'a>,
+    ) {
+        traversal.leave_function_like();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use bumpalo::Bump;
+
+    use super::*;
+    use crate::file::File;
+
+    /// Records the scope of every method call and class member.
+    #[derive(Default)]
+    struct Recorder {
+        calls: Vec<String>,
+        members: usize,
+        left: usize,
+    }
+
+    impl<'a> Visitor<'a> for &mut Recorder {
+        fn enter_expression(&mut self, expression: &'a Expression<'a>, scope: &Scope) {
+            if let Expression::Call(Call::Function(_)) = expression {
+                self.calls.push(format!(
+                    "{} {} {}",
+                    scope.namespace().unwrap_or("-"),
+                    scope.class_like().unwrap_or("-"),
+                    scope
+                        .function_like()
+                        .map_or("-".to_string(), |(kind, name)| format!(
+                            "{:?}:{}",
+                            kind, name
+                        )),
+                ));
+            }
+        }
+
+        fn enter_member(&mut self, _member: &'a ClassLikeMember<'a>, _scope: &Scope) {
+            self.members += 1;
+        }
+
+        fn leave_member(&mut self, _member: &'a ClassLikeMember<'a>, _scope: &Scope) {
+            self.left += 1;
+        }
+
+        fn finish(self: Box<Self>) -> Vec<Violation> {
+            vec![]
+        }
+    }
+
+    #[test]
+    fn test_traversal_scopes() {
+        let content = "<?php\nnamespace App;\n\nfoo();\n\nclass Service {\n    private $a;\n\n    public function run() {\n        bar(fn() => baz());\n        $o = new class {\n            public function handle() { qux(); }\n        };\n    }\n\n    public string $name {\n        get => quux();\n    }\n}\n";
+        let arena = Bump::new();
+        let file = File::new(&arena, PathBuf::from("test.php"), content.to_string());
+
+        let mut recorder = Recorder::default();
+        {
+            let mut visitors: Vec<Box<dyn Visitor<'_> + '_>> = vec![Box::new(&mut recorder)];
+            let mut traversal = Traversal::new(&mut visitors, true);
+            traversal.walk(file.ast.unwrap());
+            assert!(traversal.statements > 5);
+            assert_eq!(traversal.durations.as_ref().map(Vec::len), Some(1));
+        }
+
+        assert_eq!(
+            recorder.calls,
+            vec![
+                "App - -",
+                "App Service Method:run",
+                "App Service ArrowFunction:{closure}",
+                "App - Method:handle",
+                "App Service PropertyHook:$name::get",
+            ]
+        );
+        assert_eq!(recorder.members, 4);
+        assert_eq!(recorder.left, 4);
+    }
+}
@@ -0,0 +1,303 @@
+use std::collections::HashSet;
+
+use mago_span::{HasSpan, Span};
+use mago_syntax::ast::{ClassLikeMember, Program, Sequence, Statement};
+use serde::{Deserialize, Serialize};
+
+use crate::file::File;
+use crate::results::Violation;
+
+static MARKER: &str = "phanalist-ignore";
+static NEXT_LINE_MARKER: &str = "phanalist-ignore-next-line";
+static FILE_MARKER: &str = "phanalist-ignore-file";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Scope {
+    File,
+    /// Inclusive range of 1-based line numbers.
+    Lines(usize, usize),
+}
+
+/// A single suppression comment found in a file.
+///
+/// Supported forms:
+/// - `// phanalist-ignore-next-line E0014` for the line below the comment;
+/// - `/** @phanalist-ignore E0027 */` on a class-like, member or function;
+/// - `// phanalist-ignore-file E0009` anywhere in the file.
+///
+/// Without any rule codes all the rules are suppressed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Suppression {
+    pub span: Span,
+    pub codes: Vec<String>,
+    scope: Scope,
+    #[serde(skip)]
+    used: HashSet<String>,
+}
+
+impl Suppression {
+    fn matches(&self, violation: &Violation) -> bool {
+        let in_scope = match self.scope {
+            Scope::File => true,
+            Scope::Lines(start, end) => (start..=end).contains(&violation.start_line),
+        };
+
+        in_scope && (self.codes.is_empty() || self.codes.contains(&violation.rule))
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Suppressions {
+    pub items: Vec<Suppression>,
+}
+
+impl Suppressions {
+    /// `is_known_code` tells whether a word is the code of a rule, for the
+    /// codes which don't look like the built-in ones, e.g. of custom rules.
+    pub fn from_file(file: &File<'_>, is_known_code: &dyn Fn(&str) -> bool) -> Self {
+        let mut items = vec![];
+        let program = match file.ast {
+            Some(program) => program,
+            None => return Self { items },
+        };
+
+        let declarations = collect_declaration_spans(program);
+        for comment in program.trivia.iter().filter(|t| t.kind.is_comment()) {
+            let text = String::from_utf8_lossy(comment.value);
+
+            let scope = if let Some(codes) = codes_after(&text, FILE_MARKER, is_known_code) {
+                Some((Scope::File, codes))
+            } else if let Some(codes) = codes_after(&text, NEXT_LINE_MARKER, is_known_code) {
+                let line = file.line_number(comment.span.end.offset) + 1;
+                Some((Scope::Lines(line, line), codes))
+            } else if comment.kind.is_docblock() {
+                codes_after(&text, &format!("@{MARKER}"), is_known_code).map(|codes| {
+                    let scope = match declarations
+                        .iter()
+                        .find(|span| follows(program, comment.span, **span))
+                    {
+                        Some(span) => Scope::Lines(
+                            file.line_number(span.start.offset),
+                            file.line_number(span.end.offset),
+                        ),
+                        // Not attached to a declaration, so it applies to the next line
+                        None => {
+                            let line = file.line_number(comment.span.end.offset) + 1;
+                            Scope::Lines(line, line)
+                        }
+                    };
+                    (scope, codes)
+                })
+            } else {
+                None
+            };
+
+            if let Some((scope, codes)) = scope {
+                items.push(Suppression {
+                    span: comment.span,
+                    codes,
+                    scope,
+                    used: HashSet::new(),
+                });
+            }
+        }
+
+        Self { items }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Drop every violation covered by a suppression and remember which
+    /// suppressions were used.
+    pub fn filter(&mut self, violations: Vec<Violation>) -> Vec<Violation> {
+        violations
+            .into_iter()
+            .filter(|violation| {
+                let mut suppressed = false;
+                for item in self.items.iter_mut() {
+                    if item.matches(violation) {
+                        item.used.insert(violation.rule.clone());
+                        suppressed = true;
+                    }
+                }
+                !suppressed
+            })
+            .collect()
+    }
+
+    /// Suppressions which didn't match any violation, paired with a suggestion.
+    /// Codes of rules which didn't run are not reported, as there is no way
+    /// to tell whether they are still needed.
+    pub fn unused(&self, active_codes: &[String]) -> Vec<(Span, String)> {
+        let mut unused = vec![];
+        for item in &self.items {
+            if item.codes.is_empty() {
+                if item.used.is_empty() {
+                    unused.push((
+                        item.span,
+                        String::from("The suppression comment doesn't match any violation."),
+                    ));
+                }
+                continue;
+            }
+
+            for code in &item.codes {
+                if !item.used.contains(code) && active_codes.contains(code) {
+                    unused.push((
+                        item.span,
+                        format!("The suppression of {} doesn't match any violation.", code),
+                    ));
+                }
+            }
+        }
+        unused
+    }
+}
+
+/// Rule codes listed after `marker`, or `None` when `marker` isn't present.
+/// Parsing stops at the first word which is neither shaped like a built-in
+/// code (`E0009`) nor a known code, so a free-form reason can follow the codes.
+fn codes_after(
+    text: &str,
+    marker: &str,
+    is_known_code: &dyn Fn(&str) -> bool,
+) -> Option<Vec<String>> {
+    let mut start = 0;
+    while let Some(position) = text[start..].find(marker) {
+        let rest = &text[start + position + marker.len()..];
+        start += position + marker.len();
+        // `phanalist-ignore` is a prefix of the other markers
+        if rest.starts_with('-') {
+            continue;
+        }
+
+        let line = rest.lines().next().unwrap_or_default();
+        let codes = line
+            .split(|c: char| c.is_whitespace() || c == ',')
+            .filter(|word| !word.is_empty())
+            .take_while(|word| is_rule_code(word) || is_known_code(word))
+            .map(String::from)
+            .collect();
+
+        return Some(codes);
+    }
+
+    None
+}
+
+/// Whether the word has the shape of a built-in rule code: an uppercase
+/// letter followed by four digits.
+fn is_rule_code(word: &str) -> bool {
+    let bytes = word.as_bytes();
+    bytes.len() == 5 && bytes[0].is_ascii_uppercase() && bytes[1..].iter().all(u8::is_ascii_digit)
+}
+
+/// Whether only whitespace separates the `comment` from the `declaration`.
+fn follows(program: &Program<'_>, comment: Span, declaration: Span) -> bool {
+    let (start, end) = (
+        comment.end.offset as usize,
+        declaration.start.offset as usize,
+    );
+    start <= end
+        && program
+            .source_text
+            .get(start..end)
+            .is_some_and(|between| between.iter().all(|b| b.is_ascii_whitespace()))
+}
+
+fn collect_declaration_spans(program: &Program<'_>) -> Vec<Span> {
+    let mut spans = vec![];
+    for statement in program.statements.iter() {
+        collect_statement_spans(statement, &mut spans);
+    }
+    spans
+}
+
+fn collect_statement_spans(statement: &Statement<'_>, spans: &mut Vec<Span>) {
+    match statement {
+        Statement::Namespace(ns) => {
+            for s in ns.statements().iter() {
+                collect_statement_spans(s, spans);
+            }
+        }
+        Statement::Class(class) => {
+            spans.push(class.span());
+            collect_member_spans(&class.members, spans);
+        }
+        Statement::Interface(interface) => {
+            spans.push(interface.span());
+            collect_member_spans(&interface.members, spans);
+        }
+        Statement::Trait(t) => {
+            spans.push(t.span());
+            collect_member_spans(&t.members, spans);
+        }
+        Statement::Enum(e) => {
+            spans.push(e.span());
+            collect_member_spans(&e.members, spans);
+        }
+        Statement::Function(function) => spans.push(function.span()),
+        _ => {}
+    }
+}
+
+fn collect_member_spans(members: &Sequence<'_, ClassLikeMember<'_>>, spans: &mut Vec<Span>) {
+    for member in members.iter() {
+        spans.push(member.span());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_codes_after_stops_at_reason() {
+        assert_eq!(
+            codes_after(
+                "// phanalist-ignore-next-line E0014, E0011 legacy code",
+                NEXT_LINE_MARKER,
+                &|_| false
+            ),
+            Some(vec!["E0014".to_string(), "E0011".to_string()])
+        );
+    }
+
+    #[test]
+    fn test_codes_after_skips_longer_markers() {
+        assert_eq!(
+            codes_after("// phanalist-ignore-file", "@phanalist-ignore", &|_| false),
+            None
+        );
+        assert_eq!(
+            codes_after("// phanalist-ignore-next-line", MARKER, &|_| false),
+            None
+        );
+        assert_eq!(
+            codes_after("/** @phanalist-ignore */", "@phanalist-ignore", &|_| false),
+            Some(vec![])
+        );
+    }
+
+    #[test]
+    fn test_codes_after_stops_at_uppercase_reason() {
+        assert_eq!(
+            codes_after(
+                "// phanalist-ignore E0009 TODO API cleanup",
+                MARKER,
+                &|_| false
+            ),
+            Some(vec!["E0009".to_string()])
+        );
+        assert_eq!(
+            codes_after(
+                "// phanalist-ignore NO_EVAL E0009 API",
+                MARKER,
+                &|code| code == "NO_EVAL"
+            ),
+            Some(vec!["NO_EVAL".to_string(), "E0009".to_string()])
+        );
+    }
+}
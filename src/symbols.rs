@@ -0,0 +1,889 @@
+//! Project-wide table of the declared symbols, keyed by fully qualified name.
+//!
+//! Every file contributes its declarations during the indexing pre-pass, so
+//! the rules can look up the class-likes, functions and constants declared
+//! anywhere in the project. Names are resolved the way PHP does, against the
+//! namespace and the `use` imports around them, so two classes sharing a short
+//! name in different namespaces are told apart.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use mago_span::HasSpan;
+use mago_syntax::ast::*;
+use serde::{Deserialize, Serialize};
+
+/// Key of the symbol table's entries in the cross-file index, next to the
+/// rule codes.
+pub static INDEX_KEY: &str = "symbols";
+
+/// Resolves the names used in a namespace to fully qualified names.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NameResolver {
+    namespace: Option<String>,
+    /// Lowercase alias → imported class or namespace.
+    classes: HashMap<String, String>,
+    /// Lowercase alias → imported function.
+    functions: HashMap<String, String>,
+    /// Alias → imported constant. Constant names are case-sensitive.
+    constants: HashMap<String, String>,
+}
+
+impl NameResolver {
+    /// The resolver of a namespace, with the imports of its `use` statements.
+    pub fn new<'a>(
+        namespace: Option<String>,
+        statements: impl Iterator<Item = &'a Statement<'a>>,
+    ) -> Self {
+        let mut resolver = Self {
+            namespace: namespace.filter(|namespace| !namespace.is_empty()),
+            ..Default::default()
+        };
+
+        for statement in statements {
+            if let Statement::Use(r#use) = statement {
+                match &r#use.items {
+                    UseItems::Sequence(sequence) => {
+                        for item in sequence.items.iter() {
+                            resolver.import(None, None, item);
+                        }
+                    }
+                    UseItems::TypedSequence(sequence) => {
+                        for item in sequence.items.iter() {
+                            resolver.import(Some(&sequence.r#type), None, item);
+                        }
+                    }
+                    UseItems::TypedList(list) => {
+                        let prefix = utf8(list.namespace.value());
+                        for item in list.items.iter() {
+                            resolver.import(Some(&list.r#type), Some(&prefix), item);
+                        }
+                    }
+                    UseItems::MixedList(list) => {
+                        let prefix = utf8(list.namespace.value());
+                        for item in list.items.iter() {
+                            resolver.import(item.r#type.as_ref(), Some(&prefix), &item.item);
+                        }
+                    }
+                }
+            }
+        }
+
+        resolver
+    }
+
+    fn import(&mut self, kind: Option<&UseType<'_>>, prefix: Option<&str>, item: &UseItem<'_>) {
+        let name = utf8(item.name.value());
+        let name = match prefix {
+            Some(prefix) => format!("{}\\{}", prefix.trim_start_matches('\\'), name),
+            None => name.trim_start_matches('\\').to_string(),
+        };
+        let alias = match &item.alias {
+            Some(alias) => utf8(alias.identifier.value),
+            None => last_segment(&name).to_string(),
+        };
+
+        match kind {
+            Some(UseType::Function(_)) => self.functions.insert(alias.to_ascii_lowercase(), name),
+            Some(UseType::Const(_)) => self.constants.insert(alias, name),
+            None => self.classes.insert(alias.to_ascii_lowercase(), name),
+        };
+    }
+
+    /// Fully qualified name of a symbol declared in the namespace.
+    pub fn qualify(&self, name: &str) -> String {
+        match &self.namespace {
+            Some(namespace) => format!("{}\\{}", namespace, name),
+            None => name.to_string(),
+        }
+    }
+
+    /// Fully qualified name of a class-like referenced in the namespace.
+    /// Built-in types and `self`, `static` and `parent` are left as they are.
+    pub fn resolve_class(&self, name: &str) -> String {
+        if let Some(name) = name.strip_prefix('\\') {
+            return name.to_string();
+        }
+        if let Some(name) = strip_namespace_keyword(name) {
+            return self.qualify(name);
+        }
+        if is_builtin_type(name) {
+            return name.to_string();
+        }
+
+        let (first, rest) = match name.split_once('\\') {
+            Some((first, rest)) => (first, Some(rest)),
+            None => (name, None),
+        };
+        match (self.classes.get(&first.to_ascii_lowercase()), rest) {
+            (Some(import), Some(rest)) => format!("{}\\{}", import, rest),
+            (Some(import), None) => import.clone(),
+            (None, _) => self.qualify(name),
+        }
+    }
+
+    /// Fully qualified name of a function called in the namespace. An
+    /// unqualified name which isn't imported resolves to the namespace, see
+    /// [`SymbolTable::resolve_function`] for the fallback to the global one.
+    pub fn resolve_function(&self, name: &str) -> String {
+        if name.contains('\\') {
+            return self.resolve_qualified(name);
+        }
+
+        match self.functions.get(&name.to_ascii_lowercase()) {
+            Some(import) => import.clone(),
+            None => self.qualify(name),
+        }
+    }
+
+    /// Fully qualified name of a constant used in the namespace, resolved
+    /// like [`Self::resolve_function`].
+    pub fn resolve_constant(&self, name: &str) -> String {
+        if name.contains('\\') {
+            return self.resolve_qualified(name);
+        }
+
+        match self.constants.get(name) {
+            Some(import) => import.clone(),
+            None => self.qualify(name),
+        }
+    }
+
+    /// Qualified function and constant names resolve their first segment
+    /// against the class and namespace imports.
+    fn resolve_qualified(&self, name: &str) -> String {
+        if let Some(name) = name.strip_prefix('\\') {
+            return name.to_string();
+        }
+        if let Some(name) = strip_namespace_keyword(name) {
+            return self.qualify(name);
+        }
+
+        match name.split_once('\\') {
+            Some((first, rest)) => match self.classes.get(&first.to_ascii_lowercase()) {
+                Some(import) => format!("{}\\{}", import, rest),
+                None => self.qualify(name),
+            },
+            None => self.qualify(name),
+        }
+    }
+}
+
+/// The resolver of the namespace around the byte offset. A namespace
+/// declared without braces extends up to the next one.
+pub fn resolver_at(program: &Program<'_>, offset: u32) -> NameResolver {
+    let namespace = program
+        .statements
+        .iter()
+        .filter_map(|statement| match statement {
+            Statement::Namespace(ns) => Some(ns),
+            _ => None,
+        })
+        .take_while(|ns| ns.span().start.offset <= offset)
+        .last();
+
+    match namespace {
+        Some(ns) => NameResolver::new(namespace_name(ns), ns.statements().iter()),
+        None => NameResolver::new(None, program.statements.iter()),
+    }
+}
+
+/// Scalar and pseudo types, and the relative class names.
+pub fn is_builtin_type(name: &str) -> bool {
+    matches!(
+        name.to_ascii_lowercase().as_str(),
+        "array"
+            | "bool"
+            | "boolean"
+            | "callable"
+            | "false"
+            | "float"
+            | "int"
+            | "integer"
+            | "iterable"
+            | "mixed"
+            | "never"
+            | "null"
+            | "object"
+            | "resource"
+            | "string"
+            | "true"
+            | "void"
+            | "self"
+            | "static"
+            | "parent"
+    )
+}
+
+/// The namespace part of a fully qualified name, empty for the global one.
+pub fn namespace_of(name: &str) -> &str {
+    name.rsplit_once('\\')
+        .map_or("", |(namespace, _)| namespace)
+}
+
+/// The unqualified part of a name.
+pub fn last_segment(name: &str) -> &str {
+    name.rsplit('\\').next().unwrap_or(name)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ClassKind {
+    #[default]
+    Class,
+    Interface,
+    Trait,
+    Enum,
+}
+
+/// A class, interface, trait or enum. Every name is fully qualified.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClassSymbol {
+    pub name: String,
+    pub kind: ClassKind,
+    pub is_abstract: bool,
+    /// The parent class, or the parent interfaces of an interface.
+    pub extends: Vec<String>,
+    pub implements: Vec<String>,
+    pub traits: Vec<String>,
+    /// Class-likes referenced by the type hints of the members.
+    pub hinted_types: BTreeSet<String>,
+    pub methods: Vec<MethodSymbol>,
+    pub properties: Vec<PropertySymbol>,
+    /// Class constants and enum cases.
+    pub constants: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct MethodSymbol {
+    pub name: String,
+    /// The single class-like or `self`, `static` or `parent` returned by the
+    /// method, if its return type hint names one.
+    pub return_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PropertySymbol {
+    /// The name, without the `$`.
+    pub name: String,
+    /// The single class-like or `self`, `static` or `parent` the property
+    /// holds, if its type hint names one.
+    pub r#type: Option<String>,
+}
+
+impl ClassSymbol {
+    pub fn namespace(&self) -> &str {
+        namespace_of(&self.name)
+    }
+
+    /// The parent class of a class.
+    pub fn parent(&self) -> Option<&str> {
+        match self.kind {
+            ClassKind::Class => self.extends.first().map(String::as_str),
+            _ => None,
+        }
+    }
+
+    /// Class-likes the class-like depends on: its parents, interfaces, traits
+    /// and the types hinted by its members.
+    pub fn dependencies(&self) -> BTreeSet<&str> {
+        self.extends
+            .iter()
+            .chain(&self.implements)
+            .chain(&self.traits)
+            .chain(&self.hinted_types)
+            .map(String::as_str)
+            .collect()
+    }
+}
+
+/// Symbols declared by a single file, its entry in the cross-file index.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileSymbols {
+    pub classes: Vec<ClassSymbol>,
+    pub functions: Vec<String>,
+    pub constants: Vec<String>,
+}
+
+impl FileSymbols {
+    pub fn collect(program: &Program<'_>) -> Self {
+        let mut symbols = Self::default();
+        let global = NameResolver::new(None, program.statements.iter());
+
+        for statement in program.statements.iter() {
+            match statement {
+                Statement::Namespace(ns) => {
+                    let resolver = NameResolver::new(namespace_name(ns), ns.statements().iter());
+                    for statement in ns.statements().iter() {
+                        symbols.collect_statement(statement, &resolver);
+                    }
+                }
+                _ => symbols.collect_statement(statement, &global),
+            }
+        }
+
+        symbols
+    }
+
+    fn collect_statement(&mut self, statement: &Statement<'_>, resolver: &NameResolver) {
+        let class = match statement {
+            Statement::Class(class) => {
+                let mut symbol =
+                    class_symbol(resolver, class.name.value, ClassKind::Class, &class.members);
+                symbol.is_abstract = class
+                    .modifiers
+                    .iter()
+                    .any(|modifier| matches!(modifier, Modifier::Abstract(_)));
+                if let Some(extends) = &class.extends {
+                    symbol.extends = resolve_all(resolver, extends.types.iter());
+                }
+                if let Some(implements) = &class.implements {
+                    symbol.implements = resolve_all(resolver, implements.types.iter());
+                }
+                symbol
+            }
+            Statement::Interface(interface) => {
+                let mut symbol = class_symbol(
+                    resolver,
+                    interface.name.value,
+                    ClassKind::Interface,
+                    &interface.members,
+                );
+                symbol.is_abstract = true;
+                if let Some(extends) = &interface.extends {
+                    symbol.extends = resolve_all(resolver, extends.types.iter());
+                }
+                symbol
+            }
+            Statement::Trait(r#trait) => class_symbol(
+                resolver,
+                r#trait.name.value,
+                ClassKind::Trait,
+                &r#trait.members,
+            ),
+            Statement::Enum(r#enum) => {
+                let mut symbol = class_symbol(
+                    resolver,
+                    r#enum.name.value,
+                    ClassKind::Enum,
+                    &r#enum.members,
+                );
+                if let Some(implements) = &r#enum.implements {
+                    symbol.implements = resolve_all(resolver, implements.types.iter());
+                }
+                symbol
+            }
+            Statement::Function(function) => {
+                self.functions
+                    .push(resolver.qualify(&utf8(function.name.value)));
+                return;
+            }
+            Statement::Constant(constant) => {
+                for item in constant.items.iter() {
+                    self.constants
+                        .push(resolver.qualify(&utf8(item.name.value)));
+                }
+                return;
+            }
+            _ => return,
+        };
+
+        self.classes.push(class);
+    }
+}
+
+fn class_symbol(
+    resolver: &NameResolver,
+    name: &[u8],
+    kind: ClassKind,
+    members: &Sequence<'_, ClassLikeMember<'_>>,
+) -> ClassSymbol {
+    let mut symbol = ClassSymbol {
+        name: resolver.qualify(&utf8(name)),
+        kind,
+        ..Default::default()
+    };
+
+    for member in members.iter() {
+        match member {
+            ClassLikeMember::Method(method) => {
+                for parameter in method.parameter_list.parameters.iter() {
+                    if let Some(hint) = &parameter.hint {
+                        hinted_types(resolver, hint, &mut symbol.hinted_types);
+                    }
+                }
+                if let Some(return_type) = &method.return_type_hint {
+                    hinted_types(resolver, &return_type.hint, &mut symbol.hinted_types);
+                }
+                symbol.methods.push(MethodSymbol {
+                    name: utf8(method.name.value),
+                    return_type: method
+                        .return_type_hint
+                        .as_ref()
+                        .and_then(|return_type| hinted_type(resolver, &return_type.hint)),
+                });
+            }
+            ClassLikeMember::Property(property) => {
+                if let Some(hint) = property.hint() {
+                    hinted_types(resolver, hint, &mut symbol.hinted_types);
+                }
+                let r#type = property.hint().and_then(|hint| hinted_type(resolver, hint));
+                for variable in property.variables() {
+                    symbol.properties.push(PropertySymbol {
+                        name: utf8(variable.name).trim_start_matches('$').to_string(),
+                        r#type: r#type.clone(),
+                    });
+                }
+            }
+            ClassLikeMember::TraitUse(trait_use) => {
+                symbol
+                    .traits
+                    .extend(resolve_all(resolver, trait_use.trait_names.iter()));
+            }
+            ClassLikeMember::Constant(constant) => {
+                for item in constant.items.iter() {
+                    symbol.constants.push(utf8(item.name.value));
+                }
+            }
+            ClassLikeMember::EnumCase(case) => {
+                symbol.constants.push(utf8(case.item.name().value));
+            }
+        }
+    }
+
+    symbol
+}
+
+/// The class-like named by a type hint, if it names a single one.
+fn hinted_type(resolver: &NameResolver, hint: &Hint<'_>) -> Option<String> {
+    match hint {
+        Hint::Identifier(identifier) => Some(resolver.resolve_class(&utf8(identifier.value()))),
+        Hint::Self_(_) => Some("self".to_string()),
+        Hint::Static(_) => Some("static".to_string()),
+        Hint::Parent(_) => Some("parent".to_string()),
+        Hint::Nullable(nullable) => hinted_type(resolver, nullable.hint),
+        _ => None,
+    }
+}
+
+/// Every class-like named by a type hint.
+fn hinted_types(resolver: &NameResolver, hint: &Hint<'_>, types: &mut BTreeSet<String>) {
+    match hint {
+        Hint::Identifier(identifier) => {
+            let name = utf8(identifier.value());
+            if !is_builtin_type(&name) {
+                types.insert(resolver.resolve_class(&name));
+            }
+        }
+        Hint::Nullable(nullable) => hinted_types(resolver, nullable.hint, types),
+        Hint::Union(union) => {
+            hinted_types(resolver, union.left, types);
+            hinted_types(resolver, union.right, types);
+        }
+        Hint::Intersection(intersection) => {
+            hinted_types(resolver, intersection.left, types);
+            hinted_types(resolver, intersection.right, types);
+        }
+        Hint::Parenthesized(parenthesized) => hinted_types(resolver, parenthesized.hint, types),
+        _ => {}
+    }
+}
+
+fn resolve_all<'a>(
+    resolver: &NameResolver,
+    names: impl Iterator<Item = &'a Identifier<'a>>,
+) -> Vec<String> {
+    names
+        .map(|name| resolver.resolve_class(&utf8(name.value())))
+        .collect()
+}
+
+fn namespace_name(ns: &Namespace<'_>) -> Option<String> {
+    ns.name.as_ref().map(|name| utf8(name.value()))
+}
+
+fn strip_namespace_keyword(name: &str) -> Option<&str> {
+    match name.split_once('\\') {
+        Some((keyword, rest)) if keyword.eq_ignore_ascii_case("namespace") => Some(rest),
+        _ => None,
+    }
+}
+
+fn utf8(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// The symbols of every indexed file. Lookups are case-insensitive, like
+/// class and function names in PHP.
+#[derive(Debug, Clone, Default)]
+pub struct SymbolTable {
+    /// Lowercase fully qualified name → class-like.
+    classes: HashMap<String, ClassSymbol>,
+    /// Lowercase fully qualified name of a class → its direct subclasses.
+    children: HashMap<String, BTreeSet<String>>,
+    /// Lowercase fully qualified name → function.
+    functions: HashMap<String, String>,
+    constants: HashSet<String>,
+}
+
+impl SymbolTable {
+    pub fn merge(&mut self, symbols: FileSymbols) {
+        for class in symbols.classes {
+            if let Some(parent) = class.parent() {
+                self.children
+                    .entry(parent.to_ascii_lowercase())
+                    .or_default()
+                    .insert(class.name.clone());
+            }
+            self.classes.insert(class.name.to_ascii_lowercase(), class);
+        }
+        for function in symbols.functions {
+            self.functions
+                .insert(function.to_ascii_lowercase(), function);
+        }
+        self.constants.extend(symbols.constants);
+    }
+
+    /// Remove the symbols merged from a file.
+    pub fn remove(&mut self, symbols: FileSymbols) {
+        for class in symbols.classes {
+            if let Some(parent) = class.parent() {
+                let parent = parent.to_ascii_lowercase();
+                if let Some(children) = self.children.get_mut(&parent) {
+                    children.remove(&class.name);
+                    if children.is_empty() {
+                        self.children.remove(&parent);
+                    }
+                }
+            }
+            self.classes.remove(&class.name.to_ascii_lowercase());
+        }
+        for function in symbols.functions {
+            self.functions.remove(&function.to_ascii_lowercase());
+        }
+        for constant in symbols.constants {
+            self.constants.remove(&constant);
+        }
+    }
+
+    pub fn class(&self, name: &str) -> Option<&ClassSymbol> {
+        self.classes.get(&name.to_ascii_lowercase())
+    }
+
+    pub fn classes(&self) -> impl Iterator<Item = &ClassSymbol> {
+        self.classes.values()
+    }
+
+    /// The class-likes declared in the namespace, not in its sub-namespaces.
+    pub fn namespace_classes<'a>(
+        &'a self,
+        namespace: &'a str,
+    ) -> impl Iterator<Item = &'a ClassSymbol> {
+        self.classes()
+            .filter(move |class| class.namespace().eq_ignore_ascii_case(namespace))
+    }
+
+    /// The classes directly extending the class.
+    pub fn children(&self, name: &str) -> impl Iterator<Item = &str> {
+        self.children
+            .get(&name.to_ascii_lowercase())
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+    }
+
+    /// Number of ancestors of the class. A parent outside the project counts,
+    /// but its own ancestors are unknown.
+    pub fn depth(&self, name: &str) -> usize {
+        // Guard against circular inheritance
+        let max_depth = 100;
+
+        let mut depth = 0;
+        let mut current = self.class(name);
+        while let Some(parent) = current.and_then(ClassSymbol::parent) {
+            depth += 1;
+            if depth >= max_depth {
+                break;
+            }
+            current = self.class(parent);
+        }
+
+        depth
+    }
+
+    /// The method declared by the class-like or by one of the traits it uses.
+    pub fn method(&self, class: &str, method: &str) -> Option<&MethodSymbol> {
+        self.find_member(class, &mut HashSet::new(), &|class| {
+            class
+                .methods
+                .iter()
+                .find(|candidate| candidate.name.eq_ignore_ascii_case(method))
+        })
+    }
+
+    /// The property declared by the class-like or by one of the traits it uses.
+    pub fn property(&self, class: &str, property: &str) -> Option<&PropertySymbol> {
+        self.find_member(class, &mut HashSet::new(), &|class| {
+            class
+                .properties
+                .iter()
+                .find(|candidate| candidate.name == property)
+        })
+    }
+
+    fn find_member<'a, T>(
+        &'a self,
+        class: &str,
+        visited: &mut HashSet<String>,
+        find: &dyn Fn(&'a ClassSymbol) -> Option<&'a T>,
+    ) -> Option<&'a T> {
+        let class = self.class(class)?;
+        if !visited.insert(class.name.to_ascii_lowercase()) {
+            return None;
+        }
+
+        find(class).or_else(|| {
+            class
+                .traits
+                .iter()
+                .find_map(|r#trait| self.find_member(r#trait, visited, find))
+        })
+    }
+
+    /// Afferent and efferent coupling of the namespace: the number of
+    /// class-likes outside of it depending on its class-likes, and the number
+    /// of class-likes outside of it its class-likes depend on.
+    pub fn coupling(&self, namespace: &str) -> (usize, usize) {
+        let is_outside = |name: &str| !namespace_of(name).eq_ignore_ascii_case(namespace);
+
+        let mut afferent = HashSet::new();
+        let mut efferent = HashSet::new();
+        for class in self.classes() {
+            let dependencies = class.dependencies();
+            if is_outside(&class.name) {
+                if dependencies
+                    .iter()
+                    .any(|dependency| !is_outside(dependency))
+                {
+                    afferent.insert(class.name.to_ascii_lowercase());
+                }
+            } else {
+                efferent.extend(
+                    dependencies
+                        .into_iter()
+                        .filter(|dependency| is_outside(dependency))
+                        .map(str::to_ascii_lowercase),
+                );
+            }
+        }
+
+        (afferent.len(), efferent.len())
+    }
+
+    /// Fully qualified name of the function called by name in the namespace,
+    /// falling back to the global function like PHP does for unqualified
+    /// names.
+    #[allow(dead_code)]
+    pub fn resolve_function(&self, resolver: &NameResolver, name: &str) -> String {
+        let resolved = resolver.resolve_function(name);
+        if name.contains('\\') || self.functions.contains_key(&resolved.to_ascii_lowercase()) {
+            resolved
+        } else {
+            name.to_string()
+        }
+    }
+
+    /// Fully qualified name of the constant used by name in the namespace,
+    /// falling back to the global constant like PHP does for unqualified
+    /// names.
+    #[allow(dead_code)]
+    pub fn resolve_constant(&self, resolver: &NameResolver, name: &str) -> String {
+        let resolved = resolver.resolve_constant(name);
+        if name.contains('\\') || self.constants.contains(&resolved) {
+            resolved
+        } else {
+            name.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use bumpalo::Bump;
+
+    use crate::file::File;
+
+    use super::*;
+
+    fn symbols(content: &str) -> FileSymbols {
+        let arena = Bump::new();
+        let file = File::new(&arena, PathBuf::from("test.php"), content.to_string());
+        FileSymbols::collect(file.ast.unwrap())
+    }
+
+    fn table(contents: &[&str]) -> SymbolTable {
+        let mut table = SymbolTable::default();
+        for content in contents {
+            table.merge(symbols(content));
+        }
+        table
+    }
+
+    #[test]
+    fn test_resolve_class() {
+        let content = "<?php\nnamespace App\\Http;\n\nuse App\\Model\\User;\nuse App\\Model as M;\nuse Vendor\\{Client, Logger as Log};\nuse function Vendor\\helper;\nuse const Vendor\\LEVEL;\n";
+        let arena = Bump::new();
+        let file = File::new(&arena, PathBuf::from("test.php"), content.to_string());
+        let resolver = resolver_at(file.ast.unwrap(), content.len() as u32 - 1);
+
+        assert_eq!(resolver.resolve_class("User"), "App\\Model\\User");
+        assert_eq!(resolver.resolve_class("user"), "App\\Model\\User");
+        assert_eq!(resolver.resolve_class("M\\Post"), "App\\Model\\Post");
+        assert_eq!(resolver.resolve_class("Client"), "Vendor\\Client");
+        assert_eq!(resolver.resolve_class("Log"), "Vendor\\Logger");
+        assert_eq!(resolver.resolve_class("Request"), "App\\Http\\Request");
+        assert_eq!(resolver.resolve_class("\\Exception"), "Exception");
+        assert_eq!(
+            resolver.resolve_class("namespace\\Kernel"),
+            "App\\Http\\Kernel"
+        );
+        assert_eq!(resolver.resolve_class("self"), "self");
+        assert_eq!(resolver.resolve_class("string"), "string");
+        assert_eq!(resolver.resolve_function("helper"), "Vendor\\helper");
+        assert_eq!(resolver.resolve_function("M\\helper"), "App\\Model\\helper");
+        assert_eq!(resolver.resolve_function("strlen"), "App\\Http\\strlen");
+        assert_eq!(resolver.resolve_constant("LEVEL"), "Vendor\\LEVEL");
+        assert_eq!(resolver.resolve_constant("level"), "App\\Http\\level");
+    }
+
+    #[test]
+    fn test_resolver_per_namespace() {
+        let content =
+            "<?php\nnamespace A {\n    use Lib\\Base;\n}\nnamespace B {\n    class C {}\n}\n";
+        let arena = Bump::new();
+        let file = File::new(&arena, PathBuf::from("test.php"), content.to_string());
+        let program = file.ast.unwrap();
+
+        let first = resolver_at(program, content.find("use").unwrap() as u32);
+        let second = resolver_at(program, content.find("class").unwrap() as u32);
+        assert_eq!(first.resolve_class("Base"), "Lib\\Base");
+        assert_eq!(second.resolve_class("Base"), "B\\Base");
+        assert_eq!(resolver_at(program, 0).resolve_class("Base"), "Base");
+        assert_eq!(
+            resolver_at(program, content.len() as u32).resolve_class("Base"),
+            "B\\Base"
+        );
+    }
+
+    #[test]
+    fn test_collect() {
+        let symbols = symbols("<?php\nnamespace App;\n\nuse Lib\\Base;\n\nabstract class Service extends Base implements \\Countable\n{\n    use Logs;\n    const LIMIT = 1;\n    private ?Client $client;\n\n    public function run(Request|int $request): static {}\n}\n\nenum Status { case Active; }\n\nfunction helper() {}\n\nconst VERSION = 1;\n");
+
+        let service = &symbols.classes[0];
+        assert_eq!(service.name, "App\\Service");
+        assert_eq!(service.kind, ClassKind::Class);
+        assert!(service.is_abstract);
+        assert_eq!(service.extends, vec!["Lib\\Base"]);
+        assert_eq!(service.implements, vec!["Countable"]);
+        assert_eq!(service.traits, vec!["App\\Logs"]);
+        assert_eq!(service.constants, vec!["LIMIT"]);
+        assert_eq!(
+            service.properties,
+            vec![PropertySymbol {
+                name: "client".to_string(),
+                r#type: Some("App\\Client".to_string()),
+            }]
+        );
+        assert_eq!(service.methods[0].return_type.as_deref(), Some("static"));
+        assert_eq!(
+            service.dependencies().into_iter().collect::<Vec<_>>(),
+            vec![
+                "App\\Client",
+                "App\\Logs",
+                "App\\Request",
+                "Countable",
+                "Lib\\Base"
+            ]
+        );
+
+        assert_eq!(symbols.classes[1].name, "App\\Status");
+        assert_eq!(symbols.classes[1].kind, ClassKind::Enum);
+        assert_eq!(symbols.classes[1].constants, vec!["Active"]);
+        assert_eq!(symbols.functions, vec!["App\\helper"]);
+        assert_eq!(symbols.constants, vec!["App\\VERSION"]);
+    }
+
+    #[test]
+    fn test_same_short_name_in_different_namespaces() {
+        let table = table(&[
+            "<?php\nnamespace App\\Model;\n\nclass User extends Base {}\n",
+            "<?php\nnamespace App\\Security;\n\nuse App\\Model\\User as ModelUser;\n\nclass User extends ModelUser {}\n",
+            "<?php\nnamespace App\\Model;\n\nclass Base {}\n",
+        ]);
+
+        assert_eq!(table.depth("App\\Model\\User"), 1);
+        assert_eq!(table.depth("App\\Security\\User"), 2);
+        assert_eq!(table.depth("app\\security\\user"), 2);
+        assert_eq!(
+            table.children("App\\Model\\User").collect::<Vec<_>>(),
+            vec!["App\\Security\\User"]
+        );
+        assert_eq!(table.children("App\\Security\\User").count(), 0);
+    }
+
+    #[test]
+    fn test_remove_file_symbols() {
+        let user = "<?php\nnamespace App\\Security;\n\nuse App\\Model\\User as ModelUser;\n\nclass User extends ModelUser {}\n";
+        let mut table = table(&["<?php\nnamespace App\\Model;\n\nclass User {}\n", user]);
+
+        table.remove(symbols(user));
+
+        assert!(table.class("App\\Security\\User").is_none());
+        assert!(table.class("App\\Model\\User").is_some());
+        assert_eq!(table.children("App\\Model\\User").count(), 0);
+    }
+
+    #[test]
+    fn test_members_through_traits() {
+        let table = table(&[
+            "<?php\nnamespace App;\n\nclass Builder { use Fluent; public ?Builder $next; }\n",
+            "<?php\nnamespace App;\n\ntrait Fluent { public function where(): static {} }\n",
+        ]);
+
+        let method = table.method("App\\Builder", "WHERE").unwrap();
+        assert_eq!(method.return_type.as_deref(), Some("static"));
+        assert_eq!(
+            table
+                .property("App\\Builder", "next")
+                .unwrap()
+                .r#type
+                .as_deref(),
+            Some("App\\Builder")
+        );
+        assert!(table.method("App\\Fluent", "missing").is_none());
+    }
+
+    #[test]
+    fn test_coupling() {
+        let table = table(&[
+            "<?php\nnamespace App\\Model;\n\nclass User {}\n",
+            "<?php\nnamespace App\\Http;\n\nuse App\\Model\\User;\n\nclass Controller { public function show(User $user): Response {} }\n",
+            "<?php\nnamespace Admin\\Model;\n\nclass User {}\n",
+        ]);
+
+        assert_eq!(table.coupling("App\\Model"), (1, 0));
+        assert_eq!(table.coupling("Admin\\Model"), (0, 0));
+        assert_eq!(table.coupling("App\\Http"), (0, 1));
+    }
+
+    #[test]
+    fn test_resolve_function_falls_back_to_global() {
+        let table = table(&["<?php\nnamespace App;\n\nfunction helper() {}\n"]);
+        let resolver = NameResolver::new(Some("App".to_string()), std::iter::empty());
+
+        assert_eq!(table.resolve_function(&resolver, "helper"), "App\\helper");
+        assert_eq!(table.resolve_function(&resolver, "strlen"), "strlen");
+        assert_eq!(table.resolve_constant(&resolver, "PHP_EOL"), "PHP_EOL");
+    }
+}
@@ -0,0 +1,125 @@
+use std::fs;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::process::{ChildStdin, ChildStdout, Command, Stdio};
+
+use serde_json::{json, Value};
+
+fn send(stdin: &mut ChildStdin, message: Value) {
+    let body = message.to_string();
+    write!(stdin, "Content-Length: {}\r\n\r\n{}", body.len(), body).unwrap();
+    stdin.flush().unwrap();
+}
+
+fn receive(stdout: &mut BufReader<ChildStdout>) -> Value {
+    let mut length = 0;
+    loop {
+        let mut header = String::new();
+        stdout.read_line(&mut header).unwrap();
+        let header = header.trim();
+        if header.is_empty() {
+            break;
+        }
+        if let Some(value) = header.strip_prefix("Content-Length: ") {
+            length = value.parse().unwrap();
+        }
+    }
+
+    let mut body = vec![0; length];
+    stdout.read_exact(&mut body).unwrap();
+    serde_json::from_slice(&body).unwrap()
+}
+
+/// Read messages until the one matching `predicate`.
+fn receive_until(stdout: &mut BufReader<ChildStdout>, predicate: impl Fn(&Value) -> bool) -> Value {
+    loop {
+        let message = receive(stdout);
+        if predicate(&message) {
+            return message;
+        }
+    }
+}
+
+#[test]
+fn test_lsp_publishes_diagnostics_and_hover() {
+    let root = std::env::temp_dir().join("phanalist-test-lsp");
+    let _ = fs::remove_dir_all(&root);
+    fs::create_dir_all(root.join("src")).unwrap();
+    let root = fs::canonicalize(root).unwrap();
+    let path = root.join("src").join("Service.php");
+    let content = "<?php\n\nnamespace App;\n\nclass Service\n{\n    public function run(): void\n    {\n        @$this->fetch();\n    }\n}\n";
+    fs::write(&path, content).unwrap();
+    let root_uri = format!("file://{}", root.display());
+    let uri = format!("file://{}", path.display());
+
+    let mut server = Command::new(env!("CARGO_BIN_EXE_phanalist"))
+        .arg("lsp")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = server.stdin.take().unwrap();
+    let mut stdout = BufReader::new(server.stdout.take().unwrap());
+
+    send(
+        &mut stdin,
+        json!({"jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {"rootUri": root_uri, "capabilities": {}}}),
+    );
+    let initialized = receive_until(&mut stdout, |m| m["id"] == 1);
+    assert_eq!(initialized["result"]["capabilities"]["hoverProvider"], true);
+    send(
+        &mut stdin,
+        json!({"jsonrpc": "2.0", "method": "initialized", "params": {}}),
+    );
+
+    send(
+        &mut stdin,
+        json!({"jsonrpc": "2.0", "method": "textDocument/didOpen", "params": {"textDocument": {
+            "uri": uri, "languageId": "php", "version": 1, "text": content
+        }}}),
+    );
+    let published = receive_until(&mut stdout, |m| {
+        m["method"] == "textDocument/publishDiagnostics"
+    });
+    let diagnostics = published["params"]["diagnostics"].as_array().unwrap();
+    let suppression = diagnostics
+        .iter()
+        .find(|d| d["code"] == "E0011")
+        .expect("E0011 diagnostic");
+    assert_eq!(
+        suppression["range"]["start"],
+        json!({"line": 8, "character": 8})
+    );
+    assert_eq!(suppression["source"], "phanalist");
+
+    send(
+        &mut stdin,
+        json!({"jsonrpc": "2.0", "id": 2, "method": "textDocument/hover", "params": {
+            "textDocument": {"uri": uri}, "position": {"line": 8, "character": 10}
+        }}),
+    );
+    let hover = receive_until(&mut stdout, |m| m["id"] == 2);
+    assert_eq!(hover["result"]["contents"]["kind"], "markdown");
+
+    let fixed = content.replace('@', "");
+    send(
+        &mut stdin,
+        json!({"jsonrpc": "2.0", "method": "textDocument/didChange", "params": {
+            "textDocument": {"uri": uri, "version": 2}, "contentChanges": [{"text": fixed}]
+        }}),
+    );
+    let published = receive_until(&mut stdout, |m| {
+        m["method"] == "textDocument/publishDiagnostics"
+    });
+    let diagnostics = published["params"]["diagnostics"].as_array().unwrap();
+    assert!(diagnostics.iter().all(|d| d["code"] != "E0011"));
+
+    send(
+        &mut stdin,
+        json!({"jsonrpc": "2.0", "id": 3, "method": "shutdown"}),
+    );
+    receive_until(&mut stdout, |m| m["id"] == 3);
+    send(&mut stdin, json!({"jsonrpc": "2.0", "method": "exit"}));
+
+    assert!(server.wait().unwrap().success());
+    fs::remove_dir_all(root).unwrap();
+}